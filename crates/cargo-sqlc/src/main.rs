@@ -5,20 +5,26 @@
 //! mirroring the source directory structure.
 
 use clap::{Parser, ValueEnum};
+use sql_composer::codegen::{Driver, RustModule};
 use sql_composer::composer::Composer;
 use sql_composer::error::Error as ComposeError;
 use sql_composer::parser;
-use sql_composer::types::{Dialect, TemplateSource};
+use sql_composer::types::{CommentStyle, Dialect, PlaceholderOrdering};
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use walkdir::WalkDir;
 
+mod watch;
+
 #[derive(Debug, Clone, ValueEnum)]
 enum DialectArg {
     Postgres,
     Mysql,
     Sqlite,
+    SqlServer,
+    Oracle,
+    Clickhouse,
 }
 
 impl From<DialectArg> for Dialect {
@@ -27,6 +33,75 @@ impl From<DialectArg> for Dialect {
             DialectArg::Postgres => Dialect::Postgres,
             DialectArg::Mysql => Dialect::Mysql,
             DialectArg::Sqlite => Dialect::Sqlite,
+            DialectArg::SqlServer => Dialect::SqlServer,
+            DialectArg::Oracle => Dialect::Oracle,
+            DialectArg::Clickhouse => Dialect::ClickHouse,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum CommentsArg {
+    /// `#` anywhere outside quoted SQL.
+    Hash,
+    /// `#` as the first non-blank character of a line.
+    LineHash,
+    /// `##` anywhere outside quoted SQL.
+    DoubleHash,
+    /// `--#` anywhere outside quoted SQL.
+    DashHash,
+}
+
+impl From<CommentsArg> for CommentStyle {
+    fn from(c: CommentsArg) -> Self {
+        match c {
+            CommentsArg::Hash => CommentStyle::Hash,
+            CommentsArg::LineHash => CommentStyle::LineHash,
+            CommentsArg::DoubleHash => CommentStyle::DoubleHash,
+            CommentsArg::DashHash => CommentStyle::DashHash,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum OrderingArg {
+    /// Binding names sorted alphabetically.
+    Alphabetical,
+    /// Binding names in the order they first appear.
+    FirstAppearance,
+    /// The order declared by `:bind_order()`, then first appearance.
+    Explicit,
+}
+
+impl From<OrderingArg> for PlaceholderOrdering {
+    fn from(o: OrderingArg) -> Self {
+        match o {
+            OrderingArg::Alphabetical => PlaceholderOrdering::Alphabetical,
+            OrderingArg::FirstAppearance => PlaceholderOrdering::FirstAppearance,
+            OrderingArg::Explicit => PlaceholderOrdering::Explicit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum DriverArg {
+    Rusqlite,
+    Postgres,
+    PostgresSync,
+    Mysql,
+    MysqlSync,
+    Duckdb,
+}
+
+impl From<DriverArg> for Driver {
+    fn from(d: DriverArg) -> Self {
+        match d {
+            DriverArg::Rusqlite => Driver::Rusqlite,
+            DriverArg::Postgres => Driver::Postgres,
+            DriverArg::PostgresSync => Driver::PostgresSync,
+            DriverArg::Mysql => Driver::Mysql,
+            DriverArg::MysqlSync => Driver::MysqlSync,
+            DriverArg::Duckdb => Driver::Duckdb,
         }
     }
 }
@@ -52,6 +127,12 @@ struct SqlcArgs {
 enum SqlcCommand {
     /// Compose template files into output SQL files.
     Compose(ComposeArgs),
+    /// Watch the source directory and recompose templates as they change.
+    Watch(ComposeArgs),
+    /// Generate a Rust module with one typed struct per template.
+    Codegen(CodegenArgs),
+    /// List templates whose meaning changes under a new comment style.
+    CheckComments(CheckCommentsArgs),
 }
 
 #[derive(Debug, Parser)]
@@ -70,6 +151,16 @@ struct ComposeArgs {
     #[arg(long, default_value = "postgres")]
     dialect: DialectArg,
 
+    /// Which text starts a template comment.
+    /// Falls back to SQLC_COMMENTS env var, then "hash".
+    #[arg(long, env = "SQLC_COMMENTS", default_value = "hash")]
+    comments: CommentsArg,
+
+    /// How numbered placeholders (`$1`, `?1`) are assigned to binding names.
+    /// Falls back to SQLC_ORDERING env var, then "alphabetical".
+    #[arg(long, env = "SQLC_ORDERING", default_value = "alphabetical")]
+    ordering: OrderingArg,
+
     /// Skip running `cargo sqlx prepare` after composing.
     #[arg(long)]
     skip_prepare: bool,
@@ -80,6 +171,58 @@ struct ComposeArgs {
     verify: bool,
 }
 
+#[derive(Debug, Parser)]
+struct CodegenArgs {
+    /// Source directory containing .sqlc template files.
+    /// Falls back to SQLC_SOURCE_DIR env var, then "sqlc".
+    #[arg(long, env = "SQLC_SOURCE_DIR", default_value = "sqlc")]
+    source: PathBuf,
+
+    /// Rust file to write, e.g. `src/queries.rs`.
+    #[arg(long)]
+    output: PathBuf,
+
+    /// Target database dialect for placeholder syntax.
+    #[arg(long, default_value = "postgres")]
+    dialect: DialectArg,
+
+    /// Driver crate whose bind value type the generated code uses.
+    #[arg(long)]
+    driver: DriverArg,
+
+    /// Which text starts a template comment.
+    /// Falls back to SQLC_COMMENTS env var, then "hash".
+    #[arg(long, env = "SQLC_COMMENTS", default_value = "hash")]
+    comments: CommentsArg,
+
+    /// How numbered placeholders (`$1`, `?1`) are assigned to binding names.
+    /// Falls back to SQLC_ORDERING env var, then "alphabetical".
+    #[arg(long, env = "SQLC_ORDERING", default_value = "alphabetical")]
+    ordering: OrderingArg,
+
+    /// Verify that the generated module matches the existing output file.
+    /// Exits with code 1 if it differs or is missing.
+    #[arg(long)]
+    verify: bool,
+}
+
+#[derive(Debug, Parser)]
+struct CheckCommentsArgs {
+    /// Source directory containing .sqlc template files.
+    /// Falls back to SQLC_SOURCE_DIR env var, then "sqlc".
+    #[arg(long, env = "SQLC_SOURCE_DIR", default_value = "sqlc")]
+    source: PathBuf,
+
+    /// The comment style the templates are written for.
+    #[arg(long, default_value = "hash")]
+    from: CommentsArg,
+
+    /// The comment style to switch to.
+    /// Falls back to SQLC_COMMENTS env var.
+    #[arg(long, env = "SQLC_COMMENTS")]
+    comments: CommentsArg,
+}
+
 fn main() {
     let Cli::Sqlc(args) = Cli::parse();
 
@@ -90,6 +233,24 @@ fn main() {
                 std::process::exit(1);
             }
         }
+        SqlcCommand::Watch(watch_args) => {
+            if let Err(e) = watch::run_watch(&watch_args) {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+        SqlcCommand::Codegen(codegen_args) => {
+            if let Err(e) = run_codegen(&codegen_args) {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+        SqlcCommand::CheckComments(check_args) => {
+            if let Err(e) = run_check_comments(&check_args) {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
     }
 }
 
@@ -98,9 +259,13 @@ fn main() {
 fn compose_all(
     source_dir: &Path,
     dialect: Dialect,
+    comments: CommentStyle,
+    ordering: PlaceholderOrdering,
 ) -> Result<BTreeMap<PathBuf, String>, Box<dyn std::error::Error>> {
     let mut composer = Composer::new(dialect);
     composer.add_search_path(source_dir.to_path_buf());
+    composer.set_comments(comments);
+    composer.ordering = ordering;
 
     let mut results = BTreeMap::new();
 
@@ -118,24 +283,27 @@ fn compose_all(
 
         let output_rel = rel_path.with_extension("sql");
 
-        let content = std::fs::read_to_string(path)?;
-        let template = parser::parse_template(&content, TemplateSource::File(path.to_path_buf()))?;
-        let result = match composer.compose(&template) {
-            Ok(r) => r,
-            Err(ComposeError::MissingSlot { .. }) => {
-                // Template has unfilled slots — it's a shared template meant
-                // to be composed by callers, not standalone. Skip it.
-                continue;
-            }
-            Err(e) => return Err(e.into()),
-        };
-
-        results.insert(output_rel, result.sql);
+        if let Some(sql) = compose_file(&composer, path)? {
+            results.insert(output_rel, sql);
+        }
     }
 
     Ok(results)
 }
 
+/// Compose a single `.sqlc` file through `composer`'s template cache.
+///
+/// Returns `Ok(None)` for a template with unfilled slots — it's a shared
+/// template meant to be composed by callers, not standalone.
+fn compose_file(composer: &Composer, path: &Path) -> Result<Option<String>, ComposeError> {
+    let template = composer.template(path)?;
+    match composer.compose(&template) {
+        Ok(result) => Ok(Some(result.sql)),
+        Err(e) if matches!(e.root(), ComposeError::MissingSlot { .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
 fn run_compose(args: &ComposeArgs) -> Result<(), Box<dyn std::error::Error>> {
     let source_dir = &args.source;
     let target_dir = &args.target;
@@ -145,7 +313,12 @@ fn run_compose(args: &ComposeArgs) -> Result<(), Box<dyn std::error::Error>> {
         return Err(format!("Source directory does not exist: {}", source_dir.display()).into());
     }
 
-    let composed = compose_all(source_dir, dialect)?;
+    let composed = compose_all(
+        source_dir,
+        dialect,
+        args.comments.clone().into(),
+        args.ordering.clone().into(),
+    )?;
 
     if composed.is_empty() {
         println!("No .sqlc files found in {}", source_dir.display());
@@ -156,12 +329,135 @@ fn run_compose(args: &ComposeArgs) -> Result<(), Box<dyn std::error::Error>> {
         return run_verify(&composed, target_dir);
     }
 
-    // Write to a temp directory first, then swap into place.
+    write_all(&composed, source_dir, target_dir)?;
+
+    if !args.skip_prepare {
+        run_sqlx_prepare()?;
+    }
+
+    Ok(())
+}
+
+fn run_codegen(args: &CodegenArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let source_dir = &args.source;
+    if !source_dir.exists() {
+        return Err(format!("Source directory does not exist: {}", source_dir.display()).into());
+    }
+
+    let mut composer = Composer::new(Dialect::from(args.dialect.clone()));
+    composer.add_search_path(source_dir.to_path_buf());
+    composer.set_comments(args.comments.clone().into());
+    composer.ordering = args.ordering.clone().into();
+    let mut module = RustModule::new(args.driver.clone().into());
+
+    for entry in WalkDir::new(source_dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+
+        if !path.is_file() || path.extension().is_some_and(|ext| ext != "sqlc") {
+            continue;
+        }
+
+        let rel_path = path
+            .strip_prefix(source_dir)
+            .expect("walkdir entry must be under source_dir");
+
+        // Shared templates with unfilled slots have no struct of their own.
+        let template = composer.template(path)?;
+        match module.add(&composer, rel_path, &template) {
+            Ok(()) => {}
+            Err(e) if matches!(e.root(), ComposeError::MissingSlot { .. }) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let source = module.to_source();
+    let output = &args.output;
+    let rel_path = Path::new(output.file_name().unwrap_or_default());
+
+    if args.verify {
+        let composed = BTreeMap::from([(rel_path.to_path_buf(), source)]);
+        let dir = output.parent().unwrap_or(Path::new("."));
+        let mismatches = check_outputs(&composed, dir);
+        if mismatches.is_empty() {
+            println!("Verify OK: {} matches", output.display());
+            return Ok(());
+        }
+        eprintln!("Verify failed:");
+        for m in &mismatches {
+            eprintln!("  {m}");
+        }
+        return Err("generated module out of sync — run `cargo sqlc codegen` to update".into());
+    }
+
+    if let Some(dir) = output.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(output, source)?;
+    println!(
+        "Generated {} struct(s) into {}",
+        module.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn run_check_comments(args: &CheckCommentsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let source_dir = &args.source;
+    if !source_dir.exists() {
+        return Err(format!("Source directory does not exist: {}", source_dir.display()).into());
+    }
+
+    let from: CommentStyle = args.from.clone().into();
+    let to: CommentStyle = args.comments.clone().into();
+    let mut changed = Vec::new();
+    let mut checked = 0;
+
+    for entry in WalkDir::new(source_dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+
+        if !path.is_file() || path.extension().is_some_and(|ext| ext != "sqlc") {
+            continue;
+        }
+
+        checked += 1;
+        let content = std::fs::read_to_string(path)?;
+        if let Some(line) = parser::comment_style_change(&content, from, to) {
+            changed.push(format!("{}:{line}", path.display()));
+        }
+    }
+
+    if changed.is_empty() {
+        println!("Comments OK: all {checked} file(s) parse the same");
+        return Ok(());
+    }
+
+    eprintln!("Meaning changes under the new comment style:");
+    for c in &changed {
+        eprintln!("  {c}");
+    }
+    Err(format!(
+        "{} file(s) would change — rewrite their comments before switching",
+        changed.len()
+    )
+    .into())
+}
+
+/// Write every composed file into a fresh copy of `target_dir`.
+///
+/// Files are written to a temp directory first, which replaces `target_dir`
+/// only once all writes have succeeded.
+fn write_all(
+    composed: &BTreeMap<PathBuf, String>,
+    source_dir: &Path,
+    target_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
     let parent = target_dir.parent().unwrap_or(Path::new("."));
     std::fs::create_dir_all(parent)?;
     let tmp_dir = tempfile::tempdir_in(parent)?;
 
-    for (rel_path, sql) in &composed {
+    for (rel_path, sql) in composed {
         let out_path = tmp_dir.path().join(rel_path);
 
         if let Some(dir) = out_path.parent() {
@@ -186,11 +482,6 @@ fn run_compose(args: &ComposeArgs) -> Result<(), Box<dyn std::error::Error>> {
     std::fs::rename(&tmp_path, target_dir)?;
 
     println!("Composed {} template(s) into {}", composed.len(), target_dir.display());
-
-    if !args.skip_prepare {
-        run_sqlx_prepare()?;
-    }
-
     Ok(())
 }
 
@@ -198,23 +489,7 @@ fn run_verify(
     composed: &BTreeMap<PathBuf, String>,
     target_dir: &Path,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut mismatches = Vec::new();
-
-    // Check each composed file against the existing target.
-    for (rel_path, expected_sql) in composed {
-        let target_path = target_dir.join(rel_path);
-
-        match std::fs::read_to_string(&target_path) {
-            Ok(existing) if existing == *expected_sql => {}
-            Ok(existing) => {
-                mismatches.push(format!("CHANGED: {}", rel_path.display()));
-                print_diff(rel_path, &existing, expected_sql);
-            }
-            Err(_) => {
-                mismatches.push(format!("MISSING: {}", rel_path.display()));
-            }
-        }
-    }
+    let mut mismatches = check_outputs(composed, target_dir);
 
     // Check for stale files in the target that have no corresponding source.
     if target_dir.exists() {
@@ -252,6 +527,30 @@ fn run_verify(
     }
 }
 
+/// Compare each composed file against the existing target, printing a diff
+/// for every changed file and returning a `CHANGED:`/`MISSING:` line for each
+/// mismatch.
+fn check_outputs(composed: &BTreeMap<PathBuf, String>, target_dir: &Path) -> Vec<String> {
+    let mut mismatches = Vec::new();
+
+    for (rel_path, expected_sql) in composed {
+        let target_path = target_dir.join(rel_path);
+
+        match std::fs::read_to_string(&target_path) {
+            Ok(existing) if existing == *expected_sql => {}
+            Ok(existing) => {
+                mismatches.push(format!("CHANGED: {}", rel_path.display()));
+                print_diff(rel_path, &existing, expected_sql);
+            }
+            Err(_) => {
+                mismatches.push(format!("MISSING: {}", rel_path.display()));
+            }
+        }
+    }
+
+    mismatches
+}
+
 /// Print a simple line-by-line diff between existing and expected content.
 fn print_diff(rel_path: &Path, existing: &str, expected: &str) {
     eprintln!("--- {} (target)", rel_path.display());
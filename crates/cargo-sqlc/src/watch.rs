@@ -0,0 +1,351 @@
+//! `cargo sqlc watch` — recompose templates as the source tree changes.
+//!
+//! After an initial full compose, every change to a `.sqlc` file recomposes
+//! only the templates whose dependency closure includes it. Compose errors are
+//! reported inline and the watcher keeps running.
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use sql_composer::composer::Composer;
+use sql_composer::types::Dialect;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+use walkdir::WalkDir;
+
+use crate::{check_outputs, compose_file, run_sqlx_prepare, run_verify, write_all, ComposeArgs};
+
+/// How long to keep collecting events after the first one of a burst, so an
+/// editor's save (often several events) triggers a single recompose.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+pub(crate) fn run_watch(args: &ComposeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if !args.source.exists() {
+        return Err(format!("Source directory does not exist: {}", args.source.display()).into());
+    }
+
+    let mut state = WatchState::new(args)?;
+    state.compose_initial();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(&state.source_dir, RecursiveMode::Recursive)?;
+    println!("Watching {} for changes...", args.source.display());
+
+    while let Ok(event) = rx.recv() {
+        let mut changed = BTreeSet::new();
+        collect_changes(event, &mut changed);
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            collect_changes(event, &mut changed);
+        }
+
+        if !changed.is_empty() {
+            state.recompose(&changed);
+        }
+    }
+
+    Ok(())
+}
+
+/// Add the `.sqlc` paths touched by `event` to `changed`.
+fn collect_changes(event: notify::Result<Event>, changed: &mut BTreeSet<PathBuf>) {
+    match event {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+            changed.extend(
+                event
+                    .paths
+                    .into_iter()
+                    .filter(|p| p.extension().is_some_and(|ext| ext == "sqlc")),
+            );
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Watch error: {e}"),
+    }
+}
+
+struct WatchState<'a> {
+    args: &'a ComposeArgs,
+    /// Canonical source directory; watcher events and cache keys share it.
+    source_dir: PathBuf,
+    composer: Composer,
+    /// Templates whose last compose failed. They are retried on every change,
+    /// since the fix may be a file they could not resolve before.
+    failed: BTreeSet<PathBuf>,
+}
+
+impl<'a> WatchState<'a> {
+    fn new(args: &'a ComposeArgs) -> Result<Self, Box<dyn std::error::Error>> {
+        let source_dir = args.source.canonicalize()?;
+        let dialect: Dialect = args.dialect.clone().into();
+        let mut composer = Composer::new(dialect);
+        composer.add_search_path(source_dir.clone());
+        composer.set_comments(args.comments.clone().into());
+        composer.ordering = args.ordering.clone().into();
+
+        Ok(Self {
+            args,
+            source_dir,
+            composer,
+            failed: BTreeSet::new(),
+        })
+    }
+
+    /// Compose every template, like `cargo sqlc compose`.
+    ///
+    /// When every template composes, the target directory is replaced (or
+    /// verified) as a whole. Otherwise the errors are reported and the
+    /// templates that did compose are written individually.
+    fn compose_initial(&mut self) {
+        let mut paths = BTreeSet::new();
+        for entry in WalkDir::new(&self.source_dir) {
+            match entry {
+                Ok(entry) if entry.path().extension().is_some_and(|ext| ext == "sqlc") => {
+                    paths.insert(entry.into_path());
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Error: {e}"),
+            }
+        }
+
+        let outputs = self.compose_paths(&paths);
+        if !self.failed.is_empty() {
+            self.apply(&outputs);
+            return;
+        }
+
+        let composed: BTreeMap<PathBuf, String> = outputs
+            .into_iter()
+            .filter_map(|(rel_path, sql)| Some((rel_path, sql?)))
+            .collect();
+
+        let result = if self.args.verify {
+            run_verify(&composed, &self.args.target)
+        } else {
+            write_all(&composed, &self.args.source, &self.args.target).and_then(|()| {
+                if self.args.skip_prepare {
+                    Ok(())
+                } else {
+                    run_sqlx_prepare()
+                }
+            })
+        };
+
+        if let Err(e) = result {
+            eprintln!("Error: {e}");
+        }
+    }
+
+    /// Recompose the templates affected by a change to `changed`.
+    fn recompose(&mut self, changed: &BTreeSet<PathBuf>) {
+        let mut affected: BTreeSet<PathBuf> = changed.clone();
+        affected.extend(self.failed.iter().cloned());
+
+        match self.composer.dependency_graph() {
+            Ok(graph) => {
+                for template in graph.templates() {
+                    let closure = graph.closure(template);
+                    if changed.iter().any(|path| closure.contains(path)) {
+                        affected.insert(template.to_path_buf());
+                    }
+                }
+            }
+            Err(e) => eprintln!("Error: {e}"),
+        }
+
+        for path in changed {
+            self.composer.templates.invalidate(path);
+        }
+
+        let outputs = self.compose_paths(&affected);
+        self.apply(&outputs);
+    }
+
+    /// Compose each of `paths`, reporting errors inline.
+    ///
+    /// Returns the output path (relative to the target directory) of every
+    /// template under the source directory, with `None` for templates that
+    /// no longer produce output: deleted files and shared templates with
+    /// unfilled slots.
+    fn compose_paths(&mut self, paths: &BTreeSet<PathBuf>) -> BTreeMap<PathBuf, Option<String>> {
+        let mut outputs = BTreeMap::new();
+
+        for path in paths {
+            let Ok(rel_path) = path.strip_prefix(&self.source_dir) else {
+                continue;
+            };
+
+            self.failed.remove(path);
+            let sql = if path.is_file() {
+                match compose_file(&self.composer, path) {
+                    Ok(sql) => sql,
+                    Err(e) => {
+                        eprintln!("Error: {e}");
+                        self.failed.insert(path.clone());
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+
+            outputs.insert(rel_path.with_extension("sql"), sql);
+        }
+
+        outputs
+    }
+
+    /// Write (or, with `--verify`, check) individual outputs.
+    ///
+    /// Each file is written to a temp file beside its target and renamed
+    /// into place, so readers never see a partial file.
+    fn apply(&self, outputs: &BTreeMap<PathBuf, Option<String>>) {
+        if self.args.verify {
+            self.verify(outputs);
+            return;
+        }
+
+        let mut written = 0;
+        for (rel_path, sql) in outputs {
+            let result = match sql {
+                Some(sql) => write_output(&self.args.target, rel_path, sql),
+                None => remove_output(&self.args.target, rel_path),
+            };
+
+            match result {
+                Ok(false) => {}
+                Ok(true) => {
+                    written += 1;
+                    let source = self.args.source.join(rel_path.with_extension("sqlc"));
+                    match sql {
+                        Some(_) => println!("Composed {}", source.display()),
+                        None => println!("Removed output of {}", source.display()),
+                    }
+                }
+                Err(e) => eprintln!("Error: {}: {e}", rel_path.display()),
+            }
+        }
+
+        if written > 0 && !self.args.skip_prepare {
+            if let Err(e) = run_sqlx_prepare() {
+                eprintln!("Error: {e}");
+            }
+        }
+    }
+
+    fn verify(&self, outputs: &BTreeMap<PathBuf, Option<String>>) {
+        let composed: BTreeMap<PathBuf, String> = outputs
+            .iter()
+            .filter_map(|(rel_path, sql)| Some((rel_path.clone(), sql.clone()?)))
+            .collect();
+
+        let mut mismatches = check_outputs(&composed, &self.args.target);
+        for (rel_path, sql) in outputs {
+            if sql.is_none() && self.args.target.join(rel_path).exists() {
+                mismatches.push(format!("STALE: {}", rel_path.display()));
+            }
+        }
+
+        if mismatches.is_empty() {
+            println!("Verify OK: {} file(s) match", composed.len());
+        } else {
+            eprintln!("Verify failed:");
+            for m in &mismatches {
+                eprintln!("  {m}");
+            }
+        }
+    }
+}
+
+/// Atomically replace `target_dir/rel_path` with `sql`.
+///
+/// Returns `false` without touching the file when it already holds `sql`.
+fn write_output(target_dir: &Path, rel_path: &Path, sql: &str) -> std::io::Result<bool> {
+    let out_path = target_dir.join(rel_path);
+    if std::fs::read_to_string(&out_path).is_ok_and(|existing| existing == sql) {
+        return Ok(false);
+    }
+
+    let dir = out_path.parent().unwrap_or(target_dir);
+    std::fs::create_dir_all(dir)?;
+
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    std::io::Write::write_all(&mut tmp, sql.as_bytes())?;
+    tmp.persist(&out_path)?;
+    Ok(true)
+}
+
+/// Remove `target_dir/rel_path`, returning `false` if it did not exist.
+fn remove_output(target_dir: &Path, rel_path: &Path) -> std::io::Result<bool> {
+    match std::fs::remove_file(target_dir.join(rel_path)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, name: &str, content: &str) {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn read(dir: &Path, name: &str) -> String {
+        std::fs::read_to_string(dir.join(name)).unwrap()
+    }
+
+    #[test]
+    fn test_recompose_only_dependents() {
+        let source = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        write(source.path(), "shared/where.sqlc", "WHERE id = :bind(id)");
+        write(
+            source.path(),
+            "a.sqlc",
+            "SELECT * FROM a :compose(shared/where.sqlc)",
+        );
+        write(
+            source.path(),
+            "b.sqlc",
+            "SELECT * FROM b :compose(shared/where.sqlc)",
+        );
+        write(source.path(), "c.sqlc", "SELECT * FROM c");
+
+        let args = ComposeArgs::parse_from([
+            "compose".as_ref(),
+            "--source".as_ref(),
+            source.path().as_os_str(),
+            "--target".as_ref(),
+            target.path().as_os_str(),
+            "--skip-prepare".as_ref(),
+        ]);
+        let mut state = WatchState::new(&args).unwrap();
+        state.compose_initial();
+        assert_eq!(
+            read(target.path(), "a.sql"),
+            "SELECT * FROM a WHERE id = $1"
+        );
+
+        // A recomposed c.sql would lose the marker.
+        write(target.path(), "c.sql", "-- untouched");
+        write(source.path(), "shared/where.sqlc", "WHERE key = :bind(id)");
+        let changed = BTreeSet::from([state.source_dir.join("shared/where.sqlc")]);
+        state.recompose(&changed);
+
+        assert_eq!(
+            read(target.path(), "a.sql"),
+            "SELECT * FROM a WHERE key = $1"
+        );
+        assert_eq!(
+            read(target.path(), "b.sql"),
+            "SELECT * FROM b WHERE key = $1"
+        );
+        assert_eq!(read(target.path(), "shared/where.sql"), "WHERE key = $1");
+        assert_eq!(read(target.path(), "c.sql"), "-- untouched");
+    }
+}
@@ -34,8 +34,9 @@ pub use duckdb;
 use std::collections::BTreeMap;
 use std::ops::{Deref, DerefMut};
 
-use sql_composer::composer::Composer;
-use sql_composer::driver::{self, ComposerConnection};
+use duckdb::types::{ToSqlOutput, Type};
+use sql_composer::composer::{ComposedChunk, Composer};
+use sql_composer::driver::{self, ComposerConnection, ValueKind};
 use sql_composer::types::Template;
 
 /// Error type for sql-composer-duckdb operations.
@@ -71,6 +72,59 @@ impl DuckDbConnection {
     pub fn from_connection(conn: duckdb::Connection) -> Self {
         Self(conn)
     }
+
+    /// Run a query once per statement from [`Composer::compose_chunked()`],
+    /// mapping every row with `f` and concatenating the results in chunk
+    /// order.
+    pub fn query_chunked<T, F>(
+        &self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<Box<dyn duckdb::ToSql>>>,
+        mut f: F,
+    ) -> Result<Vec<T>, Error>
+    where
+        F: FnMut(&duckdb::Row<'_>) -> duckdb::Result<T>,
+    {
+        let mut results = Vec::new();
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = chunk_params(&chunk, values)?;
+            let mut stmt = self.prepare(&chunk.composed.sql)?;
+            let rows = stmt.query_map(params.as_slice(), &mut f)?;
+            for row in rows {
+                results.push(row?);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Execute a statement once per chunk from
+    /// [`Composer::compose_chunked()`], returning the total number of rows
+    /// changed. Chunks run one after another; wrap the call in a
+    /// transaction to apply them atomically.
+    pub fn execute_chunked(
+        &self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<Box<dyn duckdb::ToSql>>>,
+    ) -> Result<usize, Error> {
+        let mut changed = 0;
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = chunk_params(&chunk, values)?;
+            changed += self.execute(&chunk.composed.sql, params.as_slice())?;
+        }
+        Ok(changed)
+    }
+}
+
+/// The checked values one chunk binds, in placeholder order.
+fn chunk_params<'v>(
+    chunk: &ComposedChunk,
+    values: &'v BTreeMap<String, Vec<Box<dyn duckdb::ToSql>>>,
+) -> Result<Vec<&'v dyn duckdb::ToSql>, Error> {
+    let ordered = driver::resolve_chunk_values(chunk, values)?;
+    driver::check_value_types(&chunk.composed, &ordered, |v| value_kind(v.as_ref()))?;
+    Ok(ordered.into_iter().map(|v| v.as_ref()).collect())
 }
 
 impl Deref for DuckDbConnection {
@@ -100,10 +154,46 @@ impl ComposerConnection for DuckDbConnection {
     ) -> Result<(String, Vec<Self::Value>), Error> {
         let composed = composer.compose_with_values(template, &values)?;
         let ordered = driver::resolve_values(&composed, &mut values)?;
+        driver::check_value_types(&composed, &ordered, |v| value_kind(v.as_ref()))?;
         Ok((composed.sql, ordered))
     }
 }
 
+/// Classify a value by the DuckDB type it binds as.
+fn value_kind(value: &dyn duckdb::ToSql) -> ValueKind {
+    let Ok(output) = value.to_sql() else {
+        return ValueKind::Unknown;
+    };
+    let data_type = if let ToSqlOutput::Borrowed(value_ref) = &output {
+        value_ref.data_type()
+    } else if let ToSqlOutput::Owned(value) = &output {
+        value.data_type()
+    } else {
+        return ValueKind::Unknown;
+    };
+    match data_type {
+        Type::Null => ValueKind::Null,
+        Type::Boolean => ValueKind::Bool,
+        Type::TinyInt
+        | Type::SmallInt
+        | Type::Int
+        | Type::BigInt
+        | Type::HugeInt
+        | Type::UTinyInt
+        | Type::USmallInt
+        | Type::UInt
+        | Type::UBigInt => ValueKind::Integer,
+        Type::Float | Type::Double => ValueKind::Float,
+        Type::Decimal => ValueKind::Decimal,
+        Type::Text => ValueKind::Text,
+        Type::Blob => ValueKind::Bytes,
+        Type::Date32 => ValueKind::Date,
+        Type::Time64 => ValueKind::Time,
+        Type::Timestamp => ValueKind::Timestamp,
+        _ => ValueKind::Unknown,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -204,4 +294,26 @@ mod tests {
         assert_eq!(sql, "SELECT $1 AS col_1, $2 AS col_2");
         assert_eq!(params.len(), 2);
     }
+
+    #[test]
+    fn test_typed_bind_checks_values() {
+        let conn = DuckDbConnection::open_in_memory().unwrap();
+
+        let input = "SELECT :bind(id AS int8) AS id";
+        let template = parse_template(input, TemplateSource::Literal("test".into())).unwrap();
+        let composer = Composer::new(Dialect::Postgres);
+
+        let values = bind_values!("id" => [boxed(7i64)]);
+        let (sql, _) = conn.compose(&composer, &template, values).unwrap();
+        assert_eq!(sql, "SELECT $1::int8 AS id");
+
+        let values = bind_values!("id" => [boxed("seven")]);
+        let result = conn.compose(&composer, &template, values);
+        assert!(matches!(
+            result,
+            Err(Error::Composer(
+                sql_composer::Error::BindTypeMismatch { .. }
+            ))
+        ));
+    }
 }
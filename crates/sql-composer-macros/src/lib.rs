@@ -0,0 +1,233 @@
+//! Compile-time template composition for sql-composer.
+//!
+//! [`sqlc_file!()`] parses and composes a `.sqlc` template while the crate
+//! compiles, so the composed SQL ships in the binary without a separate
+//! `cargo sqlc compose` step.
+//!
+//! ```ignore
+//! use sql_composer::codegen::StaticSql;
+//! use sql_composer_macros::sqlc_file;
+//!
+//! const SET_PARTS: StaticSql = sqlc_file!("sets/select_set_parts.sqlc", dialect = postgres);
+//!
+//! let mut values = sql_composer::bind_values!("set_num" => ["75192-1".to_string()]);
+//! let ordered = SET_PARTS.resolve_values(&mut values)?;
+//! let rows = client.query(SET_PARTS.sql, &params(&ordered)).await?;
+//! ```
+//!
+//! Template paths, including `:compose()` targets, resolve against the
+//! crate's `sqlc/` directory and then the crate root. Parse and compose
+//! errors become compile errors at the template path, and editing any
+//! template the macro read triggers a rebuild.
+
+use std::path::{Path, PathBuf};
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use sql_composer::composer::Composer;
+use sql_composer::types::{CommentStyle, Dialect};
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitStr, Token};
+
+/// Compose a `.sqlc` template at compile time.
+///
+/// Expands to a [`sql_composer::codegen::StaticSql`] holding the composed SQL
+/// as a `&'static str` and the bind parameter names in placeholder order.
+///
+/// ```ignore
+/// sqlc_file!("reports/x.sqlc")                    // Postgres
+/// sqlc_file!("reports/x.sqlc", dialect = sqlite)  // postgres, mysql, sqlite, sql_server, oracle or clickhouse
+/// sqlc_file!("reports/x.sqlc", comments = double_hash)
+/// ```
+///
+/// `comments` picks the template comment marker like `cargo sqlc --comments`:
+/// `hash` (the default), `line_hash`, `double_hash` or `dash_hash`.
+#[proc_macro]
+pub fn sqlc_file(input: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(input as SqlcFile);
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+
+    match expand(&args, &manifest_dir) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => syn::Error::new(args.path.span(), e)
+            .to_compile_error()
+            .into(),
+    }
+}
+
+/// The arguments of [`sqlc_file!()`]: a path and optional `key = value` options.
+struct SqlcFile {
+    path: LitStr,
+    dialect: Dialect,
+    comments: CommentStyle,
+}
+
+impl Parse for SqlcFile {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut dialect = Dialect::Postgres;
+        let mut comments = CommentStyle::default();
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "dialect" => {
+                    let value: Ident = input.parse()?;
+                    dialect = match value.to_string().as_str() {
+                        "postgres" => Dialect::Postgres,
+                        "mysql" => Dialect::Mysql,
+                        "sqlite" => Dialect::Sqlite,
+                        "sql_server" => Dialect::SqlServer,
+                        "oracle" => Dialect::Oracle,
+                        "clickhouse" => Dialect::ClickHouse,
+                        other => {
+                            return Err(syn::Error::new(
+                                value.span(),
+                                format!(
+                                    "unknown dialect `{other}`, expected `postgres`, `mysql`, \
+                                     `sqlite`, `sql_server`, `oracle` or `clickhouse`"
+                                ),
+                            ))
+                        }
+                    };
+                }
+                "comments" => {
+                    let value: Ident = input.parse()?;
+                    comments = match value.to_string().as_str() {
+                        "hash" => CommentStyle::Hash,
+                        "line_hash" => CommentStyle::LineHash,
+                        "double_hash" => CommentStyle::DoubleHash,
+                        "dash_hash" => CommentStyle::DashHash,
+                        other => {
+                            return Err(syn::Error::new(
+                                value.span(),
+                                format!(
+                                    "unknown comment style `{other}`, expected `hash`, `line_hash`, `double_hash` or `dash_hash`"
+                                ),
+                            ))
+                        }
+                    };
+                }
+                other => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("unknown option `{other}`, expected `dialect` or `comments`"),
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            path,
+            dialect,
+            comments,
+        })
+    }
+}
+
+/// Compose the template named by `args`, resolving paths under `manifest_dir`.
+fn expand(args: &SqlcFile, manifest_dir: &Path) -> sql_composer::error::Result<TokenStream2> {
+    let mut composer = Composer::new(args.dialect);
+    composer.add_search_path(manifest_dir.join("sqlc"));
+    composer.add_search_path(manifest_dir.to_path_buf());
+    composer.set_comments(args.comments);
+
+    let template = composer.template(Path::new(&args.path.value()))?;
+    let composed = composer.compose(&template)?;
+
+    // `include_bytes!` makes cargo rebuild the caller when a template changes.
+    let files: Vec<String> = composer
+        .dependency_graph()?
+        .templates()
+        .map(|path| {
+            std::fs::canonicalize(path)
+                .unwrap_or_else(|_| path.to_path_buf())
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+
+    let path = args.path.value();
+    let sql = composed.sql;
+    let bind_params = composed.bind_params;
+    Ok(quote! {{
+        #( const _: &[u8] = include_bytes!(#files); )*
+        ::sql_composer::codegen::StaticSql {
+            template: #path,
+            sql: #sql,
+            bind_params: &[#(#bind_params),*],
+        }
+    }})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, name: &str, content: &str) {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args: SqlcFile = syn::parse_str(r#""a.sqlc", dialect = sqlite,"#).unwrap();
+        assert_eq!(args.path.value(), "a.sqlc");
+        assert_eq!(args.dialect, Dialect::Sqlite);
+
+        let args: SqlcFile = syn::parse_str(r#""a.sqlc""#).unwrap();
+        assert_eq!(args.dialect, Dialect::Postgres);
+        assert_eq!(args.comments, CommentStyle::Hash);
+
+        let args: SqlcFile = syn::parse_str(r#""a.sqlc", comments = line_hash"#).unwrap();
+        assert_eq!(args.comments, CommentStyle::LineHash);
+
+        let args: SqlcFile = syn::parse_str(r#""a.sqlc", dialect = sql_server"#).unwrap();
+        assert_eq!(args.dialect, Dialect::SqlServer);
+
+        assert!(syn::parse_str::<SqlcFile>(r#""a.sqlc", dialect = db2"#).is_err());
+        assert!(syn::parse_str::<SqlcFile>(r#""a.sqlc", casts = off"#).is_err());
+    }
+
+    #[test]
+    fn test_expand_composes_from_sqlc_dir() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path(), "sqlc/shared/where.sqlc", "WHERE id = :bind(id)");
+        write(
+            dir.path(),
+            "sqlc/reports/x.sqlc",
+            "SELECT * FROM t :compose(shared/where.sqlc) AND a = :bind(a)",
+        );
+
+        let args: SqlcFile = syn::parse_str(r#""reports/x.sqlc", dialect = sqlite"#).unwrap();
+        let tokens = expand(&args, dir.path()).unwrap().to_string();
+
+        assert!(tokens.contains(r#""SELECT * FROM t WHERE id = ?2 AND a = ?1""#));
+        assert!(tokens.contains(r#"bind_params : & ["a" , "id"]"#));
+        assert_eq!(tokens.matches("include_bytes !").count(), 2);
+    }
+
+    #[test]
+    fn test_expand_reports_compose_errors() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path(), "sqlc/x.sqlc", "SELECT :compose(missing.sqlc)");
+
+        let args: SqlcFile = syn::parse_str(r#""x.sqlc""#).unwrap();
+        let err = expand(&args, dir.path()).unwrap_err();
+        assert!(matches!(
+            err.root(),
+            sql_composer::Error::TemplateNotFound { .. }
+        ));
+    }
+}
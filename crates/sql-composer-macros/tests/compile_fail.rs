@@ -0,0 +1,7 @@
+//! Checks that `sqlc_file!()` reports its errors at the template path.
+
+#[test]
+fn compile_fail() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,8 @@
+use sql_composer::codegen::StaticSql;
+use sql_composer_macros::sqlc_file;
+
+const MISSING: StaticSql = sqlc_file!("reports/missing.sqlc", dialect = sqlite);
+
+fn main() {
+    let _ = MISSING;
+}
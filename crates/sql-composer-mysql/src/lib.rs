@@ -59,9 +59,38 @@ use std::collections::BTreeMap;
 use std::ops::{Deref, DerefMut};
 
 use sql_composer::composer::Composer;
-use sql_composer::driver;
+use sql_composer::driver::{self, ValueKind};
 use sql_composer::types::Template;
 
+/// Define a function classifying a MySQL protocol value. `mysql` and
+/// `mysql_async` may depend on different `mysql_common` releases, so each
+/// gets its own copy.
+macro_rules! value_kind_fn {
+    ($name:ident, $value:ty) => {
+        /// Classify a value for [`driver::check_value_types`]. Bytes are
+        /// reported as text when they are valid UTF-8.
+        fn $name(value: &$value) -> ValueKind {
+            type Value = $value;
+            match value {
+                Value::NULL => ValueKind::Null,
+                Value::Bytes(bytes) if std::str::from_utf8(bytes).is_ok() => ValueKind::Text,
+                Value::Bytes(_) => ValueKind::Bytes,
+                Value::Int(_) | Value::UInt(_) => ValueKind::Integer,
+                Value::Float(_) | Value::Double(_) => ValueKind::Float,
+                Value::Date(_, _, _, 0, 0, 0, 0) => ValueKind::Date,
+                Value::Date(..) => ValueKind::Timestamp,
+                Value::Time(..) => ValueKind::Time,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "async")]
+value_kind_fn!(async_value_kind, mysql_async::Value);
+
+#[cfg(feature = "sync")]
+value_kind_fn!(sync_value_kind, mysql::Value);
+
 // ---------------------------------------------------------------------------
 // Async: MysqlConn (mysql_async)
 // ---------------------------------------------------------------------------
@@ -92,6 +121,58 @@ impl MysqlConn {
     pub fn from_conn(conn: mysql_async::Conn) -> Self {
         Self(conn)
     }
+
+    /// Run a query once per statement from [`Composer::compose_chunked()`]
+    /// and concatenate the rows in chunk order.
+    ///
+    /// Use it for lookups whose value lists can exceed the 65535 bind
+    /// parameters a MySQL prepared statement allows.
+    pub async fn query_chunked<T>(
+        &mut self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<mysql_async::Value>>,
+    ) -> Result<Vec<T>, AsyncError>
+    where
+        T: mysql_async::prelude::FromRow + Send + 'static,
+    {
+        use mysql_async::prelude::Queryable;
+
+        let mut rows = Vec::new();
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = driver::resolve_chunk_values(&chunk, values)?;
+            driver::check_value_types(&chunk.composed, &params, |v| async_value_kind(v))?;
+            let params: Vec<mysql_async::Value> = params.into_iter().cloned().collect();
+            let chunk_rows: Vec<T> = self.0.exec(chunk.composed.sql.as_str(), params).await?;
+            rows.extend(chunk_rows);
+        }
+        Ok(rows)
+    }
+
+    /// Execute a statement once per chunk from
+    /// [`Composer::compose_chunked()`], returning the total number of
+    /// affected rows. Chunks run one after another; run the call in a
+    /// transaction to apply them atomically.
+    pub async fn execute_chunked(
+        &mut self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<mysql_async::Value>>,
+    ) -> Result<u64, AsyncError> {
+        use mysql_async::prelude::Queryable;
+
+        let mut changed = 0;
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = driver::resolve_chunk_values(&chunk, values)?;
+            driver::check_value_types(&chunk.composed, &params, |v| async_value_kind(v))?;
+            let params: Vec<mysql_async::Value> = params.into_iter().cloned().collect();
+            self.0
+                .exec_drop(chunk.composed.sql.as_str(), params)
+                .await?;
+            changed += self.0.affected_rows();
+        }
+        Ok(changed)
+    }
 }
 
 #[cfg(feature = "async")]
@@ -124,6 +205,7 @@ impl driver::ComposerConnectionAsync for MysqlConn {
     ) -> Result<(String, Vec<Self::Value>), AsyncError> {
         let composed = composer.compose_with_values(template, &values)?;
         let ordered = driver::resolve_values(&composed, &mut values)?;
+        driver::check_value_types(&composed, &ordered, async_value_kind)?;
         Ok((composed.sql, ordered))
     }
 }
@@ -158,6 +240,52 @@ impl MysqlConnection {
     pub fn from_conn(conn: mysql::Conn) -> Self {
         Self(conn)
     }
+
+    /// Run a query once per statement from [`Composer::compose_chunked()`]
+    /// and concatenate the rows in chunk order.
+    pub fn query_chunked<T>(
+        &mut self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<mysql::Value>>,
+    ) -> Result<Vec<T>, SyncError>
+    where
+        T: mysql::prelude::FromRow,
+    {
+        use mysql::prelude::Queryable;
+
+        let mut rows = Vec::new();
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = driver::resolve_chunk_values(&chunk, values)?;
+            driver::check_value_types(&chunk.composed, &params, |v| sync_value_kind(v))?;
+            let params: Vec<mysql::Value> = params.into_iter().cloned().collect();
+            let chunk_rows: Vec<T> = self.0.exec(chunk.composed.sql.as_str(), params)?;
+            rows.extend(chunk_rows);
+        }
+        Ok(rows)
+    }
+
+    /// Execute a statement once per chunk from
+    /// [`Composer::compose_chunked()`], returning the total number of
+    /// affected rows.
+    pub fn execute_chunked(
+        &mut self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<mysql::Value>>,
+    ) -> Result<u64, SyncError> {
+        use mysql::prelude::Queryable;
+
+        let mut changed = 0;
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = driver::resolve_chunk_values(&chunk, values)?;
+            driver::check_value_types(&chunk.composed, &params, |v| sync_value_kind(v))?;
+            let params: Vec<mysql::Value> = params.into_iter().cloned().collect();
+            self.0.exec_drop(chunk.composed.sql.as_str(), params)?;
+            changed += self.0.affected_rows();
+        }
+        Ok(changed)
+    }
 }
 
 #[cfg(feature = "sync")]
@@ -190,6 +318,7 @@ impl driver::ComposerConnection for MysqlConnection {
     ) -> Result<(String, Vec<Self::Value>), SyncError> {
         let composed = composer.compose_with_values(template, &values)?;
         let ordered = driver::resolve_values(&composed, &mut values)?;
+        driver::check_value_types(&composed, &ordered, sync_value_kind)?;
         Ok((composed.sql, ordered))
     }
 }
@@ -239,4 +368,36 @@ mod tests {
         assert_eq!(result.sql, "SELECT * FROM users WHERE id IN (?, ?, ?)");
         assert_eq!(result.bind_params, vec!["ids", "ids", "ids"]);
     }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_check_value_types_mysql() {
+        use mysql_async::Value;
+        use sql_composer::driver;
+
+        let input =
+            "SELECT * FROM users WHERE id = :bind(id AS int8) AND name = :bind(name AS text)";
+        let template = parse_template(input, TemplateSource::Literal("test".into())).unwrap();
+        let composer = Composer::new(Dialect::Mysql);
+        let values = sql_composer::bind_values!(
+            "id" => [Value::Int(7)],
+            "name" => [Value::Bytes(b"Alice".to_vec())],
+        );
+        let result = composer.compose_with_values(&template, &values).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM users WHERE id = CAST(? AS SIGNED) AND name = CAST(? AS CHAR)"
+        );
+
+        let ordered = vec![Value::Int(7), Value::Bytes(b"Alice".to_vec())];
+        driver::check_value_types(&result, &ordered, super::async_value_kind).unwrap();
+
+        let ordered = vec![Value::Bytes(b"7".to_vec()), Value::Bytes(b"Alice".to_vec())];
+        let err =
+            driver::check_value_types(&result, &ordered, super::async_value_kind).unwrap_err();
+        assert!(matches!(
+            err,
+            sql_composer::Error::BindTypeMismatch { ref name, .. } if name == "id"
+        ));
+    }
 }
@@ -0,0 +1,81 @@
+//! Compiles a module generated by `sql_composer::codegen` for mysql_async
+//! and checks the statements and values its queries build.
+
+use std::path::Path;
+
+use sql_composer::codegen::{Driver, RustModule};
+use sql_composer::{Composer, Dialect};
+use sql_composer_mysql::mysql_async::Value;
+
+mod queries {
+    include!("codegen/queries.rs");
+}
+
+/// The templates the module is generated from, under `tests/codegen`.
+const TEMPLATES: [&str; 3] = ["users/by_ids.sqlc", "users/count.sqlc", "users/insert.sqlc"];
+
+fn generate() -> String {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen");
+    let mut composer = Composer::new(Dialect::Mysql);
+    composer.add_search_path(dir);
+    let mut module = RustModule::new(Driver::Mysql);
+    for path in TEMPLATES {
+        let template = composer.template(Path::new(path)).unwrap();
+        module.add(&composer, Path::new(path), &template).unwrap();
+    }
+    module.to_source()
+}
+
+#[test]
+fn test_generated_module_is_current() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen/queries.rs");
+    let expected = std::fs::read_to_string(path).unwrap();
+    assert_eq!(
+        generate(),
+        expected,
+        "tests/codegen/queries.rs is out of date"
+    );
+}
+
+#[test]
+fn test_generated_queries_build() {
+    let query = queries::users::ByIds {
+        ids: vec![1, 2, 3],
+        min_age: Some(26),
+        sort: "age".into(),
+    };
+    let (sql, values) = query.build().unwrap();
+    assert_eq!(
+        sql,
+        "SELECT name FROM users\nWHERE active = 1\n\n\
+         AND id IN (CAST(? AS SIGNED), CAST(? AS SIGNED), CAST(? AS SIGNED))\n\
+         AND age >= CAST(? AS SIGNED)\nORDER BY `age`\n"
+    );
+    assert_eq!(
+        values,
+        [Value::Int(1), Value::Int(2), Value::Int(3), Value::Int(26)]
+    );
+
+    let count = queries::users::Count {
+        ids: vec![2, 3],
+        min_age: None,
+        sort: "name".into(),
+    };
+    let (sql, values) = count.build().unwrap();
+    assert!(sql.starts_with("SELECT COUNT(*) FROM ("));
+    assert_eq!(values, [Value::Int(2), Value::Int(3)]);
+
+    let row = |id: i64, name: &str, age: i64, active: bool| -> [queries::Value; 4] {
+        [id.into(), name.into(), age.into(), active.into()]
+    };
+    let insert = queries::users::Insert {
+        rows: vec![row(1, "Alice", 30, true), row(2, "Bob", 25, true)],
+    };
+    let (sql, values) = insert.build().unwrap();
+    assert_eq!(
+        sql,
+        "INSERT INTO users (id, name, age, active) VALUES (?, ?, ?, ?), (?, ?, ?, ?)\n"
+    );
+    assert_eq!(values.len(), 8);
+    assert_eq!(values[4], Value::Int(2));
+}
@@ -0,0 +1,120 @@
+// @generated by sql-composer. Do not edit by hand.
+
+/// The bind value type of the generated queries.
+pub type Value = sql_composer_mysql::mysql_async::Value;
+
+pub mod users {
+    /// Bind parameters of `users/by_ids.sqlc`.
+    pub struct ByIds {
+        /// `:bind(ids AS int8)`
+        pub ids: Vec<i64>,
+        /// `:bind(min_age AS int4)`
+        pub min_age: Option<i32>,
+        /// `:ident(sort ONE OF name, age)`
+        pub sort: String,
+    }
+
+    impl ByIds {
+        /// The composed template.
+        pub const QUERY: sql_composer::codegen::Query<'static> = sql_composer::codegen::Query {
+            dialect: sql_composer::Dialect::Mysql,
+            template: "users/by_ids.sqlc",
+            emit_casts: true,
+            ordering: sql_composer::PlaceholderOrdering::Alphabetical,
+            fragments: &[
+                sql_composer::codegen::Fragment::Sql("SELECT name FROM users\nWHERE active = 1\n\nAND id IN ("),
+                sql_composer::codegen::Fragment::Bind { name: "ids", sql_type: Some("int8"), min_values: Some(1), max_values: Some(10), nullable: false, default: None, array: false },
+                sql_composer::codegen::Fragment::Sql(")\n"),
+                sql_composer::codegen::Fragment::If("min_age"),
+                sql_composer::codegen::Fragment::Sql("AND age >= "),
+                sql_composer::codegen::Fragment::Bind { name: "min_age", sql_type: Some("int4"), min_values: None, max_values: None, nullable: false, default: None, array: false },
+                sql_composer::codegen::Fragment::Sql("\n"),
+                sql_composer::codegen::Fragment::EndIf,
+                sql_composer::codegen::Fragment::Sql("ORDER BY "),
+                sql_composer::codegen::Fragment::Ident { name: "sort", allowed: &["name", "age"] },
+                sql_composer::codegen::Fragment::Sql("\n"),
+            ],
+        };
+
+        /// The SQL and its bind values, in placeholder order.
+        pub fn build(self) -> sql_composer::error::Result<(String, Vec<super::Value>)> {
+            let mut values = std::collections::BTreeMap::new();
+            values.insert("ids".to_string(), self.ids.into_iter().map(super::Value::from).collect());
+            values.insert("min_age".to_string(), self.min_age.into_iter().map(super::Value::from).collect());
+            let mut idents = std::collections::BTreeMap::new();
+            idents.insert("sort".to_string(), self.sort);
+            Self::QUERY.render_with_idents(values, &idents)
+        }
+    }
+
+    /// Bind parameters of `users/count.sqlc`.
+    pub struct Count {
+        /// `:bind(ids AS int8)`
+        pub ids: Vec<i64>,
+        /// `:bind(min_age AS int4)`
+        pub min_age: Option<i32>,
+        /// `:ident(sort ONE OF name, age)`
+        pub sort: String,
+    }
+
+    impl Count {
+        /// The composed template.
+        pub const QUERY: sql_composer::codegen::Query<'static> = sql_composer::codegen::Query {
+            dialect: sql_composer::Dialect::Mysql,
+            template: "users/count.sqlc",
+            emit_casts: true,
+            ordering: sql_composer::PlaceholderOrdering::Alphabetical,
+            fragments: &[
+                sql_composer::codegen::Fragment::Sql("SELECT COUNT(*) FROM (\nSELECT name FROM users\nWHERE active = 1\n\nAND id IN ("),
+                sql_composer::codegen::Fragment::Bind { name: "ids", sql_type: Some("int8"), min_values: Some(1), max_values: Some(10), nullable: false, default: None, array: false },
+                sql_composer::codegen::Fragment::Sql(")\n"),
+                sql_composer::codegen::Fragment::If("min_age"),
+                sql_composer::codegen::Fragment::Sql("AND age >= "),
+                sql_composer::codegen::Fragment::Bind { name: "min_age", sql_type: Some("int4"), min_values: None, max_values: None, nullable: false, default: None, array: false },
+                sql_composer::codegen::Fragment::Sql("\n"),
+                sql_composer::codegen::Fragment::EndIf,
+                sql_composer::codegen::Fragment::Sql("ORDER BY "),
+                sql_composer::codegen::Fragment::Ident { name: "sort", allowed: &["name", "age"] },
+                sql_composer::codegen::Fragment::Sql("\n\n) AS _count_sub\n"),
+            ],
+        };
+
+        /// The SQL and its bind values, in placeholder order.
+        pub fn build(self) -> sql_composer::error::Result<(String, Vec<super::Value>)> {
+            let mut values = std::collections::BTreeMap::new();
+            values.insert("ids".to_string(), self.ids.into_iter().map(super::Value::from).collect());
+            values.insert("min_age".to_string(), self.min_age.into_iter().map(super::Value::from).collect());
+            let mut idents = std::collections::BTreeMap::new();
+            idents.insert("sort".to_string(), self.sort);
+            Self::QUERY.render_with_idents(values, &idents)
+        }
+    }
+
+    /// Bind parameters of `users/insert.sqlc`.
+    pub struct Insert {
+        /// `:bind_rows(rows (id, name, age, active))`
+        pub rows: Vec<[super::Value; 4]>,
+    }
+
+    impl Insert {
+        /// The composed template.
+        pub const QUERY: sql_composer::codegen::Query<'static> = sql_composer::codegen::Query {
+            dialect: sql_composer::Dialect::Mysql,
+            template: "users/insert.sqlc",
+            emit_casts: true,
+            ordering: sql_composer::PlaceholderOrdering::Alphabetical,
+            fragments: &[
+                sql_composer::codegen::Fragment::Sql("INSERT INTO users (id, name, age, active) VALUES "),
+                sql_composer::codegen::Fragment::BindRows { name: "rows", columns: &["id", "name", "age", "active"] },
+                sql_composer::codegen::Fragment::Sql("\n"),
+            ],
+        };
+
+        /// The SQL and its bind values, in placeholder order.
+        pub fn build(self) -> sql_composer::error::Result<(String, Vec<super::Value>)> {
+            let mut values = std::collections::BTreeMap::new();
+            values.insert("rows".to_string(), self.rows.into_iter().flatten().collect());
+            Self::QUERY.render(values)
+        }
+    }
+}
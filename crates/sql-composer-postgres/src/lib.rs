@@ -59,11 +59,15 @@ pub use tokio_postgres;
 pub use postgres;
 
 use std::collections::BTreeMap;
+use std::fmt;
 use std::ops::{Deref, DerefMut};
 
-use sql_composer::composer::Composer;
+use bytes::BytesMut;
+use postgres_protocol::types::{array_to_sql, ArrayDimension};
+use sql_composer::composer::{ComposedChunk, ComposedSql, Composer};
 use sql_composer::driver;
-use sql_composer::types::Template;
+use sql_composer::types::{BindType, Template};
+use tokio_postgres::types::{to_sql_checked, IsNull, Kind, ToSql, Type};
 
 /// Error type for sql-composer-postgres operations.
 #[derive(Debug, thiserror::Error)]
@@ -77,6 +81,195 @@ pub enum Error {
     Postgres(#[from] tokio_postgres::Error),
 }
 
+// ---------------------------------------------------------------------------
+// Typed bindings
+// ---------------------------------------------------------------------------
+
+/// Check each value of a typed binding by encoding it as the declared
+/// Postgres type, the way the server will read it after the `$n::type` cast.
+fn check_value_types<'a>(
+    composed: &ComposedSql,
+    values: impl IntoIterator<Item = &'a dyn ToSql>,
+) -> Result<(), sql_composer::Error> {
+    let mut buf = BytesMut::new();
+    for (name, value) in composed.bind_params.iter().zip(values) {
+        let Some(expected) = composed.bind_type(name) else {
+            continue;
+        };
+        buf.clear();
+        if let Err(e) = value.to_sql_checked(&pg_type(expected), &mut buf) {
+            return Err(sql_composer::Error::BindTypeMismatch {
+                name: name.clone(),
+                expected: expected.clone(),
+                found: format!("a value that does not encode as {expected} ({e})"),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// One value a chunk from [`Composer::compose_chunked()`] binds: a borrowed
+/// value, or the borrowed values of an `AS ARRAY` binding as one array.
+#[derive(Debug)]
+enum ChunkParam<'v> {
+    Value(&'v (dyn ToSql + Sync)),
+    Array(ValueArray<&'v (dyn ToSql + Sync)>),
+}
+
+impl ChunkParam<'_> {
+    fn as_sql(&self) -> &(dyn ToSql + Sync) {
+        match self {
+            ChunkParam::Value(value) => *value,
+            ChunkParam::Array(array) => array,
+        }
+    }
+}
+
+impl<'v, V: BoxedParam> From<&'v V> for ChunkParam<'v> {
+    fn from(value: &'v V) -> Self {
+        ChunkParam::Value(value.as_param())
+    }
+}
+
+/// A boxed value the chunked helpers take.
+trait BoxedParam {
+    fn as_param(&self) -> &(dyn ToSql + Sync);
+}
+
+impl BoxedParam for Box<dyn ToSql + Sync + Send> {
+    fn as_param(&self) -> &(dyn ToSql + Sync) {
+        self.as_ref()
+    }
+}
+
+impl BoxedParam for Box<dyn ToSql + Sync> {
+    fn as_param(&self) -> &(dyn ToSql + Sync) {
+        self.as_ref()
+    }
+}
+
+/// The checked values one chunk from [`Composer::compose_chunked()`] binds,
+/// in placeholder order, with `AS ARRAY` bindings bundled.
+fn chunk_params<'v, V: BoxedParam>(
+    chunk: &ComposedChunk,
+    values: &'v BTreeMap<String, Vec<V>>,
+) -> Result<Vec<ChunkParam<'v>>, sql_composer::Error> {
+    let ordered = driver::resolve_chunk_values_with(chunk, values, |_, array| {
+        let array = array.into_iter().map(V::as_param).collect();
+        Ok(ChunkParam::Array(ValueArray(array)))
+    })?;
+    check_value_types(
+        &chunk.composed,
+        ordered.iter().map(|v| v.as_sql() as &dyn ToSql),
+    )?;
+    Ok(ordered)
+}
+
+/// Borrow chunk params as the reference slice query methods expect.
+fn chunk_param_refs<'a>(params: &'a [ChunkParam<'_>]) -> Vec<&'a (dyn ToSql + Sync)> {
+    params.iter().map(ChunkParam::as_sql).collect()
+}
+
+/// The Postgres type for a declared bind type.
+fn pg_type(sql_type: &BindType) -> Type {
+    match sql_type {
+        BindType::Array(element) => pg_types(element).1,
+        scalar => pg_types(scalar).0,
+    }
+}
+
+/// The scalar and array Postgres types for a declared element type.
+fn pg_types(sql_type: &BindType) -> (Type, Type) {
+    match sql_type {
+        BindType::Bool => (Type::BOOL, Type::BOOL_ARRAY),
+        BindType::Int2 => (Type::INT2, Type::INT2_ARRAY),
+        BindType::Int4 => (Type::INT4, Type::INT4_ARRAY),
+        BindType::Int8 => (Type::INT8, Type::INT8_ARRAY),
+        BindType::Float4 => (Type::FLOAT4, Type::FLOAT4_ARRAY),
+        BindType::Float8 => (Type::FLOAT8, Type::FLOAT8_ARRAY),
+        BindType::Numeric => (Type::NUMERIC, Type::NUMERIC_ARRAY),
+        BindType::Text => (Type::TEXT, Type::TEXT_ARRAY),
+        BindType::Bytea => (Type::BYTEA, Type::BYTEA_ARRAY),
+        BindType::Date => (Type::DATE, Type::DATE_ARRAY),
+        BindType::Time => (Type::TIME, Type::TIME_ARRAY),
+        BindType::Timestamp => (Type::TIMESTAMP, Type::TIMESTAMP_ARRAY),
+        BindType::Timestamptz => (Type::TIMESTAMPTZ, Type::TIMESTAMPTZ_ARRAY),
+        BindType::Uuid => (Type::UUID, Type::UUID_ARRAY),
+        BindType::Json => (Type::JSON, Type::JSON_ARRAY),
+        BindType::Jsonb => (Type::JSONB, Type::JSONB_ARRAY),
+        // Postgres arrays are multi-dimensional over a single element type.
+        BindType::Array(element) => pg_types(element),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Array bindings
+// ---------------------------------------------------------------------------
+
+/// The values of an `AS ARRAY` binding, sent as one Postgres array.
+///
+/// Each element is encoded as the array's member type, so the values may be
+/// any boxed [`ToSql`] the server could bind one by one.
+#[derive(Debug)]
+pub struct ValueArray<T>(pub Vec<T>);
+
+impl<T, D> ToSql for ValueArray<T>
+where
+    T: Deref<Target = D> + fmt::Debug,
+    D: ToSql + ?Sized,
+{
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        let Kind::Array(member) = ty.kind() else {
+            return Err(format!("cannot bind an array as {ty}").into());
+        };
+        let dimension = ArrayDimension {
+            len: i32::try_from(self.0.len())?,
+            lower_bound: 1,
+        };
+        array_to_sql(
+            Some(dimension),
+            member.oid(),
+            self.0.iter(),
+            |value, out| match value.to_sql_checked(member, out)? {
+                IsNull::No => Ok(postgres_protocol::IsNull::No),
+                IsNull::Yes => Ok(postgres_protocol::IsNull::Yes),
+            },
+            out,
+        )?;
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(ty.kind(), Kind::Array(_))
+    }
+
+    to_sql_checked!();
+}
+
+/// Bundle the values of an `AS ARRAY` binding into one async parameter,
+/// for [`driver::resolve_values_with()`].
+#[cfg(feature = "async")]
+pub fn bundle_array(
+    _name: &str,
+    values: Vec<Box<dyn ToSql + Sync + Send>>,
+) -> Result<Box<dyn ToSql + Sync + Send>, sql_composer::Error> {
+    Ok(Box::new(ValueArray(values)))
+}
+
+/// Bundle the values of an `AS ARRAY` binding into one sync parameter,
+/// for [`driver::resolve_values_with()`].
+#[cfg(feature = "sync")]
+pub fn bundle_array_sync(
+    _name: &str,
+    values: Vec<Box<dyn ToSql + Sync>>,
+) -> Result<Box<dyn ToSql + Sync>, sql_composer::Error> {
+    Ok(Box::new(ValueArray(values)))
+}
+
 // ---------------------------------------------------------------------------
 // Async: PgClient (tokio-postgres)
 // ---------------------------------------------------------------------------
@@ -95,6 +288,50 @@ impl PgClient {
     pub fn from_client(client: tokio_postgres::Client) -> Self {
         Self(client)
     }
+
+    /// Run a query once per statement from [`Composer::compose_chunked()`]
+    /// and concatenate the rows in chunk order.
+    ///
+    /// Use it for lookups whose value lists can exceed the 65535 bind
+    /// parameters a Postgres statement allows.
+    pub async fn query_chunked(
+        &self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<Box<dyn ToSql + Sync + Send>>>,
+    ) -> Result<Vec<tokio_postgres::Row>, Error> {
+        let mut rows = Vec::new();
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = chunk_params(&chunk, values)?;
+            rows.extend(
+                self.0
+                    .query(&chunk.composed.sql as &str, &chunk_param_refs(&params))
+                    .await?,
+            );
+        }
+        Ok(rows)
+    }
+
+    /// Execute a statement once per chunk from
+    /// [`Composer::compose_chunked()`], returning the total number of rows
+    /// changed. Chunks run one after another; run the call in a transaction
+    /// to apply them atomically.
+    pub async fn execute_chunked(
+        &self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<Box<dyn ToSql + Sync + Send>>>,
+    ) -> Result<u64, Error> {
+        let mut changed = 0;
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = chunk_params(&chunk, values)?;
+            changed += self
+                .0
+                .execute(&chunk.composed.sql as &str, &chunk_param_refs(&params))
+                .await?;
+        }
+        Ok(changed)
+    }
 }
 
 #[cfg(feature = "async")]
@@ -138,7 +375,8 @@ impl driver::ComposerConnectionAsync for PgClient {
         mut values: BTreeMap<String, Vec<Self::Value>>,
     ) -> Result<(String, Vec<Self::Value>), Error> {
         let composed = composer.compose_with_values(template, &values)?;
-        let ordered = driver::resolve_values(&composed, &mut values)?;
+        let ordered = driver::resolve_values_with(&composed, &mut values, bundle_array)?;
+        check_value_types(&composed, ordered.iter().map(|v| v.as_ref() as &dyn ToSql))?;
         Ok((composed.sql, ordered))
     }
 }
@@ -160,6 +398,44 @@ impl PgConnection {
     pub fn from_client(client: postgres::Client) -> Self {
         Self(client)
     }
+
+    /// Run a query once per statement from [`Composer::compose_chunked()`]
+    /// and concatenate the rows in chunk order.
+    pub fn query_chunked(
+        &mut self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<Box<dyn ToSql + Sync>>>,
+    ) -> Result<Vec<postgres::Row>, Error> {
+        let mut rows = Vec::new();
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = chunk_params(&chunk, values)?;
+            rows.extend(
+                self.0
+                    .query(&chunk.composed.sql as &str, &chunk_param_refs(&params))?,
+            );
+        }
+        Ok(rows)
+    }
+
+    /// Execute a statement once per chunk from
+    /// [`Composer::compose_chunked()`], returning the total number of rows
+    /// changed.
+    pub fn execute_chunked(
+        &mut self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<Box<dyn ToSql + Sync>>>,
+    ) -> Result<u64, Error> {
+        let mut changed = 0;
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = chunk_params(&chunk, values)?;
+            changed += self
+                .0
+                .execute(&chunk.composed.sql as &str, &chunk_param_refs(&params))?;
+        }
+        Ok(changed)
+    }
 }
 
 #[cfg(feature = "sync")]
@@ -203,16 +479,21 @@ impl driver::ComposerConnection for PgConnection {
         mut values: BTreeMap<String, Vec<Self::Value>>,
     ) -> Result<(String, Vec<Self::Value>), Error> {
         let composed = composer.compose_with_values(template, &values)?;
-        let ordered = driver::resolve_values(&composed, &mut values)?;
+        let ordered = driver::resolve_values_with(&composed, &mut values, bundle_array_sync)?;
+        check_value_types(&composed, ordered.iter().map(|v| v.as_ref() as &dyn ToSql))?;
         Ok((composed.sql, ordered))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
+    use bytes::BytesMut;
     use sql_composer::composer::Composer;
     use sql_composer::parser::parse_template;
     use sql_composer::types::{Dialect, TemplateSource};
+    use tokio_postgres::types::{ToSql, Type};
 
     #[test]
     fn test_compose_single_bind_postgres() {
@@ -248,4 +529,77 @@ mod tests {
         assert_eq!(result.sql, "SELECT * FROM users WHERE id IN ($1, $2, $3)");
         assert_eq!(result.bind_params, vec!["ids", "ids", "ids"]);
     }
+
+    #[test]
+    fn test_value_array_encodes_like_vec() {
+        let values: Vec<Box<dyn ToSql + Sync + Send>> = vec![Box::new(1i64), Box::new(2i64)];
+        let mut bundled = BytesMut::new();
+        super::ValueArray(values)
+            .to_sql_checked(&Type::INT8_ARRAY, &mut bundled)
+            .unwrap();
+        let mut expected = BytesMut::new();
+        vec![1i64, 2]
+            .to_sql(&Type::INT8_ARRAY, &mut expected)
+            .unwrap();
+        assert_eq!(bundled, expected);
+
+        let values: Vec<Box<dyn ToSql + Sync>> = vec![Box::new("a")];
+        let err = super::ValueArray(values).to_sql_checked(&Type::INT8_ARRAY, &mut bundled);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_chunk_params_bundle_arrays() {
+        let input = "SELECT * FROM t WHERE org IN (:bind(orgs)) AND id = ANY(:bind(ids AS ARRAY))";
+        let template = parse_template(input, TemplateSource::Literal("test".into())).unwrap();
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.max_bind_params = 2;
+        let values: BTreeMap<String, Vec<Box<dyn ToSql + Sync + Send>>> = sql_composer::bind_values!(
+            "ids" => [Box::new(7i64) as Box<dyn ToSql + Sync + Send>, Box::new(8i64)],
+            "orgs" => [Box::new(1i64) as Box<dyn ToSql + Sync + Send>, Box::new(2i64)],
+        );
+
+        let chunks = composer.compose_chunked(&template, &values).unwrap();
+        assert_eq!(chunks.len(), 2);
+        let mut expected = BytesMut::new();
+        vec![7i64, 8]
+            .to_sql(&Type::INT8_ARRAY, &mut expected)
+            .unwrap();
+        for chunk in &chunks {
+            assert_eq!(
+                chunk.composed.sql,
+                "SELECT * FROM t WHERE org IN ($2) AND id = ANY($1)"
+            );
+            let params = super::chunk_params(chunk, &values).unwrap();
+            assert_eq!(params.len(), 2);
+            let mut bundled = BytesMut::new();
+            params[0]
+                .as_sql()
+                .to_sql_checked(&Type::INT8_ARRAY, &mut bundled)
+                .unwrap();
+            assert_eq!(bundled, expected);
+        }
+    }
+
+    #[test]
+    fn test_check_value_types() {
+        let input =
+            "SELECT * FROM users WHERE id = :bind(id AS int8) AND tags && :bind(tags AS text[])";
+        let template = parse_template(input, TemplateSource::Literal("test".into())).unwrap();
+        let composer = Composer::new(Dialect::Postgres);
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM users WHERE id = $1::int8 AND tags && $2::text[]"
+        );
+
+        let tags = vec!["a".to_string()];
+        super::check_value_types(&result, [&7i64 as &dyn ToSql, &tags]).unwrap();
+
+        let err = super::check_value_types(&result, [&7i32 as &dyn ToSql, &tags]).unwrap_err();
+        assert!(matches!(
+            err,
+            sql_composer::Error::BindTypeMismatch { ref name, .. } if name == "id"
+        ));
+    }
 }
@@ -0,0 +1,102 @@
+//! Compiles a module generated by `sql_composer::codegen` for tokio-postgres
+//! and checks the statements and values its queries build.
+
+use std::path::Path;
+
+use bytes::BytesMut;
+use sql_composer::codegen::{Driver, RustModule};
+use sql_composer::{Composer, Dialect};
+use sql_composer_postgres::tokio_postgres::types::{ToSql, Type};
+
+mod queries {
+    include!("codegen/queries.rs");
+}
+
+/// The templates the module is generated from, under `tests/codegen`.
+const TEMPLATES: [&str; 3] = ["users/by_ids.sqlc", "users/count.sqlc", "users/insert.sqlc"];
+
+fn generate() -> String {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen");
+    let mut composer = Composer::new(Dialect::Postgres);
+    composer.add_search_path(dir);
+    let mut module = RustModule::new(Driver::Postgres);
+    for path in TEMPLATES {
+        let template = composer.template(Path::new(path)).unwrap();
+        module.add(&composer, Path::new(path), &template).unwrap();
+    }
+    module.to_source()
+}
+
+/// Encode `value` as `ty`, the way the server reads the parameter.
+fn encode(value: &dyn ToSql, ty: &Type) -> BytesMut {
+    let mut out = BytesMut::new();
+    value.to_sql_checked(ty, &mut out).unwrap();
+    out
+}
+
+#[test]
+fn test_generated_module_is_current() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen/queries.rs");
+    let expected = std::fs::read_to_string(path).unwrap();
+    assert_eq!(
+        generate(),
+        expected,
+        "tests/codegen/queries.rs is out of date"
+    );
+}
+
+#[test]
+fn test_generated_queries_build() {
+    let query = queries::users::ByIds {
+        ids: vec![1, 2, 3],
+        min_age: Some(26),
+        sort: "age".into(),
+    };
+    let (sql, values) = query.build().unwrap();
+    assert_eq!(
+        sql,
+        "SELECT name FROM users\nWHERE active = 1\n\n\
+         AND id = ANY($1::int8[])\nAND age >= $2::int4\nORDER BY \"age\"\n"
+    );
+    assert_eq!(values.len(), 2);
+    assert_eq!(
+        encode(values[0].as_ref(), &Type::INT8_ARRAY),
+        encode(&vec![1i64, 2, 3], &Type::INT8_ARRAY)
+    );
+    assert_eq!(
+        encode(values[1].as_ref(), &Type::INT4),
+        encode(&26i32, &Type::INT4)
+    );
+
+    let count = queries::users::Count {
+        ids: vec![2, 3],
+        min_age: None,
+        sort: "name".into(),
+    };
+    let (sql, values) = count.build().unwrap();
+    assert!(sql.starts_with("SELECT COUNT(*) FROM ("));
+    assert!(sql.contains("AND id = ANY($1::int8[])"));
+    assert_eq!(values.len(), 1);
+
+    let row = |id: i64, name: &'static str, age: i64, active: bool| -> [queries::Value; 4] {
+        [
+            Box::new(id),
+            Box::new(name),
+            Box::new(age),
+            Box::new(active),
+        ]
+    };
+    let insert = queries::users::Insert {
+        rows: vec![row(1, "Alice", 30, true), row(2, "Bob", 25, true)],
+    };
+    let (sql, values) = insert.build().unwrap();
+    assert_eq!(
+        sql,
+        "INSERT INTO users (id, name, age, active) VALUES ($1, $2, $3, $4), ($5, $6, $7, $8)\n"
+    );
+    assert_eq!(values.len(), 8);
+    assert_eq!(
+        encode(values[4].as_ref(), &Type::INT8),
+        encode(&2i64, &Type::INT8)
+    );
+}
@@ -34,8 +34,9 @@ pub use rusqlite;
 use std::collections::BTreeMap;
 use std::ops::{Deref, DerefMut};
 
-use sql_composer::composer::Composer;
-use sql_composer::driver::{self, ComposerConnection};
+use rusqlite::types::{ToSql, ToSqlOutput, ValueRef};
+use sql_composer::composer::{ComposedChunk, Composer};
+use sql_composer::driver::{self, ComposerConnection, ValueKind};
 use sql_composer::types::Template;
 
 /// Error type for sql-composer-rusqlite operations.
@@ -71,6 +72,65 @@ impl SqliteConnection {
     pub fn from_connection(conn: rusqlite::Connection) -> Self {
         Self(conn)
     }
+
+    /// Run a query once per statement from [`Composer::compose_chunked()`],
+    /// mapping every row with `f` and concatenating the results in chunk
+    /// order.
+    ///
+    /// Use it for lookups whose value lists can exceed SQLite's bind
+    /// parameter limit.
+    pub fn query_chunked<T, F>(
+        &self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<Box<dyn ToSql>>>,
+        mut f: F,
+    ) -> Result<Vec<T>, Error>
+    where
+        F: FnMut(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+    {
+        let mut results = Vec::new();
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = chunk_params(&chunk, values)?;
+            let mut stmt = self.prepare(&chunk.composed.sql)?;
+            let rows = stmt.query_map(params.as_slice(), &mut f)?;
+            for row in rows {
+                results.push(row?);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Execute a statement once per chunk from
+    /// [`Composer::compose_chunked()`], returning the total number of rows
+    /// changed.
+    ///
+    /// Use it for bulk `:bind_rows()` inserts that can exceed SQLite's bind
+    /// parameter limit. Chunks run one after another; wrap the call in a
+    /// transaction to apply them atomically.
+    pub fn execute_chunked(
+        &self,
+        composer: &Composer,
+        template: &Template,
+        values: &BTreeMap<String, Vec<Box<dyn ToSql>>>,
+    ) -> Result<usize, Error> {
+        let mut changed = 0;
+        for chunk in composer.compose_chunked(template, values)? {
+            let params = chunk_params(&chunk, values)?;
+            changed += self.execute(&chunk.composed.sql, params.as_slice())?;
+        }
+        Ok(changed)
+    }
+}
+
+/// The checked values one chunk binds, in placeholder order.
+fn chunk_params<'v>(
+    chunk: &ComposedChunk,
+    values: &'v BTreeMap<String, Vec<Box<dyn ToSql>>>,
+) -> Result<Vec<&'v dyn ToSql>, Error> {
+    let ordered = driver::resolve_chunk_values(chunk, values)?;
+    driver::check_value_types(&chunk.composed, &ordered, |v| value_kind(v.as_ref()))?;
+    Ok(ordered.into_iter().map(|v| v.as_ref()).collect())
 }
 
 impl Deref for SqliteConnection {
@@ -100,10 +160,30 @@ impl ComposerConnection for SqliteConnection {
     ) -> Result<(String, Vec<Self::Value>), Error> {
         let composed = composer.compose_with_values(template, &values)?;
         let ordered = driver::resolve_values(&composed, &mut values)?;
+        driver::check_value_types(&composed, &ordered, |v| value_kind(v.as_ref()))?;
         Ok((composed.sql, ordered))
     }
 }
 
+/// Classify a value by the SQLite storage class it binds as.
+fn value_kind(value: &dyn ToSql) -> ValueKind {
+    let Ok(output) = value.to_sql() else {
+        return ValueKind::Unknown;
+    };
+    let value_ref = match &output {
+        ToSqlOutput::Borrowed(value_ref) => *value_ref,
+        ToSqlOutput::Owned(value) => ValueRef::from(value),
+        _ => return ValueKind::Unknown,
+    };
+    match value_ref {
+        ValueRef::Null => ValueKind::Null,
+        ValueRef::Integer(_) => ValueKind::Integer,
+        ValueRef::Real(_) => ValueKind::Float,
+        ValueRef::Text(_) => ValueKind::Text,
+        ValueRef::Blob(_) => ValueKind::Bytes,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,4 +283,89 @@ mod tests {
         assert_eq!(sql, "SELECT ?1 AS col_1, ?2 AS col_2");
         assert_eq!(params.len(), 2);
     }
+
+    #[test]
+    fn test_compose_with_mock_table_on_empty_database() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+
+        let mut mock = sql_composer::MockTable::new("users");
+        mock.rows = sql_composer::mock_rows![
+            {"id" => "1", "name" => "Alice"},
+            {"id" => "2", "name" => "Bob"},
+        ];
+
+        let input = "SELECT u.name FROM users u WHERE u.id = :bind(user_id)";
+        let template = parse_template(input, TemplateSource::Literal("test".into())).unwrap();
+        let mut composer = Composer::new(Dialect::Sqlite);
+        composer.add_mock_table(mock);
+
+        let values = bind_values!("user_id" => [boxed("2")]);
+        let (sql, params) = conn.compose(&composer, &template, values).unwrap();
+
+        let refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|v| v.as_ref()).collect();
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let names: Vec<String> = stmt
+            .query_map(refs.as_slice(), |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(names, vec!["Bob".to_string()]);
+    }
+
+    #[test]
+    fn test_chunked_insert_and_query() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE parts (num INTEGER, qty INTEGER)", [])
+            .unwrap();
+        let mut composer = Composer::new(Dialect::Sqlite);
+        composer.max_bind_params = 4;
+
+        let input = "INSERT INTO parts (num, qty) VALUES :bind_rows(items (num, qty))";
+        let template = parse_template(input, TemplateSource::Literal("test".into())).unwrap();
+        let items: Vec<Box<dyn ToSql>> =
+            driver::flatten_rows((1..=5i64).map(|n| [boxed(n), boxed(n * 10)]));
+        let values = BTreeMap::from([("items".to_string(), items)]);
+        let changed = conn.execute_chunked(&composer, &template, &values).unwrap();
+        assert_eq!(changed, 5);
+
+        let input = "SELECT qty FROM parts WHERE num IN (:bind(nums)) AND qty > :bind(min)";
+        let template = parse_template(input, TemplateSource::Literal("test".into())).unwrap();
+        let values = bind_values!(
+            "nums" => [boxed(1i64), boxed(2i64), boxed(4i64), boxed(5i64)],
+            "min" => [boxed(10i64)],
+        );
+        let qty: Vec<i64> = conn
+            .query_chunked(&composer, &template, &values, |row| row.get(0))
+            .unwrap();
+        assert_eq!(qty, vec![20, 40, 50]);
+    }
+
+    #[test]
+    fn test_typed_bind_checks_values() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+
+        let input = "SELECT :bind(id AS int8) AS id";
+        let template = parse_template(input, TemplateSource::Literal("test".into())).unwrap();
+        let composer = Composer::new(Dialect::Sqlite);
+
+        let values = bind_values!("id" => [boxed(7i64)]);
+        let (sql, params) = conn.compose(&composer, &template, values).unwrap();
+        assert_eq!(sql, "SELECT CAST(?1 AS INTEGER) AS id");
+
+        let refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|v| v.as_ref()).collect();
+        let id: i64 = conn
+            .query_row(&sql, refs.as_slice(), |row| row.get(0))
+            .unwrap();
+        assert_eq!(id, 7);
+
+        let values = bind_values!("id" => [boxed("seven")]);
+        let result = conn.compose(&composer, &template, values);
+        assert!(matches!(
+            result,
+            Err(Error::Composer(
+                sql_composer::Error::BindTypeMismatch { .. }
+            ))
+        ));
+    }
 }
@@ -0,0 +1,102 @@
+//! Compiles a module generated by `sql_composer::codegen` for rusqlite and
+//! runs its queries against an in-memory database.
+
+use std::path::Path;
+
+use sql_composer::codegen::{Driver, RustModule};
+use sql_composer::{Composer, Dialect};
+use sql_composer_rusqlite::rusqlite::{params_from_iter, Connection};
+
+mod queries {
+    include!("codegen/queries.rs");
+}
+
+/// The templates the module is generated from, under `tests/codegen`.
+const TEMPLATES: [&str; 3] = ["users/by_ids.sqlc", "users/count.sqlc", "users/insert.sqlc"];
+
+fn generate() -> String {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen");
+    let mut composer = Composer::new(Dialect::Sqlite);
+    composer.add_search_path(dir);
+    let mut module = RustModule::new(Driver::Rusqlite);
+    for path in TEMPLATES {
+        let template = composer.template(Path::new(path)).unwrap();
+        module.add(&composer, Path::new(path), &template).unwrap();
+    }
+    module.to_source()
+}
+
+fn users() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, \
+         age INTEGER NOT NULL, active INTEGER NOT NULL)",
+        [],
+    )
+    .unwrap();
+
+    let row = |id: i64, name: &'static str, age: i64, active: bool| -> [queries::Value; 4] {
+        [
+            Box::new(id),
+            Box::new(name),
+            Box::new(age),
+            Box::new(active),
+        ]
+    };
+    let insert = queries::users::Insert {
+        rows: vec![
+            row(1, "Alice", 30, true),
+            row(2, "Bob", 25, true),
+            row(3, "Carol", 41, false),
+        ],
+    };
+    let (sql, values) = insert.build().unwrap();
+    conn.execute(&sql, params_from_iter(values.iter())).unwrap();
+    conn
+}
+
+#[test]
+fn test_generated_module_is_current() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen/queries.rs");
+    let expected = std::fs::read_to_string(path).unwrap();
+    assert_eq!(
+        generate(),
+        expected,
+        "tests/codegen/queries.rs is out of date"
+    );
+}
+
+#[test]
+fn test_generated_queries_run() {
+    let conn = users();
+    let names = |query: queries::users::ByIds| {
+        let (sql, values) = query.build().unwrap();
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let rows = stmt.query_map(params_from_iter(values.iter()), |row| row.get(0));
+        rows.unwrap().collect::<Result<Vec<String>, _>>().unwrap()
+    };
+
+    let all = queries::users::ByIds {
+        ids: vec![1, 2, 3],
+        min_age: None,
+        sort: "name".into(),
+    };
+    assert_eq!(names(all), ["Alice", "Bob"]);
+    let older = queries::users::ByIds {
+        ids: vec![1, 2, 3],
+        min_age: Some(26),
+        sort: "age".into(),
+    };
+    assert_eq!(names(older), ["Alice"]);
+
+    let count = queries::users::Count {
+        ids: vec![2, 3],
+        min_age: None,
+        sort: "age".into(),
+    };
+    let (sql, values) = count.build().unwrap();
+    let n: i64 = conn
+        .query_row(&sql, params_from_iter(values.iter()), |row| row.get(0))
+        .unwrap();
+    assert_eq!(n, 1);
+}
@@ -62,48 +62,129 @@ pub async fn verify_postgres(database_url: &str, statements: &[&ComposedSql]) ->
 /// This does not check table/column existence.
 #[cfg(feature = "validate")]
 pub fn validate_syntax(sql: &str, dialect: sql_composer::Dialect) -> Result<()> {
-    use sqlparser::dialect::{MySqlDialect, PostgreSqlDialect, SQLiteDialect};
+    use sqlparser::dialect::{
+        ClickHouseDialect, GenericDialect, MsSqlDialect, MySqlDialect, PostgreSqlDialect,
+        SQLiteDialect,
+    };
     use sqlparser::parser::Parser;
 
+    // Replace placeholders with literal values for parsing
+    let normalized = normalize_placeholders(sql, dialect);
+
     let dialect: Box<dyn sqlparser::dialect::Dialect> = match dialect {
         sql_composer::Dialect::Postgres => Box::new(PostgreSqlDialect {}),
         sql_composer::Dialect::Mysql => Box::new(MySqlDialect {}),
         sql_composer::Dialect::Sqlite => Box::new(SQLiteDialect {}),
+        sql_composer::Dialect::SqlServer => Box::new(MsSqlDialect {}),
+        // sqlparser has no Oracle dialect; the generic one accepts its
+        // queries short of Oracle-only syntax.
+        sql_composer::Dialect::Oracle => Box::new(GenericDialect {}),
+        sql_composer::Dialect::ClickHouse => Box::new(ClickHouseDialect {}),
+        _ => Box::new(GenericDialect {}),
     };
 
-    // Replace placeholders with literal values for parsing
-    let normalized = normalize_placeholders(sql);
     Parser::parse_sql(dialect.as_ref(), &normalized).map_err(|e| Error::Syntax(e.to_string()))?;
 
     Ok(())
 }
 
-/// Replace dialect-specific placeholders with literal `1` for syntax validation.
+/// Replace `dialect`'s placeholders with literal `1` for syntax validation:
+/// `$1` on Postgres, `?` on MySQL, `?1` on SQLite, `@p1` on SQL Server, `:1`
+/// on Oracle and `{name:Int64}` on ClickHouse. Quoted strings, quoted
+/// identifiers and comments are copied unchanged.
 #[cfg(feature = "validate")]
-fn normalize_placeholders(sql: &str) -> String {
+fn normalize_placeholders(sql: &str, dialect: sql_composer::Dialect) -> String {
+    use sql_composer::Dialect;
+
+    let backslash = matches!(dialect, Dialect::Mysql | Dialect::ClickHouse);
     let mut result = String::with_capacity(sql.len());
     let mut chars = sql.chars().peekable();
+    let mut prev = None;
 
     while let Some(ch) = chars.next() {
-        if ch == '$' || ch == '?' {
-            // Skip the placeholder number
-            let mut has_digits = false;
-            while let Some(&next) = chars.peek() {
-                if next.is_ascii_digit() {
-                    chars.next();
-                    has_digits = true;
-                } else {
-                    break;
+        let digit_next = chars.peek().is_some_and(char::is_ascii_digit);
+        match ch {
+            '\'' | '"' | '`' => {
+                // Copy through the closing quote, keeping doubled quotes and,
+                // where the dialect has them, backslash escapes inside.
+                let escapes = backslash && ch != '`';
+                result.push(ch);
+                while let Some(next) = chars.next() {
+                    result.push(next);
+                    if next == '\\' && escapes {
+                        if let Some(escaped) = chars.next() {
+                            result.push(escaped);
+                        }
+                    } else if next == ch {
+                        match chars.next_if_eq(&ch) {
+                            Some(quote) => result.push(quote),
+                            None => break,
+                        }
+                    }
                 }
             }
-            if has_digits || ch == '?' {
-                result.push('1');
-            } else {
+            '-' if chars.peek() == Some(&'-') => {
                 result.push(ch);
+                while let Some(next) = chars.next_if(|&c| c != '\n') {
+                    result.push(next);
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                result.push_str("/*");
+                let mut star = false;
+                for next in chars.by_ref() {
+                    result.push(next);
+                    if star && next == '/' {
+                        break;
+                    }
+                    star = next == '*';
+                }
+            }
+            // Postgres `$1`
+            '$' if dialect == Dialect::Postgres && digit_next => {
+                while chars.next_if(char::is_ascii_digit).is_some() {}
+                result.push('1');
             }
-        } else {
-            result.push(ch);
+            // MySQL `?` and SQLite `?1`
+            '?' if matches!(dialect, Dialect::Mysql | Dialect::Sqlite) => {
+                if dialect == Dialect::Sqlite {
+                    while chars.next_if(char::is_ascii_digit).is_some() {}
+                }
+                result.push('1');
+            }
+            // SQL Server `@p1`
+            '@' if dialect == Dialect::SqlServer && {
+                let mut ahead = chars.clone();
+                ahead.next() == Some('p') && ahead.peek().is_some_and(char::is_ascii_digit)
+            } =>
+            {
+                chars.next();
+                while chars.next_if(char::is_ascii_digit).is_some() {}
+                result.push('1');
+            }
+            // Oracle `:1`, but not the `::` of a cast or the `:` of `a[1:2]`
+            ':' if dialect == Dialect::Oracle
+                && digit_next
+                && !prev.is_some_and(|c: char| c == ':' || c.is_alphanumeric()) =>
+            {
+                while chars.next_if(char::is_ascii_digit).is_some() {}
+                result.push('1');
+            }
+            // ClickHouse `{name:Int64}`
+            '{' if dialect == Dialect::ClickHouse
+                && chars.peek().is_some_and(|&c| c.is_alphabetic() || c == '_') =>
+            {
+                for next in chars.by_ref() {
+                    if next == '}' {
+                        break;
+                    }
+                }
+                result.push('1');
+            }
+            _ => result.push(ch),
         }
+        prev = Some(ch);
     }
 
     result
@@ -145,36 +226,81 @@ mod tests {
 
         #[test]
         fn test_normalize_placeholders_postgres() {
-            assert_eq!(normalize_placeholders("$1"), "1");
-            assert_eq!(normalize_placeholders("$10"), "1");
+            let pg = Dialect::Postgres;
+            assert_eq!(normalize_placeholders("$1", pg), "1");
+            assert_eq!(normalize_placeholders("$10", pg), "1");
             assert_eq!(
-                normalize_placeholders("WHERE a = $1 AND b = $2"),
+                normalize_placeholders("WHERE a = $1 AND b = $2", pg),
                 "WHERE a = 1 AND b = 1"
             );
+            assert_eq!(normalize_placeholders("$1::int8", pg), "1::int8");
+            assert_eq!(normalize_placeholders("a[1:2] = ?", pg), "a[1:2] = ?");
         }
 
         #[test]
         fn test_normalize_placeholders_mysql() {
-            assert_eq!(normalize_placeholders("?"), "1");
+            assert_eq!(normalize_placeholders("?", Dialect::Mysql), "1");
             assert_eq!(
-                normalize_placeholders("WHERE a = ? AND b = ?"),
+                normalize_placeholders("WHERE a = ? AND b = ?", Dialect::Mysql),
                 "WHERE a = 1 AND b = 1"
             );
         }
 
         #[test]
         fn test_normalize_placeholders_sqlite() {
-            assert_eq!(normalize_placeholders("?1"), "1");
+            assert_eq!(normalize_placeholders("?1", Dialect::Sqlite), "1");
             assert_eq!(
-                normalize_placeholders("WHERE a = ?1 AND b = ?2"),
+                normalize_placeholders("WHERE a = ?1 AND b = ?2", Dialect::Sqlite),
                 "WHERE a = 1 AND b = 1"
             );
         }
 
+        #[test]
+        fn test_normalize_placeholders_named() {
+            assert_eq!(
+                normalize_placeholders("WHERE a = @p1 AND b = @p22", Dialect::SqlServer),
+                "WHERE a = 1 AND b = 1"
+            );
+            assert_eq!(normalize_placeholders("@pp", Dialect::SqlServer), "@pp");
+            assert_eq!(
+                normalize_placeholders("WHERE a = :1 AND b = :2", Dialect::Oracle),
+                "WHERE a = 1 AND b = 1"
+            );
+            assert_eq!(
+                normalize_placeholders("WHERE c = {c:Decimal(38, 10)}", Dialect::ClickHouse),
+                "WHERE c = 1"
+            );
+        }
+
+        #[test]
+        fn test_normalize_placeholders_skips_quoted_text() {
+            assert_eq!(
+                normalize_placeholders("SELECT '$1', \"$2\" -- $3\n/* $4 */ $5", Dialect::Postgres),
+                "SELECT '$1', \"$2\" -- $3\n/* $4 */ 1"
+            );
+            assert_eq!(
+                normalize_placeholders("SELECT 'it''s ?', 'it\\'s ?', `?` = ?", Dialect::Mysql),
+                "SELECT 'it''s ?', 'it\\'s ?', `?` = 1"
+            );
+            assert_eq!(
+                normalize_placeholders("SELECT 'a:1' FROM t WHERE x = :1", Dialect::Oracle),
+                "SELECT 'a:1' FROM t WHERE x = 1"
+            );
+        }
+
+        #[test]
+        fn test_validate_syntax_sql_server() {
+            validate_syntax(
+                "SELECT TOP 10 * FROM users WHERE id = @p1",
+                Dialect::SqlServer,
+            )
+            .unwrap();
+        }
+
         #[test]
         fn test_normalize_preserves_dollar_without_digits() {
             // A bare $ not followed by digits should be preserved
-            assert_eq!(normalize_placeholders("$"), "$");
+            assert_eq!(normalize_placeholders("$", Dialect::Postgres), "$");
         }
     }
 }
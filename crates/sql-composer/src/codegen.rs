@@ -0,0 +1,1260 @@
+//! Rust code generation: one typed struct per template.
+//!
+//! [`RustModule`] turns templates into a Rust source file with one struct per
+//! template and one field per bind parameter. A struct's `build()` method
+//! returns the SQL and its values in placeholder order for a single driver
+//! crate, so a misspelled parameter is a compile error rather than a runtime
+//! [`Error::MissingBinding`].
+//!
+//! ```text
+//! let (sql, values) = sets::SelectSetParts { set_num: Box::new("1234-1") }.build()?;
+//! ```
+//!
+//! Each struct embeds its template as a [`Query`]: the elements
+//! [`Composer`] expands compose references and commands into, with the
+//! bindings left in place. Building it hands them back to
+//! [`Composer::compose_with_idents()`], which expands multi-value bindings,
+//! checks `EXPECTING`/`NULL` constraints and picks `:if` branches.
+//! [`StaticSql`] is the expansion of the `sqlc_file!()` macro in the
+//! `sql-composer-macros` crate.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use winnow::error::ContextError;
+use winnow::Parser;
+
+use crate::composer::{self, ComposedSql, Composer};
+use crate::driver;
+use crate::error::{Error, Result};
+use crate::parser::bind;
+use crate::types::{
+    BindDefault, BindType, Binding, Dialect, Element, Identifier, PlaceholderOrdering, RowBinding,
+    Template, TemplateSource,
+};
+
+/// Parse a `DEFAULT` value as written in a template.
+fn parse_default(text: &str) -> Option<BindDefault> {
+    bind::default_value::<_, ContextError>.parse(text).ok()
+}
+
+/// A composed template as embedded in generated code.
+///
+/// Compose references and commands are already resolved; only the bindings
+/// remain, since their placeholders depend on how many values each gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Query<'a> {
+    /// The dialect the template was composed for.
+    pub dialect: Dialect,
+    /// The template path, as reported in errors.
+    pub template: &'a str,
+    /// Whether typed bindings are wrapped in a dialect cast.
+    pub emit_casts: bool,
+    /// How numbered dialects number placeholders.
+    pub ordering: PlaceholderOrdering,
+    /// The composed SQL and its bindings, in document order.
+    pub fragments: &'a [Fragment<'a>],
+}
+
+/// A piece of a [`Query`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fragment<'a> {
+    /// Literal SQL.
+    Sql(&'a str),
+    /// A `:bind()` occurrence.
+    Bind {
+        /// The binding name.
+        name: &'a str,
+        /// The declared type, by its canonical name (`AS int8`).
+        sql_type: Option<&'a str>,
+        /// Minimum number of values (`EXPECTING min..`).
+        min_values: Option<u32>,
+        /// Maximum number of values (`EXPECTING ..max`).
+        max_values: Option<u32>,
+        /// Whether the binding may have no values (`NULL`).
+        nullable: bool,
+        /// The value used when none is supplied, as written (`DEFAULT 100`).
+        default: Option<&'a str>,
+        /// Whether the values are bound as one array (`AS ARRAY`), whose
+        /// elements have the declared type (`AS ARRAY OF int8`).
+        array: bool,
+    },
+    /// A `:bind_rows()` occurrence.
+    BindRows {
+        /// The binding name.
+        name: &'a str,
+        /// The columns of each row.
+        columns: &'a [&'a str],
+    },
+    /// An `:ident()` occurrence.
+    Ident {
+        /// The identifier name.
+        name: &'a str,
+        /// The identifiers allowed by `ONE OF`.
+        allowed: &'a [&'a str],
+    },
+    /// `:if(name)`: the fragments up to the matching `Else` or `EndIf` are
+    /// rendered only when `name` has values.
+    If(&'a str),
+    /// `:else`: the fragments up to the matching `EndIf` are rendered only
+    /// when the `If` binding has no values.
+    Else,
+    /// `:endif`: closes the innermost `If`.
+    EndIf,
+    /// `:bind_order(name, ...)`: the placeholder order under
+    /// [`PlaceholderOrdering::Explicit`].
+    BindOrder(&'a [&'a str]),
+}
+
+impl Fragment<'_> {
+    /// The template element this fragment stands for.
+    fn element(&self) -> Element {
+        match *self {
+            Fragment::Sql(text) => Element::Sql(text.to_string()),
+            Fragment::Bind {
+                name,
+                sql_type,
+                min_values,
+                max_values,
+                nullable,
+                default,
+                array,
+            } => Element::Bind(Binding {
+                name: name.to_string(),
+                min_values,
+                max_values,
+                nullable,
+                sql_type: sql_type.and_then(BindType::parse),
+                default: default.and_then(parse_default),
+                array,
+            }),
+            Fragment::BindRows { name, columns } => Element::BindRows(RowBinding {
+                name: name.to_string(),
+                columns: columns.iter().map(ToString::to_string).collect(),
+            }),
+            Fragment::Ident { name, allowed } => Element::Ident(Identifier {
+                name: name.to_string(),
+                allowed: allowed.iter().map(ToString::to_string).collect(),
+            }),
+            Fragment::If(name) => Element::If(name.to_string()),
+            Fragment::Else => Element::Else,
+            Fragment::EndIf => Element::EndIf,
+            Fragment::BindOrder(names) => {
+                Element::BindOrder(names.iter().map(ToString::to_string).collect())
+            }
+        }
+    }
+}
+
+impl Query<'_> {
+    /// Render the query for the given values, like
+    /// [`Composer::compose_with_values()`].
+    pub fn compose<V>(&self, values: &BTreeMap<String, Vec<V>>) -> Result<ComposedSql> {
+        self.compose_with_idents(values, &BTreeMap::new())
+    }
+
+    /// Render the query for the given values and identifiers with
+    /// [`Composer::compose_with_idents()`], on a composer set up like the
+    /// one that generated it.
+    ///
+    /// Fails with [`Error::TooManyBindParams`] past the dialect's
+    /// [bind parameter limit](crate::SqlDialect::max_bind_params).
+    pub fn compose_with_idents<V>(
+        &self,
+        values: &BTreeMap<String, Vec<V>>,
+        idents: &BTreeMap<String, String>,
+    ) -> Result<ComposedSql> {
+        let template = Template {
+            elements: self.fragments.iter().map(Fragment::element).collect(),
+            source: TemplateSource::File(PathBuf::from(self.template)),
+        };
+        let mut composer = Composer::new(self.dialect);
+        composer.emit_casts = self.emit_casts;
+        composer.ordering = self.ordering;
+        composer.compose_with_idents(&template, values, idents)
+    }
+
+    /// Render the query and order `values` to match its placeholders.
+    pub fn render<V>(&self, values: BTreeMap<String, Vec<V>>) -> Result<(String, Vec<V>)> {
+        self.render_with_idents(values, &BTreeMap::new())
+    }
+
+    /// Render the query with identifiers and order `values` to match its
+    /// placeholders.
+    pub fn render_with_idents<V>(
+        &self,
+        mut values: BTreeMap<String, Vec<V>>,
+        idents: &BTreeMap<String, String>,
+    ) -> Result<(String, Vec<V>)> {
+        let composed = self.compose_with_idents(&values, idents)?;
+        let ordered = driver::resolve_values(&composed, &mut values)?;
+        Ok((composed.sql, ordered))
+    }
+
+    /// Like [`Query::render_with_idents()`], binding all the values of an
+    /// `AS ARRAY` binding as the one value `bundle` makes of them, as
+    /// [`driver::resolve_values_with()`] does.
+    pub fn render_with<V>(
+        &self,
+        mut values: BTreeMap<String, Vec<V>>,
+        idents: &BTreeMap<String, String>,
+        bundle: impl FnMut(&str, Vec<V>) -> Result<V>,
+    ) -> Result<(String, Vec<V>)> {
+        let composed = self.compose_with_idents(&values, idents)?;
+        let ordered = driver::resolve_values_with(&composed, &mut values, bundle)?;
+        Ok((composed.sql, ordered))
+    }
+}
+
+/// A template composed at compile time by `sql_composer_macros::sqlc_file!()`.
+///
+/// Every binding gets a single placeholder, as with [`Composer::compose()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticSql {
+    /// The template path, as reported in errors.
+    pub template: &'static str,
+    /// The final SQL with dialect-specific placeholders.
+    pub sql: &'static str,
+    /// Bind parameter names in placeholder order.
+    pub bind_params: &'static [&'static str],
+}
+
+impl StaticSql {
+    /// Order `values` to match the placeholders, like
+    /// [`driver::resolve_values()`].
+    pub fn resolve_values<V>(&self, values: &mut BTreeMap<String, Vec<V>>) -> Result<Vec<V>> {
+        driver::resolve_values(&self.composed(), values)
+    }
+
+    /// The SQL as a [`ComposedSql`], built with
+    /// [`ComposedSql::from_bind_params()`].
+    pub fn composed(&self) -> ComposedSql {
+        ComposedSql::from_bind_params(
+            self.sql,
+            self.bind_params.iter().map(ToString::to_string).collect(),
+            TemplateSource::File(PathBuf::from(self.template)),
+        )
+    }
+}
+
+/// The driver crate whose value type generated code binds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Driver {
+    /// `sql-composer-rusqlite`: `Box<dyn rusqlite::types::ToSql>`.
+    Rusqlite,
+    /// `sql-composer-postgres` with tokio-postgres:
+    /// `Box<dyn ToSql + Sync + Send>`.
+    Postgres,
+    /// `sql-composer-postgres` with the sync `postgres` client:
+    /// `Box<dyn ToSql + Sync>`.
+    PostgresSync,
+    /// `sql-composer-mysql` with mysql_async: `mysql_async::Value`.
+    Mysql,
+    /// `sql-composer-mysql` with the sync `mysql` client: `mysql::Value`.
+    MysqlSync,
+    /// `sql-composer-duckdb`: `Box<dyn duckdb::ToSql>`.
+    Duckdb,
+}
+
+impl Driver {
+    /// The driver's bind value type, as a path from the generated module.
+    fn value_type(self) -> &'static str {
+        match self {
+            Driver::Rusqlite => "Box<dyn sql_composer_rusqlite::rusqlite::types::ToSql>",
+            Driver::Postgres => {
+                "Box<dyn sql_composer_postgres::tokio_postgres::types::ToSql + Sync + Send>"
+            }
+            Driver::PostgresSync => "Box<dyn sql_composer_postgres::postgres::types::ToSql + Sync>",
+            Driver::Mysql => "sql_composer_mysql::mysql_async::Value",
+            Driver::MysqlSync => "sql_composer_mysql::mysql::Value",
+            Driver::Duckdb => "Box<dyn sql_composer_duckdb::duckdb::ToSql>",
+        }
+    }
+
+    /// The function bundling the values of an `AS ARRAY` binding into one
+    /// value, for drivers that bind arrays.
+    fn bundle_array(self) -> Option<&'static str> {
+        match self {
+            Driver::Postgres => Some("sql_composer_postgres::bundle_array"),
+            Driver::PostgresSync => Some("sql_composer_postgres::bundle_array_sync"),
+            _ => None,
+        }
+    }
+
+    /// An expression converting `expr`, of a mapped Rust type, into a value.
+    fn wrap(self, expr: &str, value: &str) -> String {
+        match self {
+            Driver::Mysql | Driver::MysqlSync => format!("{value}::from({expr})"),
+            _ => format!("Box::new({expr}) as {value}"),
+        }
+    }
+
+    /// A function converting a mapped Rust value into a value, for `map()`.
+    fn wrap_fn(self, value: &str) -> String {
+        match self {
+            Driver::Mysql | Driver::MysqlSync => format!("{value}::from"),
+            _ => format!("|v| {}", self.wrap("v", value)),
+        }
+    }
+
+    /// The Rust type of a field for a binding declared `AS ty`, or `None` to
+    /// fall back to the driver's value type.
+    ///
+    /// Only types every driver accepts without optional features are mapped.
+    fn rust_type(self, ty: &BindType) -> Option<String> {
+        let name = match ty {
+            BindType::Bool => "bool",
+            BindType::Int2 => "i16",
+            BindType::Int4 => "i32",
+            BindType::Int8 => "i64",
+            BindType::Float4 => "f32",
+            BindType::Float8 => "f64",
+            BindType::Text => "String",
+            BindType::Bytea => "Vec<u8>",
+            BindType::Array(inner) if matches!(self, Driver::Postgres | Driver::PostgresSync) => {
+                return Some(format!("Vec<{}>", self.rust_type(inner)?));
+            }
+            _ => return None,
+        };
+        Some(name.to_string())
+    }
+}
+
+/// How a bind parameter appears across a template.
+#[derive(Debug, Clone, PartialEq)]
+struct Param {
+    sql_type: Option<BindType>,
+    /// Some occurrence accepts more than one value.
+    multi: bool,
+    /// Every occurrence accepts no values, being `NULL`, having a `DEFAULT`
+    /// or sitting in an `:if` block; or the name is only an `:if` condition.
+    nullable: bool,
+    /// The columns of each row, for a `:bind_rows()` binding.
+    columns: Option<Vec<String>>,
+    /// Declared `AS ARRAY`.
+    array: bool,
+}
+
+/// A template prepared for code generation.
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    dialect: Dialect,
+    emit_casts: bool,
+    ordering: PlaceholderOrdering,
+    fragments: Vec<ItemFragment>,
+    params: BTreeMap<String, Param>,
+    /// The `ONE OF` list of each `:ident()` name.
+    idents: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ItemFragment {
+    Sql(String),
+    Bind(Binding),
+    BindRows(RowBinding),
+    Ident(Identifier),
+    If(String),
+    Else,
+    EndIf,
+    BindOrder(Vec<String>),
+}
+
+/// A generated Rust module with one struct per template.
+///
+/// Templates are added under their path relative to the source directory;
+/// each directory becomes a nested module and each file a struct named after
+/// it, so `sets/select_set_parts.sqlc` becomes `sets::SelectSetParts`.
+#[derive(Debug, Clone)]
+pub struct RustModule {
+    driver: Driver,
+    items: BTreeMap<PathBuf, Item>,
+}
+
+impl RustModule {
+    /// Create an empty module binding `driver`'s value type.
+    pub fn new(driver: Driver) -> Self {
+        Self {
+            driver,
+            items: BTreeMap::new(),
+        }
+    }
+
+    /// Compose `template` with `composer` and add its struct at `path`.
+    ///
+    /// Fails like [`Composer::compose()`], with
+    /// [`Error::UnsupportedBindType`] for a typed binding the dialect can't
+    /// cast when casts are enabled, and with [`Error::CustomDialect`] unless
+    /// the composer's dialect is a built-in [`Dialect`].
+    pub fn add(&mut self, composer: &Composer, path: &Path, template: &Template) -> Result<()> {
+        let dialect = composer
+            .dialect()
+            .builtin()
+            .ok_or_else(|| Error::CustomDialect {
+                dialect: composer.dialect().name().to_string(),
+            })?;
+        let flat = composer.flatten(template)?;
+
+        let mut fragments = Vec::new();
+        let mut params: BTreeMap<String, Param> = BTreeMap::new();
+        let mut recorded = BTreeMap::new();
+        let mut idents = BTreeMap::new();
+        let mut conditions = Vec::new();
+        let mut depth = 0;
+        for (at, element) in flat.elements.iter().enumerate() {
+            let locate = |e| flat.locate_element(at, e);
+            let source = flat.source(at);
+            match element {
+                Element::Sql(text) => {
+                    if let Some(ItemFragment::Sql(sql)) = fragments.last_mut() {
+                        sql.push_str(text);
+                    } else if !text.is_empty() {
+                        fragments.push(ItemFragment::Sql(text.clone()));
+                    }
+                }
+                Element::Ident(ident) => {
+                    idents
+                        .entry(ident.name.clone())
+                        .or_insert_with(|| ident.allowed.clone());
+                    fragments.push(ItemFragment::Ident(ident.clone()));
+                }
+                Element::BindRows(rows) => {
+                    Composer::record_rows(&mut recorded, rows, source, &dialect).map_err(locate)?;
+                    params.entry(rows.name.clone()).or_insert(Param {
+                        sql_type: None,
+                        multi: true,
+                        nullable: depth > 0,
+                        columns: Some(rows.columns.clone()),
+                        array: false,
+                    });
+                    fragments.push(ItemFragment::BindRows(rows.clone()));
+                }
+                Element::BindOrder(names) => {
+                    fragments.push(ItemFragment::BindOrder(names.clone()));
+                }
+                Element::If(name) => {
+                    conditions.push(name.clone());
+                    depth += 1;
+                    fragments.push(ItemFragment::If(name.clone()));
+                }
+                Element::Else => fragments.push(ItemFragment::Else),
+                Element::EndIf => {
+                    depth -= 1;
+                    fragments.push(ItemFragment::EndIf);
+                }
+                Element::Bind(binding) => {
+                    Composer::record_param(&mut recorded, binding, source, &dialect)
+                        .map_err(locate)?;
+                    if composer.emit_casts {
+                        composer::placeholder(&dialect, binding, 1, 0, true).map_err(locate)?;
+                    }
+                    let multi = binding.array
+                        || (binding.min_values.is_some() || binding.max_values.is_some())
+                            && binding.max_values != Some(1);
+                    if let Some(sql_type @ BindType::Array(_)) = &binding.sql_type {
+                        if multi {
+                            return Err(locate(Error::ExpandedArrayType {
+                                name: binding.name.clone(),
+                                sql_type: sql_type.clone(),
+                            }));
+                        }
+                    }
+                    let optional = binding.nullable || binding.default.is_some() || depth > 0;
+                    let param = params.entry(binding.name.clone()).or_insert(Param {
+                        sql_type: binding.sql_type.clone(),
+                        multi,
+                        nullable: optional,
+                        columns: None,
+                        array: binding.array,
+                    });
+                    param.multi |= multi;
+                    param.nullable &= optional;
+                    fragments.push(ItemFragment::Bind(binding.clone()));
+                }
+                // Expanded by `flatten()`.
+                Element::Compose(_) | Element::Command(_) => {}
+            }
+        }
+
+        // A condition that is never bound still needs a field to set it.
+        for name in conditions {
+            params.entry(name).or_insert(Param {
+                sql_type: None,
+                multi: false,
+                nullable: true,
+                columns: None,
+                array: false,
+            });
+        }
+
+        let item = Item {
+            dialect,
+            emit_casts: composer.emit_casts,
+            ordering: composer.ordering,
+            fragments,
+            params,
+            idents,
+        };
+        self.items.insert(path.to_path_buf(), item);
+        Ok(())
+    }
+
+    /// Number of templates in the module.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the module holds no templates.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The generated Rust source.
+    pub fn to_source(&self) -> String {
+        let mut tree = ModuleTree::default();
+        for (path, item) in &self.items {
+            let mut node = &mut tree;
+            if let Some(parent) = path.parent() {
+                for dir in parent.iter() {
+                    let dir = ident(&snake_case(&dir.to_string_lossy()));
+                    node = node.children.entry(dir).or_default();
+                }
+            }
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            node.items.push((path, item));
+            node.names.push(camel_case(&stem));
+        }
+
+        let mut out = String::new();
+        out.push_str("// @generated by sql-composer. Do not edit by hand.\n\n");
+        let _ = writeln!(out, "/// The bind value type of the generated queries.");
+        let _ = writeln!(out, "pub type Value = {};", self.driver.value_type());
+        self.write_tree(&mut out, &tree, 0);
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, tree: &ModuleTree<'_>, depth: usize) {
+        let indent = "    ".repeat(depth);
+        for (i, ((path, item), name)) in tree.items.iter().zip(&tree.names).enumerate() {
+            if depth == 0 || i > 0 {
+                out.push('\n');
+            }
+            for line in self.struct_source(path, item, name, depth).lines() {
+                if line.is_empty() {
+                    out.push('\n');
+                } else {
+                    let _ = writeln!(out, "{indent}{line}");
+                }
+            }
+        }
+        for (i, (name, child)) in tree.children.iter().enumerate() {
+            if depth == 0 || i > 0 || !tree.items.is_empty() {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "{indent}pub mod {name} {{");
+            self.write_tree(out, child, depth + 1);
+            let _ = writeln!(out, "{indent}}}");
+        }
+    }
+
+    /// The struct and impl for one template, unindented.
+    fn struct_source(&self, path: &Path, item: &Item, name: &str, depth: usize) -> String {
+        let template = path.display().to_string();
+        let value = format!("{}Value", "super::".repeat(depth));
+        let mut out = String::new();
+
+        let _ = writeln!(out, "/// Bind parameters of `{template}`.");
+        if item.params.is_empty() && item.idents.is_empty() {
+            let _ = writeln!(out, "pub struct {name};");
+        } else {
+            let _ = writeln!(out, "pub struct {name} {{");
+            for (param_name, param) in &item.params {
+                let bound = item.fragments.iter().any(
+                    |f| matches!(f, ItemFragment::Bind(binding) if &binding.name == param_name),
+                );
+                let ty = match (&param.sql_type, &param.columns) {
+                    (_, Some(columns)) => {
+                        format!(":bind_rows({param_name} ({}))", columns.join(", "))
+                    }
+                    (Some(sql_type), None) if param.array => {
+                        format!(":bind({param_name} AS ARRAY OF {sql_type})")
+                    }
+                    (Some(sql_type), None) => format!(":bind({param_name} AS {sql_type})"),
+                    (None, None) if param.array => format!(":bind({param_name} AS ARRAY)"),
+                    (None, None) if bound => format!(":bind({param_name})"),
+                    (None, None) => format!(":if({param_name})"),
+                };
+                let _ = writeln!(out, "    /// `{ty}`");
+                let _ = writeln!(
+                    out,
+                    "    pub {}: {},",
+                    ident(param_name),
+                    self.field_type(param, &value)
+                );
+            }
+            for (ident_name, allowed) in &item.idents {
+                let _ = writeln!(
+                    out,
+                    "    /// `:ident({ident_name} ONE OF {})`",
+                    allowed.join(", ")
+                );
+                let _ = writeln!(out, "    pub {}: String,", ident(ident_name));
+            }
+            let _ = writeln!(out, "}}");
+        }
+
+        let _ = writeln!(out, "\nimpl {name} {{");
+        let _ = writeln!(out, "    /// The composed template.");
+        let _ = writeln!(
+            out,
+            "    pub const QUERY: sql_composer::codegen::Query<'static> = sql_composer::codegen::Query {{"
+        );
+        let _ = writeln!(
+            out,
+            "        dialect: sql_composer::Dialect::{:?},",
+            item.dialect
+        );
+        let _ = writeln!(out, "        template: {template:?},");
+        let _ = writeln!(out, "        emit_casts: {},", item.emit_casts);
+        let _ = writeln!(
+            out,
+            "        ordering: sql_composer::PlaceholderOrdering::{:?},",
+            item.ordering
+        );
+        let _ = writeln!(out, "        fragments: &[");
+        for fragment in &item.fragments {
+            let _ = writeln!(out, "            {},", fragment_source(fragment));
+        }
+        let _ = writeln!(out, "        ],");
+        let _ = writeln!(out, "    }};");
+
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "    /// The SQL and its bind values, in placeholder order."
+        );
+        let _ = writeln!(
+            out,
+            "    pub fn build(self) -> sql_composer::error::Result<(String, Vec<{value}>)> {{"
+        );
+        let values = if item.params.is_empty() {
+            "std::collections::BTreeMap::new()"
+        } else {
+            let _ = writeln!(
+                out,
+                "        let mut values = std::collections::BTreeMap::new();"
+            );
+            for (param_name, param) in &item.params {
+                let _ = writeln!(
+                    out,
+                    "        values.insert({param_name:?}.to_string(), {});",
+                    self.field_values(param_name, param, &value)
+                );
+            }
+            "values"
+        };
+        let bundle = self
+            .driver
+            .bundle_array()
+            .filter(|_| item.params.values().any(|param| param.array));
+        if let Some(bundle) = bundle {
+            let idents = if item.idents.is_empty() {
+                "std::collections::BTreeMap::new()"
+            } else {
+                let _ = writeln!(
+                    out,
+                    "        let mut idents = std::collections::BTreeMap::new();"
+                );
+                for ident_name in item.idents.keys() {
+                    let _ = writeln!(
+                        out,
+                        "        idents.insert({ident_name:?}.to_string(), self.{});",
+                        ident(ident_name)
+                    );
+                }
+                "idents"
+            };
+            let _ = writeln!(
+                out,
+                "        Self::QUERY.render_with({values}, &{idents}, {bundle})"
+            );
+        } else if item.idents.is_empty() {
+            let _ = writeln!(out, "        Self::QUERY.render({values})");
+        } else {
+            let _ = writeln!(
+                out,
+                "        let mut idents = std::collections::BTreeMap::new();"
+            );
+            for ident_name in item.idents.keys() {
+                let _ = writeln!(
+                    out,
+                    "        idents.insert({ident_name:?}.to_string(), self.{});",
+                    ident(ident_name)
+                );
+            }
+            let _ = writeln!(
+                out,
+                "        Self::QUERY.render_with_idents({values}, &idents)"
+            );
+        }
+        let _ = writeln!(out, "    }}");
+        let _ = writeln!(out, "}}");
+        out
+    }
+
+    fn field_type(&self, param: &Param, value: &str) -> String {
+        let ty = param
+            .sql_type
+            .as_ref()
+            .and_then(|ty| self.driver.rust_type(ty))
+            .unwrap_or_else(|| value.to_string());
+        if let Some(columns) = &param.columns {
+            format!("Vec<[{ty}; {}]>", columns.len())
+        } else if param.multi {
+            format!("Vec<{ty}>")
+        } else if param.nullable {
+            format!("Option<{ty}>")
+        } else {
+            ty
+        }
+    }
+
+    /// An expression turning a field into its `Vec` of values.
+    fn field_values(&self, name: &str, param: &Param, value: &str) -> String {
+        let field = format!("self.{}", ident(name));
+        if param.columns.is_some() {
+            return format!("{field}.into_iter().flatten().collect()");
+        }
+        let mapped = param
+            .sql_type
+            .as_ref()
+            .is_some_and(|ty| self.driver.rust_type(ty).is_some());
+        match (param.multi || param.nullable, mapped) {
+            (false, false) => format!("vec![{field}]"),
+            (false, true) => format!("vec![{}]", self.driver.wrap(&field, value)),
+            (true, false) if param.multi => field,
+            (true, false) => format!("{field}.into_iter().collect()"),
+            (true, true) => {
+                let wrap = self.driver.wrap_fn(value);
+                format!("{field}.into_iter().map({wrap}).collect()")
+            }
+        }
+    }
+}
+
+/// Templates grouped by directory, for nesting modules.
+#[derive(Default)]
+struct ModuleTree<'a> {
+    items: Vec<(&'a PathBuf, &'a Item)>,
+    names: Vec<String>,
+    children: BTreeMap<String, ModuleTree<'a>>,
+}
+
+/// A fragment as a Rust expression.
+fn fragment_source(fragment: &ItemFragment) -> String {
+    match fragment {
+        ItemFragment::Sql(text) => format!("sql_composer::codegen::Fragment::Sql({text:?})"),
+        ItemFragment::Bind(binding) => format!(
+            "sql_composer::codegen::Fragment::Bind {{ name: {:?}, sql_type: {:?}, min_values: {:?}, max_values: {:?}, nullable: {}, default: {:?}, array: {} }}",
+            binding.name,
+            binding.sql_type.as_ref().map(ToString::to_string),
+            binding.min_values,
+            binding.max_values,
+            binding.nullable,
+            binding.default.as_ref().map(ToString::to_string),
+            binding.array,
+        ),
+        ItemFragment::BindRows(rows) => format!(
+            "sql_composer::codegen::Fragment::BindRows {{ name: {:?}, columns: &{:?} }}",
+            rows.name, rows.columns,
+        ),
+        ItemFragment::Ident(ident) => format!(
+            "sql_composer::codegen::Fragment::Ident {{ name: {:?}, allowed: &{:?} }}",
+            ident.name, ident.allowed,
+        ),
+        ItemFragment::If(name) => format!("sql_composer::codegen::Fragment::If({name:?})"),
+        ItemFragment::Else => "sql_composer::codegen::Fragment::Else".to_string(),
+        ItemFragment::EndIf => "sql_composer::codegen::Fragment::EndIf".to_string(),
+        ItemFragment::BindOrder(names) => {
+            format!("sql_composer::codegen::Fragment::BindOrder(&{names:?})")
+        }
+    }
+}
+
+/// Words that can't be used as plain identifiers.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// `name` as a Rust identifier, raw if it is a keyword.
+fn ident(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("r#{name}")
+    } else if matches!(name, "self" | "super" | "crate" | "Self" | "_") {
+        format!("{name}_")
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// `name` with every character that can't appear in an identifier replaced
+/// by `_`, lowercased.
+fn snake_case(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// `select_set_parts` → `SelectSetParts`.
+fn camel_case(name: &str) -> String {
+    let mut out = String::new();
+    for word in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            out.push(first.to_ascii_uppercase());
+            out.extend(chars);
+        }
+    }
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, 'T');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::SqlDialect;
+    use crate::parser::parse_template;
+
+    fn template(sql: &str) -> Template {
+        parse_template(sql, TemplateSource::Literal("test".into())).unwrap()
+    }
+
+    fn fragments(sql: &str, composer: &Composer) -> Vec<ItemFragment> {
+        let mut module = RustModule::new(Driver::Rusqlite);
+        module
+            .add(composer, Path::new("q.sqlc"), &template(sql))
+            .unwrap();
+        module.items.remove(Path::new("q.sqlc")).unwrap().fragments
+    }
+
+    /// Borrow owned fragments as a [`Query`] and render it.
+    fn render_with(
+        sql: &str,
+        composer: &Composer,
+        values: &BTreeMap<String, Vec<i32>>,
+    ) -> Result<ComposedSql> {
+        let owned = fragments(sql, composer);
+        let text: Vec<(Option<String>, Option<String>)> = owned
+            .iter()
+            .map(|f| match f {
+                ItemFragment::Bind(b) => (
+                    b.sql_type.as_ref().map(ToString::to_string),
+                    b.default.as_ref().map(ToString::to_string),
+                ),
+                _ => (None, None),
+            })
+            .collect();
+        let allowed: Vec<Vec<&str>> = owned
+            .iter()
+            .map(|f| match f {
+                ItemFragment::Ident(ident) => ident.allowed.iter().map(String::as_str).collect(),
+                ItemFragment::BindRows(rows) => rows.columns.iter().map(String::as_str).collect(),
+                ItemFragment::BindOrder(names) => names.iter().map(String::as_str).collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+        let fragments: Vec<Fragment<'_>> = owned
+            .iter()
+            .zip(&text)
+            .zip(&allowed)
+            .map(|((f, (ty, default)), allowed)| match f {
+                ItemFragment::Sql(text) => Fragment::Sql(text),
+                ItemFragment::Bind(b) => Fragment::Bind {
+                    name: &b.name,
+                    sql_type: ty.as_deref(),
+                    min_values: b.min_values,
+                    max_values: b.max_values,
+                    nullable: b.nullable,
+                    default: default.as_deref(),
+                    array: b.array,
+                },
+                ItemFragment::BindRows(rows) => Fragment::BindRows {
+                    name: &rows.name,
+                    columns: allowed,
+                },
+                ItemFragment::Ident(ident) => Fragment::Ident {
+                    name: &ident.name,
+                    allowed,
+                },
+                ItemFragment::If(name) => Fragment::If(name),
+                ItemFragment::Else => Fragment::Else,
+                ItemFragment::EndIf => Fragment::EndIf,
+                ItemFragment::BindOrder(_) => Fragment::BindOrder(allowed),
+            })
+            .collect();
+        let query = Query {
+            dialect: composer.dialect().builtin().unwrap(),
+            template: "q.sqlc",
+            emit_casts: true,
+            ordering: composer.ordering,
+            fragments: &fragments,
+        };
+        query.compose(values)
+    }
+
+    #[test]
+    fn test_query_matches_composer() {
+        let sql = "SELECT * FROM t WHERE a IN (:bind(ids EXPECTING 1..5)) \
+                   AND b = :bind(b AS int8) AND c = :bind(ids) OR d = :bind(d NULL) \
+                   AND e = :bind(s DEFAULT 'it''s') LIMIT :bind(n AS int8 DEFAULT 10)";
+        let values = crate::bind_values!("ids" => [1, 2, 3], "b" => [4]);
+
+        for dialect in [Dialect::Postgres, Dialect::Sqlite, Dialect::Mysql] {
+            let mut expected = Composer::new(dialect)
+                .compose_with_values(&template(sql), &values)
+                .unwrap();
+            for param in expected.params.values_mut() {
+                param.template = TemplateSource::File("q.sqlc".into());
+            }
+            let rendered = render_with(sql, &Composer::new(dialect), &values).unwrap();
+            assert_eq!(rendered, expected, "{dialect:?}");
+        }
+    }
+
+    #[test]
+    fn test_query_checks_constraints() {
+        let sql = "SELECT :bind(ids EXPECTING 2..3)";
+        let values = crate::bind_values!("ids" => [1]);
+        let err = render_with(sql, &Composer::new(Dialect::Postgres), &values).unwrap_err();
+        assert!(matches!(err, Error::ValueCountOutOfRange { count: 1, .. }));
+
+        let values = BTreeMap::new();
+        let err = render_with(
+            "SELECT :bind(id)",
+            &Composer::new(Dialect::Postgres),
+            &values,
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::NullBinding { .. }));
+    }
+
+    #[test]
+    fn test_query_if_blocks_match_composer() {
+        let sql = "SELECT * FROM t WHERE a = :bind(a) :if(b)AND b = :bind(b) \
+                   :if(c)AND c IN (:bind(c EXPECTING 1..3)) :endif:else AND d = :bind(d) :endif";
+        for values in [
+            crate::bind_values!("a" => [1], "d" => [2]),
+            crate::bind_values!("a" => [1], "b" => [2]),
+            crate::bind_values!("a" => [1], "b" => [2], "c" => [3, 4]),
+        ] {
+            for dialect in [Dialect::Postgres, Dialect::Mysql] {
+                let mut expected = Composer::new(dialect)
+                    .compose_with_values(&template(sql), &values)
+                    .unwrap();
+                for param in expected.params.values_mut() {
+                    param.template = TemplateSource::File("q.sqlc".into());
+                }
+                let rendered = render_with(sql, &Composer::new(dialect), &values).unwrap();
+                assert_eq!(rendered, expected, "{dialect:?}");
+            }
+        }
+
+        // Bindings inside blocks, and bare conditions, become optional fields.
+        let mut module = RustModule::new(Driver::Rusqlite);
+        let sql = "SELECT 1 :if(verbose), :bind(a) :endif :if(b)AND b = :bind(b):endif";
+        module
+            .add(
+                &Composer::new(Dialect::Sqlite),
+                Path::new("q.sqlc"),
+                &template(sql),
+            )
+            .unwrap();
+        let source = module.to_source();
+        assert!(source.contains("    /// `:if(verbose)`\n    pub verbose: Option<Value>,"));
+        assert!(source.contains("    pub a: Option<Value>,"));
+        assert!(source.contains("    pub b: Option<Value>,"));
+        assert!(source.contains("sql_composer::codegen::Fragment::If(\"b\"),"));
+        assert!(source.contains("sql_composer::codegen::Fragment::EndIf,"));
+    }
+
+    #[test]
+    fn test_query_bind_rows_match_composer() {
+        let sql = "INSERT INTO t (a, b) VALUES :bind_rows(items (a, b)) RETURNING :bind(tag)";
+        let values = crate::bind_values!("items" => [1, 2, 3, 4], "tag" => [5]);
+        for dialect in [Dialect::Postgres, Dialect::Sqlite, Dialect::Mysql] {
+            let mut expected = Composer::new(dialect)
+                .compose_with_values(&template(sql), &values)
+                .unwrap();
+            for param in expected.params.values_mut() {
+                param.template = TemplateSource::File("q.sqlc".into());
+            }
+            let rendered = render_with(sql, &Composer::new(dialect), &values).unwrap();
+            assert_eq!(rendered, expected, "{dialect:?}");
+        }
+
+        let mut module = RustModule::new(Driver::Rusqlite);
+        module
+            .add(
+                &Composer::new(Dialect::Sqlite),
+                Path::new("q.sqlc"),
+                &template(sql),
+            )
+            .unwrap();
+        let source = module.to_source();
+        assert!(
+            source.contains("    /// `:bind_rows(items (a, b))`\n    pub items: Vec<[Value; 2]>,")
+        );
+        assert!(source.contains(
+            "values.insert(\"items\".to_string(), self.items.into_iter().flatten().collect());"
+        ));
+        assert!(source.contains(
+            "sql_composer::codegen::Fragment::BindRows { name: \"items\", columns: &[\"a\", \"b\"] },"
+        ));
+    }
+
+    #[test]
+    fn test_query_array_binding_matches_composer() {
+        let sql = "SELECT * FROM t WHERE id IN (:bind(ids AS ARRAY)) AND org = :bind(org)";
+        let values = crate::bind_values!("ids" => [1, 2, 3], "org" => [4]);
+        for dialect in [Dialect::Postgres, Dialect::Sqlite, Dialect::Mysql] {
+            let mut expected = Composer::new(dialect)
+                .compose_with_values(&template(sql), &values)
+                .unwrap();
+            for param in expected.params.values_mut() {
+                param.template = TemplateSource::File("q.sqlc".into());
+            }
+            let rendered = render_with(sql, &Composer::new(dialect), &values).unwrap();
+            assert_eq!(rendered, expected, "{dialect:?}");
+        }
+
+        let mut module = RustModule::new(Driver::Postgres);
+        module
+            .add(
+                &Composer::new(Dialect::Postgres),
+                Path::new("q.sqlc"),
+                &template(sql),
+            )
+            .unwrap();
+        let source = module.to_source();
+        assert!(source.contains("    /// `:bind(ids AS ARRAY)`\n    pub ids: Vec<Value>,"));
+        assert!(source.contains(
+            "Self::QUERY.render_with(values, &std::collections::BTreeMap::new(), sql_composer_postgres::bundle_array)"
+        ));
+
+        let sql = "SELECT * FROM t WHERE id IN (:bind(ids AS ARRAY OF int8))";
+        let mut module = RustModule::new(Driver::Postgres);
+        module
+            .add(
+                &Composer::new(Dialect::Postgres),
+                Path::new("q.sqlc"),
+                &template(sql),
+            )
+            .unwrap();
+        let source = module.to_source();
+        assert!(source.contains("    /// `:bind(ids AS ARRAY OF int8)`\n    pub ids: Vec<i64>,"));
+
+        let sql = "SELECT * FROM t WHERE tags && :bind(tags AS text[] EXPECTING 1..5)";
+        let err = RustModule::new(Driver::Postgres)
+            .add(
+                &Composer::new(Dialect::Postgres),
+                Path::new("q.sqlc"),
+                &template(sql),
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err.root(),
+            Error::ExpandedArrayType { name, .. } if name == "tags"
+        ));
+    }
+
+    #[test]
+    fn test_query_placeholder_ordering_matches_composer() {
+        let sql = ":bind_order(org)\nSELECT * FROM t WHERE id IN (:bind(ids EXPECTING 1..5)) \
+                   :if(b) AND b = :bind(b) :endif AND org = :bind(org)";
+        let values = crate::bind_values!("ids" => [1, 2], "org" => [3], "b" => [4]);
+        for ordering in [
+            PlaceholderOrdering::Alphabetical,
+            PlaceholderOrdering::FirstAppearance,
+            PlaceholderOrdering::Explicit,
+        ] {
+            for dialect in [Dialect::Postgres, Dialect::Mysql] {
+                let mut composer = Composer::new(dialect);
+                composer.ordering = ordering;
+                let mut expected = composer
+                    .compose_with_values(&template(sql), &values)
+                    .unwrap();
+                for param in expected.params.values_mut() {
+                    param.template = TemplateSource::File("q.sqlc".into());
+                }
+                let rendered = render_with(sql, &composer, &values).unwrap();
+                assert_eq!(rendered, expected, "{ordering:?} {dialect:?}");
+            }
+        }
+
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.ordering = PlaceholderOrdering::Explicit;
+        let mut module = RustModule::new(Driver::Postgres);
+        module
+            .add(&composer, Path::new("q.sqlc"), &template(sql))
+            .unwrap();
+        let source = module.to_source();
+        assert!(source.contains("        ordering: sql_composer::PlaceholderOrdering::Explicit,"));
+        assert!(source.contains("sql_composer::codegen::Fragment::BindOrder(&[\"org\"]),"));
+    }
+
+    #[test]
+    fn test_query_substitutes_idents() {
+        let fragments = [
+            Fragment::Sql("SELECT * FROM t ORDER BY "),
+            Fragment::Ident {
+                name: "sort",
+                allowed: &["name", "year"],
+            },
+        ];
+        let query = Query {
+            dialect: Dialect::Mysql,
+            template: "q.sqlc",
+            emit_casts: true,
+            ordering: PlaceholderOrdering::Alphabetical,
+            fragments: &fragments,
+        };
+        let values: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+        let idents = BTreeMap::from([("sort".to_string(), "year".to_string())]);
+        let composed = query.compose_with_idents(&values, &idents).unwrap();
+        assert_eq!(composed.sql, "SELECT * FROM t ORDER BY `year`");
+
+        let idents = BTreeMap::from([("sort".to_string(), "id".to_string())]);
+        let err = query.compose_with_idents(&values, &idents).unwrap_err();
+        assert!(matches!(err, Error::IdentNotAllowed { .. }));
+
+        let mut module = RustModule::new(Driver::Rusqlite);
+        let sql = "SELECT * FROM t ORDER BY :ident(sort ONE OF name, year)";
+        module
+            .add(
+                &Composer::new(Dialect::Sqlite),
+                Path::new("q.sqlc"),
+                &template(sql),
+            )
+            .unwrap();
+        let source = module.to_source();
+        assert!(source.contains("    /// `:ident(sort ONE OF name, year)`\n    pub sort: String,"));
+        assert!(source.contains(
+            "sql_composer::codegen::Fragment::Ident { name: \"sort\", allowed: &[\"name\", \"year\"] },"
+        ));
+        assert!(source.contains("idents.insert(\"sort\".to_string(), self.sort);"));
+        assert!(source.contains(
+            "Self::QUERY.render_with_idents(std::collections::BTreeMap::new(), &idents)"
+        ));
+    }
+
+    #[test]
+    fn test_fragments_resolve_composes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("where.sqlc"), "WHERE id = :bind(id)").unwrap();
+
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.add_search_path(dir.path().to_path_buf());
+        let mut module = RustModule::new(Driver::Postgres);
+        let root = template("SELECT * FROM t :compose(where.sqlc)");
+        module.add(&composer, Path::new("q.sqlc"), &root).unwrap();
+
+        let item = &module.items[Path::new("q.sqlc")];
+        assert_eq!(
+            item.fragments,
+            vec![
+                ItemFragment::Sql("SELECT * FROM t WHERE id = ".into()),
+                ItemFragment::Bind(Binding {
+                    name: "id".into(),
+                    ..Default::default()
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generated_source() {
+        let sql = "SELECT * FROM t WHERE id = :bind(id AS int8) \
+                   AND tag IN (:bind(tags EXPECTING 1)) AND p = :bind(type NULL)";
+        let mut module = RustModule::new(Driver::Rusqlite);
+        let composer = Composer::new(Dialect::Sqlite);
+        module
+            .add(&composer, Path::new("sets/by-id.sqlc"), &template(sql))
+            .unwrap();
+        module
+            .add(&composer, Path::new("all.sqlc"), &template("SELECT 1"))
+            .unwrap();
+        let source = module.to_source();
+
+        assert!(source.contains("pub struct All;"));
+        assert!(source.contains("pub mod sets {"));
+        assert!(source.contains("    pub struct ById {"));
+        assert!(source.contains("        pub id: i64,"));
+        assert!(source.contains("        pub tags: Vec<super::Value>,"));
+        assert!(source.contains("        pub r#type: Option<super::Value>,"));
+        assert!(source.contains(
+            "values.insert(\"id\".to_string(), vec![Box::new(self.id) as super::Value]);"
+        ));
+        assert!(source
+            .contains("values.insert(\"type\".to_string(), self.r#type.into_iter().collect());"));
+        assert!(source.contains("dialect: sql_composer::Dialect::Sqlite,"));
+    }
+
+    #[test]
+    fn test_unsupported_cast_rejected() {
+        let mut module = RustModule::new(Driver::Rusqlite);
+        let err = module
+            .add(
+                &Composer::new(Dialect::Sqlite),
+                Path::new("q.sqlc"),
+                &template("SELECT :bind(ids AS int8[])"),
+            )
+            .unwrap_err();
+        assert!(matches!(err.root(), Error::UnsupportedBindType { .. }));
+    }
+
+    #[test]
+    fn test_custom_dialect_rejected() {
+        struct Numbered;
+        impl SqlDialect for Numbered {
+            fn name(&self) -> &str {
+                "Numbered"
+            }
+            fn placeholder(&self, index: usize) -> String {
+                format!("@p{index}")
+            }
+            fn supports_numbered_placeholders(&self) -> bool {
+                true
+            }
+            fn max_bind_params(&self) -> usize {
+                2100
+            }
+        }
+
+        let mut module = RustModule::new(Driver::Rusqlite);
+        let err = module
+            .add(
+                &Composer::new(Numbered),
+                Path::new("q.sqlc"),
+                &template("SELECT :bind(id)"),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "code generation needs a built-in Dialect, got Numbered"
+        );
+    }
+}
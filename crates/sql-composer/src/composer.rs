@@ -1,14 +1,19 @@
 //! The composer transforms parsed templates into final SQL with dialect-specific
 //! placeholders and resolved compose references.
 
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use crate::error::{Error, Result};
-use crate::mock::MockTable;
-use crate::parser;
+use crate::dialect::{CountDistinct, Paging, SqlDialect};
+use crate::error::{Error, Location, Result};
+use crate::mock::{self, MockTable};
+use crate::store::{Dependency, DependencyGraph, DependencyKind, TemplateStore};
 use crate::types::{
-    Command, CommandKind, ComposeRef, ComposeTarget, Dialect, Element, Template, TemplateSource,
+    BindDefault, BindType, Binding, Command, CommandKind, CommentStyle, ComposeRef, ComposeTarget,
+    Element, Identifier, PlaceholderOrdering, RowBinding, SlotAssignment, Span, Template,
+    TemplateSource,
 };
 
 /// The result of composing a template: final SQL and ordered bind parameter names.
@@ -18,10 +23,117 @@ pub struct ComposedSql {
     pub sql: String,
     /// Ordered list of bind parameter names corresponding to placeholders.
     ///
-    /// For numbered dialects (Postgres, SQLite), names are in alphabetical order
-    /// with duplicates removed. For positional dialects (MySQL), names are in
-    /// document order.
+    /// For numbered dialects (Postgres, SQLite), names are in the composer's
+    /// [`ordering`](Self::ordering) with duplicates removed. For positional
+    /// dialects (MySQL), names are in document order.
     pub bind_params: Vec<String>,
+    /// Every unique binding with its placeholders and declaration, by name.
+    pub params: BTreeMap<String, BindParam>,
+    /// The order placeholders were numbered in;
+    /// [`FirstAppearance`](PlaceholderOrdering::FirstAppearance) for
+    /// positional dialects.
+    pub ordering: PlaceholderOrdering,
+}
+
+impl ComposedSql {
+    /// Build a `ComposedSql` from SQL and its flat placeholder list, for SQL
+    /// composed elsewhere (e.g. at compile time).
+    ///
+    /// Each name's indices are the positions it appears at in `bind_params`.
+    /// No declaration metadata is known: every binding is recorded as one
+    /// untyped, unconstrained occurrence in `template`, and the ordering is
+    /// the default.
+    pub fn from_bind_params(
+        sql: impl Into<String>,
+        bind_params: Vec<String>,
+        template: TemplateSource,
+    ) -> Self {
+        let mut params: BTreeMap<String, BindParam> = BTreeMap::new();
+        for (i, name) in bind_params.iter().enumerate() {
+            params
+                .entry(name.clone())
+                .or_insert_with(|| BindParam {
+                    name: name.clone(),
+                    indices: Vec::new(),
+                    occurrences: 1,
+                    template: template.clone(),
+                    min_values: None,
+                    max_values: None,
+                    nullable: false,
+                    sql_type: None,
+                    default: None,
+                    columns: None,
+                    array: false,
+                })
+                .indices
+                .push(i + 1);
+        }
+        Self {
+            sql: sql.into(),
+            bind_params,
+            params,
+            ordering: PlaceholderOrdering::default(),
+        }
+    }
+
+    /// The type declared for a binding (`:bind(name AS type)`), as recorded
+    /// in [`params`](Self::params).
+    pub fn bind_type(&self, name: &str) -> Option<&BindType> {
+        self.params.get(name)?.sql_type.as_ref()
+    }
+}
+
+/// One statement of a template split by [`Composer::compose_chunked()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComposedChunk {
+    /// The statement, composed for this chunk's share of the values.
+    pub composed: ComposedSql,
+    /// The range of each binding's values this statement binds. Only the
+    /// split binding has a partial range; every other binding is bound in
+    /// full by every chunk.
+    pub ranges: BTreeMap<String, Range<usize>>,
+}
+
+/// A unique binding in a [`ComposedSql`] and the placeholders it fills.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindParam {
+    /// The binding name.
+    pub name: String,
+    /// 1-based indices of the placeholders bound to this name, in order.
+    ///
+    /// On numbered dialects every occurrence shares one run of placeholders,
+    /// one per value. On positional dialects each occurrence gets its own
+    /// run, so a name bound twice lists both. A nullable binding composed
+    /// without values has none.
+    pub indices: Vec<usize>,
+    /// Number of `:bind(name)` occurrences across the composed templates.
+    pub occurrences: usize,
+    /// The template of the first occurrence.
+    pub template: TemplateSource,
+    /// `EXPECTING` minimum of the first occurrence.
+    pub min_values: Option<u32>,
+    /// `EXPECTING` maximum of the first occurrence.
+    pub max_values: Option<u32>,
+    /// Whether the first occurrence is declared `NULL`.
+    pub nullable: bool,
+    /// The type declared by any occurrence (`AS type`), or an array of it
+    /// for `AS ARRAY OF type` where the dialect binds arrays.
+    pub sql_type: Option<BindType>,
+    /// `DEFAULT` value of the first occurrence.
+    ///
+    /// [`Composer::compose_with_values()`] inlines it when no values are
+    /// supplied. [`Composer::compose()`] emits a placeholder that must be
+    /// given a value like any other: [`driver::resolve_values()`](crate::driver::resolve_values)
+    /// does not fall back to the default, and fails with
+    /// [`Error::DefaultNotInlined`] instead.
+    pub default: Option<BindDefault>,
+    /// The columns of each row for a `:bind_rows()` binding, whose values
+    /// are bound flat, row after row; `None` for `:bind()`.
+    pub columns: Option<Vec<String>>,
+    /// Whether the values are bound as one array parameter: declared
+    /// `AS ARRAY` on a dialect that [supports it](SqlDialect::supports_array_binds).
+    /// Such a binding has a single placeholder however many values it has.
+    pub array: bool,
 }
 
 /// Composes parsed templates into final SQL.
@@ -29,42 +141,175 @@ pub struct ComposedSql {
 /// Handles dialect-specific placeholder generation, compose reference resolution,
 /// and mock table substitution.
 pub struct Composer {
-    /// The target database dialect for placeholder syntax.
-    pub dialect: Dialect,
+    /// The target database dialect, set by [`Composer::new()`].
+    dialect: Arc<dyn SqlDialect>,
     /// Directories to search for template files referenced by `:compose()`.
     pub search_paths: Vec<PathBuf>,
     /// Mock tables for test data substitution.
     pub mock_tables: HashMap<String, MockTable>,
+    /// Cache of parsed template files referenced during composition, which
+    /// reads string literals with the dialect's
+    /// [`backslash_escapes`](crate::Capabilities::backslash_escapes).
+    pub templates: TemplateStore,
+    /// Whether typed bindings (`:bind(name AS type)`) are wrapped in a
+    /// dialect cast such as `$1::int8`. Enabled by default; declared types
+    /// are reported by [`ComposedSql::bind_type()`] either way.
+    pub emit_casts: bool,
+    /// The most bind parameters one composed statement may carry. Defaults
+    /// to [`SqlDialect::max_bind_params()`]; lower it for servers configured
+    /// below the dialect's limit.
+    pub max_bind_params: usize,
+    /// How numbered dialects number placeholders. Defaults to
+    /// [`PlaceholderOrdering::Alphabetical`].
+    pub ordering: PlaceholderOrdering,
+    /// Whether errors raised while composing a macro are wrapped in
+    /// [`Error::Located`] with the macro's line, column, source snippet and
+    /// include chain. Enabled by default. Only templates loaded through
+    /// [`Composer::template()`] or a `:compose()` reference can be located.
+    pub locate_errors: bool,
 }
 
 impl Composer {
-    /// Create a new composer with the given dialect.
-    pub fn new(dialect: Dialect) -> Self {
+    /// Create a new composer with the given dialect, e.g.
+    /// `Composer::new(Dialect::Postgres)`.
+    pub fn new(dialect: impl SqlDialect) -> Self {
+        let max_bind_params = dialect.max_bind_params();
+        let backslash_escapes = dialect.capabilities().backslash_escapes;
         Self {
-            dialect,
+            dialect: Arc::new(dialect),
             search_paths: vec![],
             mock_tables: HashMap::new(),
+            templates: TemplateStore::with_syntax(CommentStyle::default(), backslash_escapes),
+            emit_casts: true,
+            max_bind_params,
+            ordering: PlaceholderOrdering::default(),
+            locate_errors: true,
         }
     }
 
+    /// The target database dialect: a built-in [`Dialect`](crate::Dialect) or
+    /// any other [`SqlDialect`].
+    pub fn dialect(&self) -> &dyn SqlDialect {
+        &*self.dialect
+    }
+
     /// Add a search path for resolving compose references.
     pub fn add_search_path(&mut self, path: PathBuf) {
         self.search_paths.push(path);
     }
 
+    /// Recognize template comments as `comments` says, e.g.
+    /// [`CommentStyle::DoubleHash`] to keep Postgres `#>` operators.
+    ///
+    /// Replaces the template cache, since cached templates were parsed with
+    /// the previous style.
+    pub fn set_comments(&mut self, comments: CommentStyle) {
+        let backslash_escapes = self.templates.backslash_escapes();
+        self.templates = TemplateStore::with_syntax(comments, backslash_escapes);
+    }
+
+    /// How template files are parsed for comments.
+    pub fn comments(&self) -> CommentStyle {
+        self.templates.comments()
+    }
+
     /// Register a mock table for test data substitution.
+    ///
+    /// Once registered, every table reference following `FROM`, `JOIN` or a
+    /// comma in a `FROM` list whose name matches the mock, outside quoted
+    /// text and SQL comments, is replaced in composed SQL by the mock's
+    /// generated SELECT as a derived table (`FROM users u` becomes
+    /// `FROM (SELECT ...) u`). This lets production templates run against an
+    /// empty database in tests.
     pub fn add_mock_table(&mut self, mock: MockTable) {
         self.mock_tables.insert(mock.name.clone(), mock);
     }
 
+    /// Load a template file through the composer's cache.
+    ///
+    /// The path is resolved against the search paths like a `:compose()`
+    /// target. The file is parsed on first use and shared afterwards; call
+    /// `self.templates.invalidate()` with the resolved path after it changes.
+    pub fn template(&self, path: &Path) -> Result<Arc<Template>> {
+        let resolved = self.find_template(path)?;
+        self.templates.load(&resolved)
+    }
+
+    /// The dependency graph of every cached template and everything it
+    /// references, transitively.
+    ///
+    /// Load the roots with [`Composer::template()`] (or compose them) first.
+    /// References that cannot be found on the search paths are recorded as
+    /// written, and references that fail to parse are recorded but not
+    /// followed; composing the template reports either error.
+    pub fn dependency_graph(&self) -> Result<DependencyGraph> {
+        let mut graph = DependencyGraph::default();
+        let mut pending = self.templates.paths();
+        let mut seen: HashSet<PathBuf> = pending.iter().cloned().collect();
+
+        while let Some(path) = pending.pop() {
+            let template = self.templates.load(&path)?;
+            let mut dependencies = Vec::new();
+            for element in &template.elements {
+                match element {
+                    Element::Compose(compose_ref) => {
+                        if let ComposeTarget::Path(target) = &compose_ref.target {
+                            dependencies.push((target, DependencyKind::Compose));
+                        }
+                        for slot in &compose_ref.slots {
+                            let kind = DependencyKind::Slot(slot.name.clone());
+                            dependencies.push((&slot.path, kind));
+                        }
+                    }
+                    Element::Command(command) => {
+                        for source in &command.sources {
+                            dependencies.push((source, DependencyKind::Command(command.kind)));
+                        }
+                        for slot in &command.slots {
+                            let kind = DependencyKind::Slot(slot.name.clone());
+                            dependencies.push((&slot.path, kind));
+                        }
+                    }
+                    Element::Sql(_)
+                    | Element::Bind(_)
+                    | Element::BindRows(_)
+                    | Element::Ident(_)
+                    | Element::If(_)
+                    | Element::Else
+                    | Element::EndIf
+                    | Element::BindOrder(_) => {}
+                }
+            }
+
+            let dependencies = dependencies
+                .into_iter()
+                .map(|(target, kind)| {
+                    let path = match self.find_template(target) {
+                        Ok(resolved) => {
+                            let parsed = self.templates.load(&resolved).is_ok();
+                            if parsed && seen.insert(resolved.clone()) {
+                                pending.push(resolved.clone());
+                            }
+                            resolved
+                        }
+                        Err(_) => target.clone(),
+                    };
+                    Dependency { path, kind }
+                })
+                .collect();
+            graph.insert(path, dependencies);
+        }
+
+        Ok(graph)
+    }
+
     /// Compose a template into final SQL with placeholders.
+    ///
+    /// There are no identifiers to substitute, so a template with an
+    /// `:ident()` fails with [`Error::MissingIdent`]; use
+    /// [`Composer::compose_with_idents()`] for those.
     pub fn compose(&self, template: &Template) -> Result<ComposedSql> {
-        let mut visited = HashSet::new();
-        if let TemplateSource::File(ref path) = template.source {
-            visited.insert(path.clone());
-        }
-        let slots = HashMap::new();
-        self.compose_inner(template, &slots, &mut visited)
+        self.compose_flat(template, Inputs::default())
     }
 
     /// Compose a template with value counts, expanding multi-value bindings
@@ -82,17 +327,147 @@ impl Composer {
     /// ```
     ///
     /// For bindings with only one value, behavior is identical to [`Composer::compose()`].
+    ///
+    /// Each `:bind()` occurrence is checked against its declared constraints:
+    /// a value count outside `EXPECTING min..max` fails with
+    /// [`Error::ValueCountOutOfRange`], and a binding with no values fails with
+    /// [`Error::NullBinding`] unless it has a `DEFAULT` or is declared `NULL`.
+    /// In those cases the default, quoted for the dialect (and cast if the
+    /// binding is typed), or the SQL literal `NULL` is emitted in place of a
+    /// placeholder.
+    ///
+    /// A statement needing more than [`Composer::max_bind_params`]
+    /// placeholders fails with [`Error::TooManyBindParams`]; use
+    /// [`Composer::compose_chunked()`] to split it instead.
     pub fn compose_with_values<V>(
         &self,
         template: &Template,
         values: &BTreeMap<String, Vec<V>>,
     ) -> Result<ComposedSql> {
-        let mut visited = HashSet::new();
-        if let TemplateSource::File(ref path) = template.source {
-            visited.insert(path.clone());
+        self.compose_with_idents(template, values, &BTreeMap::new())
+    }
+
+    /// Like [`Composer::compose_with_values()`], also substituting each
+    /// `:ident(name ONE OF ...)` with the identifier `idents` maps `name` to.
+    ///
+    /// The identifier must be in the macro's `ONE OF` list, or composing fails
+    /// with [`Error::IdentNotAllowed`]; a missing one fails with
+    /// [`Error::MissingIdent`]. It is emitted quoted with
+    /// [`SqlDialect::quote_ident()`], except `ASC` and `DESC`, which are emitted
+    /// bare as sort directions.
+    pub fn compose_with_idents<V>(
+        &self,
+        template: &Template,
+        values: &BTreeMap<String, Vec<V>>,
+        idents: &BTreeMap<String, String>,
+    ) -> Result<ComposedSql> {
+        let composed = self.compose_unlimited(template, values, idents)?;
+        let count = composed.bind_params.len();
+        if count > self.max_bind_params {
+            return Err(Error::TooManyBindParams {
+                dialect: self.dialect.name().to_string(),
+                count,
+                limit: self.max_bind_params,
+            });
+        }
+        Ok(composed)
+    }
+
+    /// Like [`Composer::compose_with_values()`], splitting a statement that
+    /// needs more than [`Composer::max_bind_params`] placeholders into
+    /// several that each fit.
+    ///
+    /// The binding with the most placeholders, typically the list behind an
+    /// `IN (:bind(ids))` or a bulk `:bind_rows()`, is split into runs of
+    /// values (whole rows for `:bind_rows()`); every other binding is
+    /// repeated in each statement. Each [`ComposedChunk`] records the range
+    /// of values it binds, for [`driver::resolve_chunk_values()`](crate::driver::resolve_chunk_values).
+    /// A statement that fits comes back as a single chunk. Fails with
+    /// [`Error::TooManyBindParams`] if the other bindings alone leave no
+    /// room.
+    ///
+    /// Only split statements whose results can be concatenated, such as
+    /// lookups by key and bulk inserts.
+    pub fn compose_chunked<V>(
+        &self,
+        template: &Template,
+        values: &BTreeMap<String, Vec<V>>,
+    ) -> Result<Vec<ComposedChunk>> {
+        self.compose_chunked_with_idents(template, values, &BTreeMap::new())
+    }
+
+    /// Like [`Composer::compose_chunked()`], also substituting identifiers
+    /// as [`Composer::compose_with_idents()`] does.
+    pub fn compose_chunked_with_idents<V>(
+        &self,
+        template: &Template,
+        values: &BTreeMap<String, Vec<V>>,
+        idents: &BTreeMap<String, String>,
+    ) -> Result<Vec<ComposedChunk>> {
+        let mut counts: BTreeMap<String, Vec<()>> = values
+            .iter()
+            .map(|(name, vs)| (name.clone(), vec![(); vs.len()]))
+            .collect();
+        let mut ranges: BTreeMap<String, Range<usize>> = values
+            .iter()
+            .map(|(name, vs)| (name.clone(), 0..vs.len()))
+            .collect();
+
+        let composed = self.compose_unlimited(template, &counts, idents)?;
+        let total = composed.bind_params.len();
+        if total <= self.max_bind_params {
+            return Ok(vec![ComposedChunk { composed, ranges }]);
+        }
+
+        // Split the binding with the most placeholders. Each of its values
+        // takes one placeholder per occurrence on positional dialects, and
+        // one in all on numbered dialects.
+        let too_many = || Error::TooManyBindParams {
+            dialect: self.dialect.name().to_string(),
+            count: total,
+            limit: self.max_bind_params,
+        };
+        let (name, param) = composed
+            .params
+            .iter()
+            .max_by_key(|(_, param)| param.indices.len())
+            .ok_or_else(too_many)?;
+        let len = Self::value_count(values, name);
+        let per_value = param.indices.len() / len.max(1);
+        let fixed = total - param.indices.len();
+        let width = param.columns.as_ref().map_or(1, Vec::len);
+        let room = self.max_bind_params.saturating_sub(fixed) / per_value.max(1);
+        let size = room - room % width;
+        if size == 0 {
+            return Err(too_many());
+        }
+
+        let mut chunks = Vec::new();
+        for start in (0..len).step_by(size) {
+            let end = (start + size).min(len);
+            counts.insert(name.clone(), vec![(); end - start]);
+            ranges.insert(name.clone(), start..end);
+            chunks.push(ComposedChunk {
+                composed: self.compose_unlimited(template, &counts, idents)?,
+                ranges: ranges.clone(),
+            });
         }
-        let slots = HashMap::new();
-        self.compose_with_values_inner(template, values, &slots, &mut visited)
+        Ok(chunks)
+    }
+
+    /// [`Composer::compose_with_idents()`] without the bind parameter limit.
+    fn compose_unlimited<V>(
+        &self,
+        template: &Template,
+        values: &BTreeMap<String, Vec<V>>,
+        idents: &BTreeMap<String, String>,
+    ) -> Result<ComposedSql> {
+        let counts = Self::value_counts(values);
+        let inputs = Inputs {
+            counts: Some(&counts),
+            idents: Some(idents),
+        };
+        self.compose_flat(template, inputs)
     }
 
     // ── Slot helpers ─────────────────────────────────────────────────
@@ -114,602 +489,642 @@ impl Composer {
         }
     }
 
-    /// Build the child slot map from a compose reference's slot assignments.
+    /// Build the child slot map from the slot assignments of a compose
+    /// reference or command.
     ///
     /// These are the ONLY slots the child template sees — parent slots are
     /// NOT inherited.
-    fn build_child_slots(compose_ref: &ComposeRef) -> HashMap<String, PathBuf> {
-        compose_ref
-            .slots
+    fn build_child_slots(slots: &[SlotAssignment]) -> HashMap<String, PathBuf> {
+        slots
             .iter()
             .map(|s| (s.name.clone(), s.path.clone()))
             .collect()
     }
 
-    // ── Dispatch ──────────────────────────────────────────────────────
-
-    fn compose_inner(
-        &self,
-        template: &Template,
-        slots: &HashMap<String, PathBuf>,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
+    // ── Flattening ────────────────────────────────────────────────────
+    //
+    // Every dialect composes in two steps:
+    //   Flatten — expand `:compose()` references and commands in place,
+    //             leaving SQL, bindings, identifiers and block markers
+    //   Emit    — pick `:if` branches, check and number the bindings and
+    //             write the SQL, numbered or positional
+
+    /// Compose `template` with the given inputs.
+    fn compose_flat(&self, template: &Template, inputs: Inputs<'_>) -> Result<ComposedSql> {
+        let flat = self.flatten(template)?;
         if self.dialect.supports_numbered_placeholders() {
-            self.compose_inner_numbered(template, slots, visited)
+            self.emit_numbered(&flat, inputs)
         } else {
-            self.compose_inner_positional(template, slots, visited)
+            self.emit_positional(&flat, inputs)
         }
     }
 
-    fn compose_with_values_inner<V>(
-        &self,
-        template: &Template,
-        values: &BTreeMap<String, Vec<V>>,
-        slots: &HashMap<String, PathBuf>,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
-        if self.dialect.supports_numbered_placeholders() {
-            self.compose_with_values_numbered(template, values, slots, visited)
-        } else {
-            self.compose_with_values_positional(template, values, slots, visited)
+    /// Expand the `:compose()` references and commands of `template` in
+    /// place, recording the template each resulting element came from.
+    pub(crate) fn flatten(&self, template: &Template) -> Result<Flat> {
+        let mut visited = HashSet::new();
+        if let TemplateSource::File(ref path) = template.source {
+            visited.insert(path.clone());
         }
+        let mut flat = Flat::default();
+        let source_map = self.source_map(template);
+        let scope = flat.scope(template, source_map, vec![template.source.to_string()]);
+        self.flatten_into(&mut flat, template, scope, &HashMap::new(), &mut visited)?;
+        Ok(flat)
     }
 
-    // ── Numbered path (Postgres, SQLite) ──────────────────────────────
-    //
-    // Two-pass approach:
-    //   Pass 1 — collect all unique bind names (BTreeSet gives alphabetical order)
-    //   Allocate — assign 1-based indices from the sorted names
-    //   Pass 2 — emit SQL using the global index map (same name → same $N)
+    /// The text and element spans of `template` from the template cache, if
+    /// errors are located and it was loaded through the cache.
+    fn source_map(&self, template: &Template) -> Option<(Arc<str>, Arc<[Span]>)> {
+        let TemplateSource::File(path) = &template.source else {
+            return None;
+        };
+        if !self.locate_errors {
+            return None;
+        }
+        self.templates
+            .source_map(path)
+            .filter(|(_, spans)| spans.len() == template.elements.len())
+    }
 
-    /// Pass 1: Recursively collect unique bind names from a template tree.
-    fn collect_bind_names(
+    /// Append the elements of `template`, read as `scope`, to `flat`.
+    fn flatten_into(
         &self,
+        flat: &mut Flat,
         template: &Template,
+        scope: usize,
         slots: &HashMap<String, PathBuf>,
         visited: &mut HashSet<PathBuf>,
-    ) -> Result<BTreeSet<String>> {
-        let mut names = BTreeSet::new();
-
-        for element in &template.elements {
+    ) -> Result<()> {
+        for (index, element) in template.elements.iter().enumerate() {
+            let origin = Origin { scope, index };
             match element {
-                Element::Sql(_) => {}
-                Element::Bind(binding) => {
-                    names.insert(binding.name.clone());
-                }
+                Element::Sql(text) => flat.push(Element::Sql(self.literal_sql(text)), origin),
                 Element::Compose(compose_ref) => {
-                    let path = Self::resolve_compose_target(compose_ref, slots)?;
-                    let child_slots = Self::build_child_slots(compose_ref);
-                    let sub =
-                        self.collect_compose_bind_names(&path, &child_slots, visited)?;
-                    names.extend(sub);
+                    let frame = compose_ref.target.to_string();
+                    self.flatten_compose(flat, compose_ref, origin, slots, visited)
+                        .map_err(|e| flat.locate(origin, e, Some(frame)))?;
                 }
                 Element::Command(command) => {
-                    let sub = self.collect_command_bind_names(command, visited)?;
-                    names.extend(sub);
+                    self.flatten_command(flat, command, origin, visited)
+                        .map_err(|e| flat.locate(origin, e, None))?;
                 }
+                _ => flat.push(element.clone(), origin),
             }
         }
-
-        Ok(names)
+        Ok(())
     }
 
-    /// Collect bind names from a compose reference's resolved template.
-    fn collect_compose_bind_names(
+    /// Append the template a compose reference at `origin` resolves to.
+    ///
+    /// The slots it assigns are the ONLY slots the child template sees —
+    /// parent slots are NOT inherited.
+    fn flatten_compose(
         &self,
-        path: &Path,
-        child_slots: &HashMap<String, PathBuf>,
+        flat: &mut Flat,
+        compose_ref: &ComposeRef,
+        origin: Origin,
+        slots: &HashMap<String, PathBuf>,
         visited: &mut HashSet<PathBuf>,
-    ) -> Result<BTreeSet<String>> {
-        let resolved = self.find_template(path)?;
+    ) -> Result<()> {
+        let path = Self::resolve_compose_target(compose_ref, slots)?;
+        let resolved = self.find_template(&path)?;
 
         if !visited.insert(resolved.clone()) {
-            return Err(Error::CircularReference {
-                path: path.to_path_buf(),
-            });
+            return Err(Error::CircularReference { path });
         }
 
-        let template = parser::parse_template_file(&resolved)?;
-        let names = self.collect_bind_names(&template, child_slots, visited)?;
+        let template = self.templates.load(&resolved)?;
+        let chain = flat.chain(origin, compose_ref.target.to_string());
+        let scope = flat.scope(&template, self.source_map(&template), chain);
+        let child_slots = Self::build_child_slots(&compose_ref.slots);
+        self.flatten_into(flat, &template, scope, &child_slots, visited)?;
 
         visited.remove(&resolved);
-        Ok(names)
+        Ok(())
     }
 
-    /// Collect bind names from all sources in a command.
+    /// Append a command at `origin` (count, a set operation, an existence
+    /// test, a JSON aggregate or a page): its sources, wrapped in the SQL
+    /// the dialect writes the command with.
     ///
-    /// Command sources are standalone templates — they get empty slots.
-    fn collect_command_bind_names(
+    /// Command sources see only the command's own slot assignments.
+    fn flatten_command(
         &self,
+        flat: &mut Flat,
         command: &Command,
+        origin: Origin,
         visited: &mut HashSet<PathBuf>,
-    ) -> Result<BTreeSet<String>> {
-        let mut names = BTreeSet::new();
-        let empty_slots = HashMap::new();
-        for source in &command.sources {
-            let resolved = self.find_template(source)?;
-            let template = parser::parse_template_file(&resolved)?;
-            let sub = self.collect_bind_names(&template, &empty_slots, visited)?;
-            names.extend(sub);
-        }
-        Ok(names)
-    }
-
-    /// Build an index map for `compose` (single-value bindings).
-    /// Each name maps to `(1-based-index, 1)`.
-    fn build_index_map(names: &BTreeSet<String>) -> BTreeMap<String, (usize, usize)> {
-        names
-            .iter()
-            .enumerate()
-            .map(|(i, name)| (name.clone(), (i + 1, 1)))
-            .collect()
+    ) -> Result<()> {
+        if matches!(
+            command.kind,
+            CommandKind::Union | CommandKind::Intersect | CommandKind::Except
+        ) {
+            return self.flatten_set_operation(flat, command, origin, visited);
+        }
+
+        let (head, tail) = command_wrapper(&*self.dialect, command)?;
+        flat.push(Element::Sql(head), origin);
+
+        // If multiple sources, wrap a union first
+        if command.sources.len() > 1 {
+            let union_cmd = Command {
+                kind: CommandKind::Union,
+                distinct: command.distinct,
+                all: command.all,
+                keyset: false,
+                columns: None,
+                descending: Vec::new(),
+                sources: command.sources.clone(),
+                slots: command.slots.clone(),
+            };
+            self.flatten_set_operation(flat, &union_cmd, origin, visited)?;
+        } else {
+            let slots = Self::build_child_slots(&command.slots);
+            self.flatten_source(flat, &command.sources[0], origin, &slots, visited)?;
+        }
+
+        flat.push(Element::Sql(tail), origin);
+        if command.kind == CommandKind::Paginate {
+            let clause = page_clause(&*self.dialect, command)?;
+            for element in clause.elements {
+                flat.push(element, origin);
+            }
+        }
+        Ok(())
     }
 
-    /// Build an index map for `compose_with_values` (multi-value bindings).
-    /// Each name maps to `(start_index, count)` where count comes from the
-    /// values map (defaults to 1 if absent).
-    fn build_index_map_with_values<V>(
-        names: &BTreeSet<String>,
-        values: &BTreeMap<String, Vec<V>>,
-    ) -> BTreeMap<String, (usize, usize)> {
-        let mut map = BTreeMap::new();
-        let mut index = 1;
-        for name in names {
-            let count = values.get(name).map(|vs| vs.len()).unwrap_or(1).max(1);
-            map.insert(name.clone(), (index, count));
-            index += count;
+    /// Append the sources of a UNION, INTERSECT or EXCEPT command at
+    /// `origin`, joined by its operator.
+    fn flatten_set_operation(
+        &self,
+        flat: &mut Flat,
+        command: &Command,
+        origin: Origin,
+        visited: &mut HashSet<PathBuf>,
+    ) -> Result<()> {
+        let operator = set_operator(&*self.dialect, command)?;
+
+        let slots = Self::build_child_slots(&command.slots);
+        for (i, source) in command.sources.iter().enumerate() {
+            if i > 0 {
+                flat.trim_end();
+                flat.push(Element::Sql(format!("\n{operator}\n")), origin);
+            }
+            self.flatten_source(flat, source, origin, &slots, visited)?;
         }
-        map
+        Ok(())
     }
 
-    /// Two-pass compose for numbered dialects (single-value).
-    fn compose_inner_numbered(
+    /// Append the template a command source resolves to.
+    fn flatten_source(
         &self,
-        template: &Template,
+        flat: &mut Flat,
+        source: &Path,
+        origin: Origin,
         slots: &HashMap<String, PathBuf>,
         visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
-        // Pass 1: collect
-        let mut collect_visited = visited.clone();
-        let names = self.collect_bind_names(template, slots, &mut collect_visited)?;
+    ) -> Result<()> {
+        let resolved = self.find_template(source)?;
 
-        // Allocate
-        let index_map = Self::build_index_map(&names);
-        let bind_params: Vec<String> = names.into_iter().collect();
+        if !visited.insert(resolved.clone()) {
+            return Err(Error::CircularReference {
+                path: source.to_path_buf(),
+            });
+        }
 
-        // Pass 2: emit
-        let mut sql = String::new();
-        self.emit_sql_numbered(template, &index_map, &mut sql, slots, visited)?;
+        let template = self.templates.load(&resolved)?;
+        let chain = flat.chain(origin, source.display().to_string());
+        let scope = flat.scope(&template, self.source_map(&template), chain);
+        self.flatten_into(flat, &template, scope, slots, visited)?;
 
-        Ok(ComposedSql { sql, bind_params })
+        visited.remove(&resolved);
+        Ok(())
     }
 
-    /// Two-pass compose for numbered dialects (multi-value).
-    fn compose_with_values_numbered<V>(
-        &self,
-        template: &Template,
-        values: &BTreeMap<String, Vec<V>>,
-        slots: &HashMap<String, PathBuf>,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
+    // ── Numbered emission (Postgres, SQLite) ──────────────────────────
+    //
+    // Two-pass approach:
+    //   Pass 1 — collect all unique bind names, in order of first appearance
+    //   Allocate — assign 1-based indices in the composer's placeholder ordering
+    //   Pass 2 — emit SQL using the global index map (same name → same $N)
+
+    /// Emit a flattened template with numbered placeholders.
+    ///
+    /// With value counts, every binding occurrence is checked against its
+    /// `EXPECTING`/`NULL` constraints and takes one placeholder per value.
+    fn emit_numbered(&self, flat: &Flat, inputs: Inputs<'_>) -> Result<ComposedSql> {
+        let active = Self::active(&flat.elements, inputs.counts);
+
         // Pass 1: collect
-        let mut collect_visited = visited.clone();
-        let names = self.collect_bind_names(template, slots, &mut collect_visited)?;
+        let mut collected = Collected::default();
+        for &(at, element) in &active {
+            let locate = |e| flat.locate_element(at, e);
+            let source = flat.source(at);
+            match element {
+                Element::BindOrder(names) => collected.declared.extend(names.iter().cloned()),
+                Element::Bind(binding) => {
+                    if let Some(counts) = inputs.counts {
+                        let count = counts.get(&binding.name).copied().unwrap_or(0);
+                        Self::check_binding(binding, source, count).map_err(locate)?;
+                    }
+                    collected.see(&binding.name);
+                    let params = &mut collected.params;
+                    Self::record_param(params, binding, source, &*self.dialect).map_err(locate)?;
+                }
+                Element::BindRows(rows) => {
+                    if let Some(counts) = inputs.counts {
+                        let count = counts.get(&rows.name).copied().unwrap_or(0);
+                        Self::check_rows(rows, source, count).map_err(locate)?;
+                    }
+                    collected.see(&rows.name);
+                    let params = &mut collected.params;
+                    Self::record_rows(params, rows, source, &*self.dialect).map_err(locate)?;
+                }
+                _ => {}
+            }
+        }
+        let names = collected.ordered(self.ordering);
+        let mut params = collected.params;
 
-        // Allocate with value counts
-        let index_map = Self::build_index_map_with_values(&names, values);
+        // Allocate
+        let index_map = Self::build_index_map(&params, &names, inputs.counts);
+        Self::assign_indices(&mut params, &index_map);
 
-        // Build bind_params: each name repeated by its value count, alphabetical
+        // Build bind_params: each name repeated by its placeholder count, in order
         let mut bind_params = Vec::new();
         for name in &names {
-            let count = values
-                .get(name.as_str())
-                .map(|vs| vs.len())
-                .unwrap_or(1)
-                .max(1);
-            for _ in 0..count {
+            for _ in 0..index_map[name].1 {
                 bind_params.push(name.clone());
             }
         }
 
         // Pass 2: emit
         let mut sql = String::new();
-        self.emit_sql_numbered(template, &index_map, &mut sql, slots, visited)?;
-
-        Ok(ComposedSql { sql, bind_params })
-    }
-
-    /// Pass 2: Emit SQL for a template using the global index map.
-    fn emit_sql_numbered(
-        &self,
-        template: &Template,
-        index_map: &BTreeMap<String, (usize, usize)>,
-        sql: &mut String,
-        slots: &HashMap<String, PathBuf>,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<()> {
-        for element in &template.elements {
+        for (at, element) in active {
+            let locate = |e| flat.locate_element(at, e);
             match element {
                 Element::Sql(text) => sql.push_str(text),
+                Element::Ident(ident) => {
+                    let ident = self.identifier(ident, inputs.idents, flat.source(at));
+                    sql.push_str(&ident.map_err(locate)?);
+                }
                 Element::Bind(binding) => {
                     let &(start, count) = &index_map[&binding.name];
+                    if count == 0 {
+                        let value = no_values(&*self.dialect, binding, self.emit_casts);
+                        sql.push_str(&value.map_err(&locate)?);
+                    }
+                    if count > 0 && binding.array && self.dialect.supports_array_binds() {
+                        array_operator(&mut sql);
+                    }
                     for i in 0..count {
                         if i > 0 {
                             sql.push_str(", ");
                         }
-                        sql.push_str(&self.dialect.placeholder(start + i));
+                        sql.push_str(&self.placeholder(binding, start + i, i).map_err(locate)?);
                     }
                 }
-                Element::Compose(compose_ref) => {
-                    let path = Self::resolve_compose_target(compose_ref, slots)?;
-                    let child_slots = Self::build_child_slots(compose_ref);
-                    self.emit_compose_numbered(
-                        &path,
-                        &child_slots,
-                        index_map,
-                        sql,
-                        visited,
-                    )?;
-                }
-                Element::Command(command) => {
-                    self.emit_command_numbered(command, index_map, sql, visited)?;
+                Element::BindRows(rows) => {
+                    let &(start, count) = &index_map[&rows.name];
+                    sql.push_str(&self.rows(rows, start, count));
                 }
+                // Dropped by `active()`, or expanded by `flatten()`.
+                Element::If(_)
+                | Element::Else
+                | Element::EndIf
+                | Element::BindOrder(_)
+                | Element::Compose(_)
+                | Element::Command(_) => {}
             }
         }
-        Ok(())
-    }
 
-    /// Emit SQL for a compose reference using the global index map.
-    fn emit_compose_numbered(
-        &self,
-        path: &Path,
-        child_slots: &HashMap<String, PathBuf>,
-        index_map: &BTreeMap<String, (usize, usize)>,
-        sql: &mut String,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<()> {
-        let resolved = self.find_template(path)?;
+        Ok(ComposedSql {
+            sql,
+            bind_params,
+            params,
+            ordering: self.ordering,
+        })
+    }
 
-        if !visited.insert(resolved.clone()) {
-            return Err(Error::CircularReference {
-                path: path.to_path_buf(),
-            });
+    /// Allocate placeholder indices in the order of `names`.
+    ///
+    /// Each name maps to `(start_index, count)`. Without value counts that
+    /// is one placeholder, or one row of placeholders for a `:bind_rows()`
+    /// binding. With them it is one per value: a count of 0 marks a binding
+    /// with no values, which is emitted as its default or `NULL` and
+    /// consumes no placeholder, and an array binding takes a single
+    /// placeholder for all its values.
+    fn build_index_map(
+        params: &BTreeMap<String, BindParam>,
+        names: &[String],
+        counts: Option<&BTreeMap<String, usize>>,
+    ) -> BTreeMap<String, (usize, usize)> {
+        let mut map = BTreeMap::new();
+        let mut index = 1;
+        for name in names {
+            let param = &params[name];
+            let count = match counts {
+                None => param.columns.as_ref().map_or(1, Vec::len),
+                Some(counts) => {
+                    let count = counts.get(name).copied().unwrap_or(0);
+                    if param.array {
+                        count.min(1)
+                    } else {
+                        count
+                    }
+                }
+            };
+            map.insert(name.clone(), (index, count));
+            index += count;
         }
-
-        let template = parser::parse_template_file(&resolved)?;
-        self.emit_sql_numbered(&template, index_map, sql, child_slots, visited)?;
-
-        visited.remove(&resolved);
-        Ok(())
+        map
     }
 
-    /// Emit SQL for a command (union/count) using the global index map.
-    ///
-    /// Command sources are standalone templates — they get empty slots.
-    fn emit_command_numbered(
-        &self,
-        command: &Command,
+    /// Record the placeholder indices allocated in `index_map` on `params`.
+    fn assign_indices(
+        params: &mut BTreeMap<String, BindParam>,
         index_map: &BTreeMap<String, (usize, usize)>,
-        sql: &mut String,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<()> {
-        match command.kind {
-            CommandKind::Union => self.emit_union_numbered(command, index_map, sql, visited),
-            CommandKind::Count => self.emit_count_numbered(command, index_map, sql, visited),
+    ) {
+        for (name, param) in params {
+            let (start, count) = index_map[name];
+            param.indices = (start..start + count).collect();
         }
     }
 
-    /// Emit SQL for a UNION command using the global index map.
-    fn emit_union_numbered(
-        &self,
-        command: &Command,
-        index_map: &BTreeMap<String, (usize, usize)>,
-        sql: &mut String,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<()> {
-        let union_kw = if command.all {
-            "UNION ALL"
-        } else if command.distinct {
-            "UNION DISTINCT"
-        } else {
-            "UNION"
-        };
-
-        let empty_slots = HashMap::new();
-        for (i, source) in command.sources.iter().enumerate() {
-            if i > 0 {
-                let trimmed = sql.trim_end().len();
-                sql.truncate(trimmed);
-                sql.push_str(&format!("\n{union_kw}\n"));
-            }
-            let resolved = self.find_template(source)?;
-            let template = parser::parse_template_file(&resolved)?;
-            self.emit_sql_numbered(&template, index_map, sql, &empty_slots, visited)?;
-        }
-
-        Ok(())
-    }
-
-    /// Emit SQL for a COUNT command using the global index map.
-    fn emit_count_numbered(
-        &self,
-        command: &Command,
-        index_map: &BTreeMap<String, (usize, usize)>,
-        sql: &mut String,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<()> {
-        let columns = match &command.columns {
-            Some(cols) => cols.join(", "),
-            None => "*".to_string(),
-        };
-
-        let count_expr = if command.distinct {
-            format!("COUNT(DISTINCT {columns})")
-        } else {
-            format!("COUNT({columns})")
-        };
-
-        sql.push_str(&format!("SELECT {count_expr} FROM (\n"));
-
-        let empty_slots = HashMap::new();
-        if command.sources.len() > 1 {
-            let union_cmd = Command {
-                kind: CommandKind::Union,
-                distinct: command.distinct,
-                all: command.all,
-                columns: None,
-                sources: command.sources.clone(),
-            };
-            self.emit_union_numbered(&union_cmd, index_map, sql, visited)?;
-        } else {
-            let source = &command.sources[0];
-            let resolved = self.find_template(source)?;
-            let template = parser::parse_template_file(&resolved)?;
-            self.emit_sql_numbered(&template, index_map, sql, &empty_slots, visited)?;
-        }
-
-        sql.push_str("\n) AS _count_sub");
-        Ok(())
-    }
-
-    // ── Positional path (MySQL) ───────────────────────────────────────
+    // ── Positional emission (MySQL) ───────────────────────────────────
     //
-    // Document-order placeholders with bare `?`. No reindexing needed
-    // since MySQL placeholders carry no index number.
-
-    fn compose_inner_positional(
-        &self,
-        template: &Template,
-        slots: &HashMap<String, PathBuf>,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
-        let mut sql = String::new();
-        let mut bind_params = Vec::new();
-
-        for element in &template.elements {
-            match element {
-                Element::Sql(text) => {
-                    sql.push_str(text);
-                }
-                Element::Bind(binding) => {
-                    let index = bind_params.len() + 1;
-                    sql.push_str(&self.dialect.placeholder(index));
-                    bind_params.push(binding.name.clone());
-                }
-                Element::Compose(compose_ref) => {
-                    let path = Self::resolve_compose_target(compose_ref, slots)?;
-                    let child_slots = Self::build_child_slots(compose_ref);
-                    let composed =
-                        self.resolve_compose_positional(&path, &child_slots, visited)?;
-                    sql.push_str(&composed.sql);
-                    bind_params.extend(composed.bind_params);
-                }
-                Element::Command(command) => {
-                    let composed = self.compose_command(command, visited)?;
-                    sql.push_str(&composed.sql);
-                    bind_params.extend(composed.bind_params);
-                }
-            }
-        }
-
-        Ok(ComposedSql { sql, bind_params })
-    }
+    // Document-order placeholders: every occurrence of a binding takes
+    // placeholders of its own, numbered as they are written.
 
-    fn compose_with_values_positional<V>(
-        &self,
-        template: &Template,
-        values: &BTreeMap<String, Vec<V>>,
-        slots: &HashMap<String, PathBuf>,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
+    /// Emit a flattened template with positional placeholders.
+    ///
+    /// With value counts, every binding occurrence is checked against its
+    /// `EXPECTING`/`NULL` constraints and takes one placeholder per value.
+    fn emit_positional(&self, flat: &Flat, inputs: Inputs<'_>) -> Result<ComposedSql> {
         let mut sql = String::new();
         let mut bind_params = Vec::new();
+        let mut params = BTreeMap::new();
 
-        for element in &template.elements {
+        for (at, element) in Self::active(&flat.elements, inputs.counts) {
+            let locate = |e| flat.locate_element(at, e);
+            let source = flat.source(at);
             match element {
-                Element::Sql(text) => {
-                    sql.push_str(text);
+                Element::Sql(text) => sql.push_str(text),
+                Element::Ident(ident) => {
+                    let ident = self.identifier(ident, inputs.idents, source);
+                    sql.push_str(&ident.map_err(locate)?);
                 }
                 Element::Bind(binding) => {
-                    let count = values
-                        .get(&binding.name)
-                        .map(|vs| vs.len())
-                        .unwrap_or(1)
-                        .max(1);
-
+                    let count = match inputs.counts {
+                        Some(counts) => {
+                            let count = counts.get(&binding.name).copied().unwrap_or(0);
+                            Self::check_binding(binding, source, count).map_err(&locate)?;
+                            count
+                        }
+                        None => 1,
+                    };
+                    let param = Self::record_param(&mut params, binding, source, &*self.dialect)
+                        .map_err(&locate)?;
+
+                    if count == 0 {
+                        let value = no_values(&*self.dialect, binding, self.emit_casts);
+                        sql.push_str(&value.map_err(&locate)?);
+                    }
                     for i in 0..count {
                         if i > 0 {
                             sql.push_str(", ");
                         }
                         let index = bind_params.len() + 1;
-                        sql.push_str(&self.dialect.placeholder(index));
+                        sql.push_str(&self.placeholder(binding, index, i).map_err(&locate)?);
+                        param.indices.push(index);
                         bind_params.push(binding.name.clone());
                     }
                 }
-                Element::Compose(compose_ref) => {
-                    let path = Self::resolve_compose_target(compose_ref, slots)?;
-                    let child_slots = Self::build_child_slots(compose_ref);
-                    let composed = self.resolve_compose_with_values_positional(
-                        &path,
-                        &child_slots,
-                        values,
-                        visited,
-                    )?;
-                    sql.push_str(&composed.sql);
-                    bind_params.extend(composed.bind_params);
-                }
-                Element::Command(command) => {
-                    let composed = self.compose_command(command, visited)?;
-                    sql.push_str(&composed.sql);
-                    bind_params.extend(composed.bind_params);
+                Element::BindRows(rows) => {
+                    let count = match inputs.counts {
+                        Some(counts) => {
+                            let count = counts.get(&rows.name).copied().unwrap_or(0);
+                            Self::check_rows(rows, source, count).map_err(&locate)?;
+                            count
+                        }
+                        None => rows.columns.len(),
+                    };
+                    let param = Self::record_rows(&mut params, rows, source, &*self.dialect)
+                        .map_err(&locate)?;
+
+                    let start = bind_params.len() + 1;
+                    sql.push_str(&self.rows(rows, start, count));
+                    param.indices.extend(start..start + count);
+                    bind_params.extend(std::iter::repeat_n(rows.name.clone(), count));
                 }
+                // Dropped by `active()`, or expanded by `flatten()`.
+                Element::If(_)
+                | Element::Else
+                | Element::EndIf
+                | Element::BindOrder(_)
+                | Element::Compose(_)
+                | Element::Command(_) => {}
             }
         }
 
-        Ok(ComposedSql { sql, bind_params })
+        Ok(ComposedSql {
+            sql,
+            bind_params,
+            params,
+            ordering: PlaceholderOrdering::FirstAppearance,
+        })
     }
 
-    /// Resolve a compose reference by finding and parsing the template file (positional).
-    fn resolve_compose_positional(
-        &self,
-        path: &Path,
-        child_slots: &HashMap<String, PathBuf>,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
-        let resolved = self.find_template(path)?;
+    // ── Conditional blocks ────────────────────────────────────────────
 
-        if !visited.insert(resolved.clone()) {
-            return Err(Error::CircularReference {
-                path: path.to_path_buf(),
-            });
+    /// The elements to compose, with their indices: everything
+    /// outside `:if` blocks and the taken branch of each block.
+    ///
+    /// A block is taken when its binding has values in `counts`; without
+    /// counts, as in [`Composer::compose()`], every `:if` branch is taken.
+    /// Block markers themselves are dropped.
+    fn active<'t>(
+        elements: &'t [Element],
+        counts: Option<&BTreeMap<String, usize>>,
+    ) -> Vec<(usize, &'t Element)> {
+        let mut blocks = Blocks::default();
+        let mut active = Vec::new();
+        for (index, element) in elements.iter().enumerate() {
+            match element {
+                Element::If(name) => {
+                    blocks.open(counts.is_none_or(|counts| counts.get(name) > Some(&0)));
+                }
+                Element::Else => blocks.flip(),
+                Element::EndIf => blocks.close(),
+                _ if blocks.active() => active.push((index, element)),
+                _ => {}
+            }
         }
+        active
+    }
 
-        let template = parser::parse_template_file(&resolved)?;
-        let result = self.compose_inner_positional(&template, child_slots, visited)?;
-
-        visited.remove(&resolved);
+    // ── Shared helpers ────────────────────────────────────────────────
 
-        Ok(result)
+    /// Number of values supplied for each binding name.
+    fn value_counts<V>(values: &BTreeMap<String, Vec<V>>) -> BTreeMap<String, usize> {
+        values
+            .iter()
+            .map(|(name, vs)| (name.clone(), vs.len()))
+            .collect()
     }
 
-    /// Resolve a compose reference with value-aware expansion (positional).
-    fn resolve_compose_with_values_positional<V>(
-        &self,
-        path: &Path,
-        child_slots: &HashMap<String, PathBuf>,
-        values: &BTreeMap<String, Vec<V>>,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
-        let resolved = self.find_template(path)?;
+    /// Number of values supplied for a binding name (0 if absent).
+    fn value_count<V>(values: &BTreeMap<String, Vec<V>>, name: &str) -> usize {
+        values.get(name).map(|vs| vs.len()).unwrap_or(0)
+    }
 
-        if !visited.insert(resolved.clone()) {
-            return Err(Error::CircularReference {
-                path: path.to_path_buf(),
+    /// Check a binding occurrence against its `EXPECTING` and `NULL` constraints.
+    ///
+    /// A count of 0 means no values were supplied: allowed only for nullable
+    /// bindings and bindings with a `DEFAULT`. Otherwise the count must fall within `min_values..=max_values`.
+    fn check_binding(binding: &Binding, source: &TemplateSource, count: usize) -> Result<()> {
+        if count == 0 {
+            if binding.nullable || binding.default.is_some() {
+                return Ok(());
+            }
+            return Err(Error::NullBinding {
+                name: binding.name.clone(),
+                template: source.clone(),
             });
         }
 
-        let template = parser::parse_template_file(&resolved)?;
-        let result =
-            self.compose_with_values_positional(&template, values, child_slots, visited)?;
+        let below = binding.min_values.is_some_and(|min| count < min as usize);
+        let above = binding.max_values.is_some_and(|max| count > max as usize);
+        if below || above {
+            return Err(Error::ValueCountOutOfRange {
+                name: binding.name.clone(),
+                template: source.clone(),
+                min: binding.min_values,
+                max: binding.max_values,
+                count,
+            });
+        }
 
-        visited.remove(&resolved);
-        Ok(result)
+        Ok(())
     }
 
-    /// Compose a command (count/union) into SQL (positional path).
-    ///
-    /// Command sources are standalone templates — they get empty slots.
-    fn compose_command(
-        &self,
-        command: &Command,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
-        match command.kind {
-            CommandKind::Union => self.compose_union(command, visited),
-            CommandKind::Count => self.compose_count(command, visited),
+    /// Check a `:bind_rows()` occurrence against the number of values given:
+    /// at least one row, and only whole rows.
+    fn check_rows(rows: &RowBinding, source: &TemplateSource, count: usize) -> Result<()> {
+        if count == 0 {
+            return Err(Error::NullBinding {
+                name: rows.name.clone(),
+                template: source.clone(),
+            });
+        }
+        if !count.is_multiple_of(rows.columns.len()) {
+            return Err(Error::IncompleteRow {
+                name: rows.name.clone(),
+                template: source.clone(),
+                columns: rows.columns.len(),
+                count,
+            });
         }
+        Ok(())
     }
 
-    /// Compose a UNION command (positional path).
-    fn compose_union(
-        &self,
-        command: &Command,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
-        let mut parts = Vec::new();
-        let mut all_params = Vec::new();
-        let empty_slots = HashMap::new();
-
-        for source in &command.sources {
-            let resolved = self.find_template(source)?;
-            let template = parser::parse_template_file(&resolved)?;
-            let composed = self.compose_inner(&template, &empty_slots, visited)?;
+    /// Record an occurrence of `rows`, declared in `source`, in `params`.
+    pub(crate) fn record_rows<'p>(
+        params: &'p mut BTreeMap<String, BindParam>,
+        rows: &RowBinding,
+        source: &TemplateSource,
+        dialect: &dyn SqlDialect,
+    ) -> Result<&'p mut BindParam> {
+        let param = Self::record_param(params, &rows.binding(), source, dialect)?;
+        param.columns.get_or_insert_with(|| rows.columns.clone());
+        Ok(param)
+    }
 
-            parts.push(composed.sql.trim_end().to_string());
-            all_params.extend(composed.bind_params);
+    /// Record an occurrence of `binding`, declared in `source`, in `params`.
+    ///
+    /// Every occurrence of a name must agree on `AS ARRAY`, which only
+    /// counts where `dialect` [binds arrays](SqlDialect::supports_array_binds).
+    pub(crate) fn record_param<'p>(
+        params: &'p mut BTreeMap<String, BindParam>,
+        binding: &Binding,
+        source: &TemplateSource,
+        dialect: &dyn SqlDialect,
+    ) -> Result<&'p mut BindParam> {
+        let array = binding.array && dialect.supports_array_binds();
+        let param = params
+            .entry(binding.name.clone())
+            .or_insert_with(|| BindParam {
+                name: binding.name.clone(),
+                indices: Vec::new(),
+                occurrences: 0,
+                template: source.clone(),
+                min_values: binding.min_values,
+                max_values: binding.max_values,
+                nullable: binding.nullable,
+                sql_type: None,
+                default: binding.default.clone(),
+                columns: None,
+                array,
+            });
+        if param.array != array {
+            return Err(Error::ConflictingArrayBinding {
+                name: binding.name.clone(),
+            });
         }
+        Self::declare_type(param, bound_type(dialect, binding).as_ref())?;
+        param.occurrences += 1;
+        Ok(param)
+    }
 
-        let union_kw = if command.all {
-            "UNION ALL"
-        } else if command.distinct {
-            "UNION DISTINCT"
-        } else {
-            "UNION"
+    /// Record the declared type of a binding occurrence, rejecting a name
+    /// declared with two different types.
+    fn declare_type(param: &mut BindParam, sql_type: Option<&BindType>) -> Result<()> {
+        let Some(sql_type) = sql_type else {
+            return Ok(());
         };
+        match &param.sql_type {
+            Some(first) if first != sql_type => Err(Error::ConflictingBindTypes {
+                name: param.name.clone(),
+                first: first.clone(),
+                second: sql_type.clone(),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                param.sql_type = Some(sql_type.clone());
+                Ok(())
+            }
+        }
+    }
 
-        let sql = parts.join(&format!("\n{union_kw}\n"));
+    /// The placeholder for the 0-based `value` of a binding at a 1-based
+    /// index, wrapped in a cast when the binding is typed and casts are
+    /// enabled.
+    fn placeholder(&self, binding: &Binding, index: usize, value: usize) -> Result<String> {
+        placeholder(&*self.dialect, binding, index, value, self.emit_casts)
+    }
 
-        Ok(ComposedSql {
-            sql,
-            bind_params: all_params,
-        })
+    /// The placeholder rows for `count` values of `rows` starting at a
+    /// 1-based index.
+    fn rows(&self, rows: &RowBinding, start: usize, count: usize) -> String {
+        placeholder_rows(&*self.dialect, rows, start, count)
     }
 
-    /// Compose a COUNT command (positional path).
-    fn compose_count(
+    /// The SQL for an `:ident()` given the supplied identifiers.
+    fn identifier(
         &self,
-        command: &Command,
-        visited: &mut HashSet<PathBuf>,
-    ) -> Result<ComposedSql> {
-        let columns = match &command.columns {
-            Some(cols) => cols.join(", "),
-            None => "*".to_string(),
-        };
-
-        let empty_slots = HashMap::new();
-
-        // If multiple sources, wrap a union first
-        let inner = if command.sources.len() > 1 {
-            let union_cmd = Command {
-                kind: CommandKind::Union,
-                distinct: command.distinct,
-                all: command.all,
-                columns: None,
-                sources: command.sources.clone(),
-            };
-            self.compose_union(&union_cmd, visited)?
-        } else {
-            let source = &command.sources[0];
-            let resolved = self.find_template(source)?;
-            let template = parser::parse_template_file(&resolved)?;
-            self.compose_inner(&template, &empty_slots, visited)?
-        };
-
-        let count_expr = if command.distinct {
-            format!("COUNT(DISTINCT {columns})")
-        } else {
-            format!("COUNT({columns})")
-        };
-
-        let sql = format!("SELECT {count_expr} FROM (\n{}\n) AS _count_sub", inner.sql);
-
-        Ok(ComposedSql {
-            sql,
-            bind_params: inner.bind_params,
-        })
+        ident: &Identifier,
+        idents: Option<&BTreeMap<String, String>>,
+        template: &TemplateSource,
+    ) -> Result<String> {
+        let value = idents.and_then(|idents| idents.get(&ident.name));
+        identifier(&*self.dialect, ident, value.map(String::as_str), template)
     }
 
-    // ── Shared helpers ────────────────────────────────────────────────
+    /// Literal SQL text as emitted, with registered mock tables substituted.
+    fn literal_sql(&self, text: &str) -> String {
+        mock::substitute_mocks(text, &self.mock_tables, &*self.dialect)
+    }
 
     /// Find a template file on the search paths.
     fn find_template(&self, path: &Path) -> Result<PathBuf> {
@@ -732,85 +1147,692 @@ impl Composer {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::types::{Binding, ComposeTarget, Element, SlotAssignment, TemplateSource};
-    use std::io::Write;
-    use tempfile::TempDir;
+/// The type each value bound for `binding` has: the declared type, or an
+/// array of it for `AS ARRAY OF type` where the dialect binds arrays.
+fn bound_type(dialect: &dyn SqlDialect, binding: &Binding) -> Option<BindType> {
+    let sql_type = binding.sql_type.clone()?;
+    if binding.array && dialect.supports_array_binds() {
+        Some(BindType::Array(Box::new(sql_type)))
+    } else {
+        Some(sql_type)
+    }
+}
 
-    #[test]
-    fn test_compose_plain_sql() {
-        let composer = Composer::new(Dialect::Postgres);
-        let template = Template {
-            elements: vec![Element::Sql("SELECT 1".into())],
-            source: TemplateSource::Literal("test".into()),
-        };
-        let result = composer.compose(&template).unwrap();
-        assert_eq!(result.sql, "SELECT 1");
-        assert!(result.bind_params.is_empty());
+/// The placeholder for the 0-based `value` of `binding` at a 1-based index,
+/// wrapped in a cast when the binding is typed and `cast` is set.
+///
+/// An array type (`AS int8[]`) only fits a binding with one value: a later
+/// one fails with [`Error::ExpandedArrayType`].
+pub(crate) fn placeholder(
+    dialect: &dyn SqlDialect,
+    binding: &Binding,
+    index: usize,
+    value: usize,
+    cast: bool,
+) -> Result<String> {
+    let sql_type = bound_type(dialect, binding);
+    if let Some(sql_type @ BindType::Array(_)) = &sql_type {
+        if value > 0 {
+            return Err(Error::ExpandedArrayType {
+                name: binding.name.clone(),
+                sql_type: sql_type.clone(),
+            });
+        }
+    }
+    let name = value_name(&binding.name, value);
+    let placeholder = dialect.bind_placeholder(index, &name, sql_type.as_ref());
+    match &sql_type {
+        Some(sql_type) if cast => {
+            dialect
+                .cast(&placeholder, sql_type)
+                .ok_or_else(|| Error::UnsupportedBindType {
+                    name: binding.name.clone(),
+                    sql_type: sql_type.clone(),
+                    dialect: dialect.name().to_string(),
+                })
+        }
+        _ => Ok(placeholder),
     }
+}
 
-    #[test]
-    fn test_compose_with_bindings_postgres() {
-        let composer = Composer::new(Dialect::Postgres);
-        let template = Template {
-            elements: vec![
-                Element::Sql("SELECT * FROM users WHERE id = ".into()),
-                Element::Bind(Binding {
-                    name: "user_id".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
-                }),
-                Element::Sql(" AND active = ".into()),
-                Element::Bind(Binding {
-                    name: "active".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
-                }),
-            ],
-            source: TemplateSource::Literal("test".into()),
+/// The operator joining the sources of a set operation command, e.g.
+/// `UNION ALL` for `:union(ALL ...)`. Without `ALL` it is the bare operator
+/// or its `DISTINCT` form, whichever the dialect accepts; both deduplicate,
+/// so `DISTINCT` only picks between them when the dialect accepts either.
+fn set_operator(dialect: &dyn SqlDialect, command: &Command) -> Result<String> {
+    let capabilities = dialect.capabilities();
+    let (name, operator) = match command.kind {
+        CommandKind::Intersect => (":intersect()", "INTERSECT"),
+        CommandKind::Except => (":except()", capabilities.except_keyword),
+        CommandKind::Union
+        | CommandKind::Count
+        | CommandKind::Exists
+        | CommandKind::NotExists
+        | CommandKind::Json
+        | CommandKind::Paginate => (":union()", "UNION"),
+    };
+    let unsupported = |construct: String| Error::UnsupportedConstruct {
+        command: name.to_string(),
+        construct,
+        dialect: dialect.name().to_string(),
+    };
+
+    if command.all {
+        return if operator == "UNION" || capabilities.all_set_ops {
+            Ok(format!("{operator} ALL"))
+        } else {
+            Err(unsupported(format!("{operator} ALL")))
         };
-        let result = composer.compose(&template).unwrap();
-        // Alphabetical: active=$1, user_id=$2
-        assert_eq!(
-            result.sql,
-            "SELECT * FROM users WHERE id = $2 AND active = $1"
-        );
-        assert_eq!(result.bind_params, vec!["active", "user_id"]);
     }
+    match (capabilities.distinct_set_ops, capabilities.bare_set_ops) {
+        (true, true) if command.distinct => Ok(format!("{operator} DISTINCT")),
+        (true, false) => Ok(format!("{operator} DISTINCT")),
+        (_, true) => Ok(operator.to_string()),
+        (false, false) => Err(unsupported(operator.to_string())),
+    }
+}
 
-    #[test]
-    fn test_compose_with_bindings_mysql() {
-        let composer = Composer::new(Dialect::Mysql);
-        let template = Template {
-            elements: vec![
-                Element::Sql("SELECT * FROM users WHERE id = ".into()),
-                Element::Bind(Binding {
-                    name: "user_id".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
-                }),
-                Element::Sql(" AND active = ".into()),
-                Element::Bind(Binding {
-                    name: "active".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
-                }),
-            ],
-            source: TemplateSource::Literal("test".into()),
-        };
-        let result = composer.compose(&template).unwrap();
-        // MySQL: document order, bare ?
-        assert_eq!(
-            result.sql,
-            "SELECT * FROM users WHERE id = ? AND active = ?"
-        );
-        assert_eq!(result.bind_params, vec!["user_id", "active"]);
+/// The SQL before and after the source of a `:count()`, `:exists()`,
+/// `:not_exists()`, `:json()` or `:paginate()`, e.g. `SELECT COUNT(*) FROM (`
+/// and `) AS _count_sub`. A page's [`page_clause()`] follows.
+///
+/// An existence test wraps the source itself, so a correlated source refers
+/// to the outer query as a hand-written subquery would. `:json()` aggregates
+/// its `columns OF` with [`SqlDialect::json_array()`]. A distinct count of
+/// several columns, or of whole rows, is written as the dialect's
+/// [`CountDistinct`] allows, falling back to counting a `SELECT DISTINCT`
+/// subquery.
+fn command_wrapper(dialect: &dyn SqlDialect, command: &Command) -> Result<(String, String)> {
+    let capabilities = dialect.capabilities();
+    let alias_name = |name: &str| {
+        if capabilities.leading_underscore {
+            name.to_string()
+        } else {
+            dialect.quote_ident(name)
+        }
+    };
+    let alias = |name: &str| {
+        if capabilities.derived_table_as {
+            format!(" AS {}", alias_name(name))
+        } else {
+            format!(" {}", alias_name(name))
+        }
+    };
+    let columns = command.columns.as_deref().unwrap_or_default();
+    let list = columns.join(", ");
+
+    match command.kind {
+        CommandKind::Exists | CommandKind::NotExists => {
+            let not = if command.kind == CommandKind::NotExists {
+                "NOT "
+            } else {
+                ""
+            };
+            return Ok((format!("{not}EXISTS (\n"), "\n)".to_string()));
+        }
+        CommandKind::Json => {
+            let (head, tail) = dialect
+                .json_array(&alias_name("_json_sub"), columns)
+                .ok_or_else(|| Error::UnsupportedConstruct {
+                    command: ":json()".to_string(),
+                    construct: if columns.is_empty() {
+                        "a JSON array of whole rows".to_string()
+                    } else {
+                        "a JSON array aggregate".to_string()
+                    },
+                    dialect: dialect.name().to_string(),
+                })?;
+            return Ok((
+                format!("{head}(\n"),
+                format!("\n){}{tail}", alias("_json_sub")),
+            ));
+        }
+        CommandKind::Paginate => {
+            return Ok((
+                "SELECT * FROM (\n".to_string(),
+                format!("\n){}", alias("_page_sub")),
+            ));
+        }
+        CommandKind::Count | CommandKind::Union | CommandKind::Intersect | CommandKind::Except => {}
+    }
+
+    let count_expr = match (command.distinct, columns) {
+        (false, []) => "COUNT(*)".to_string(),
+        (false, [column]) => format!("COUNT({column})"),
+        (false, _) => {
+            // `COUNT(a, b)` isn't SQL; count the rows where, as `COUNT(a)`
+            // would for one column, none of the columns is NULL.
+            let present: Vec<String> = columns.iter().map(|c| format!("{c} IS NOT NULL")).collect();
+            let present = present.join(" AND ");
+            let tail = format!("\n){}\nWHERE {present}", alias("_count_sub"));
+            return Ok(("SELECT COUNT(*) FROM (\n".to_string(), tail));
+        }
+        (true, [column]) => format!("COUNT(DISTINCT {column})"),
+        (true, [_, _, ..]) if capabilities.count_distinct == CountDistinct::List => {
+            format!("COUNT(DISTINCT {list})")
+        }
+        (true, [_, _, ..]) if capabilities.count_distinct == CountDistinct::Row => {
+            format!("COUNT(DISTINCT ({list}))")
+        }
+        (true, _) => {
+            let list = if columns.is_empty() { "*" } else { &list };
+            return Ok((
+                format!("SELECT COUNT(*) FROM (\nSELECT DISTINCT {list} FROM (\n"),
+                format!("\n){}\n){}", alias("_distinct_sub"), alias("_count_sub")),
+            ));
+        }
+    };
+    Ok((
+        format!("SELECT {count_expr} FROM (\n"),
+        format!("\n){}", alias("_count_sub")),
+    ))
+}
+
+/// The clause after the source of a `:paginate()`, as a template of its own:
+/// the keyset predicate, the `ORDER BY` of the columns and the dialect's page
+/// limit.
+///
+/// By offset it binds `page_size` and `page_offset`; with `KEYSET`,
+/// `page_size` and an `after_{column}` cursor value per column. The names are
+/// fixed and share the template's namespace, so a binding of the same name
+/// in a source takes the same value. The page size and offset are `int8`
+/// where the dialect needs [typed paging](crate::dialect::Capabilities::typed_paging).
+///
+/// The keyset predicate sits in an `:if(after_{column})` block on the first
+/// column, so the first page is fetched without a cursor. It compares
+/// [row values](crate::dialect::Capabilities::row_values) when every column
+/// sorts the same way, or is spelled out column by column.
+fn page_clause(dialect: &dyn SqlDialect, command: &Command) -> Result<Template> {
+    let capabilities = dialect.capabilities();
+    let columns = command.columns.as_deref().unwrap_or_default();
+    let descending = |i: usize| command.descending.get(i).copied().unwrap_or(false);
+    let text = |sql: &str| Element::Sql(sql.to_string());
+    let bind = |name: String, sql_type: Option<BindType>| {
+        Element::Bind(Binding {
+            name,
+            min_values: Some(1),
+            max_values: Some(1),
+            sql_type,
+            ..Default::default()
+        })
+    };
+    let page = |name: &str| {
+        let sql_type = capabilities.typed_paging.then_some(BindType::Int8);
+        bind(name.to_string(), sql_type)
+    };
+    let after = |column: &String| bind(format!("after_{column}"), None);
+    let after_op = |i: usize| if descending(i) { " < " } else { " > " };
+
+    let size = page("page_size");
+    let offset = page("page_offset");
+    let limit = match (capabilities.paging, command.keyset) {
+        (Paging::LimitOffset, true) => vec![text("LIMIT "), size],
+        (Paging::LimitOffset, false) => vec![text("LIMIT "), size, text(" OFFSET "), offset],
+        (Paging::OffsetFetch, true) => {
+            vec![text("OFFSET 0 ROWS FETCH NEXT "), size, text(" ROWS ONLY")]
+        }
+        (Paging::OffsetFetch, false) => vec![
+            text("OFFSET "),
+            offset,
+            text(" ROWS FETCH NEXT "),
+            size,
+            text(" ROWS ONLY"),
+        ],
+        (Paging::Unsupported, _) => {
+            return Err(Error::UnsupportedConstruct {
+                command: ":paginate()".to_string(),
+                construct: "a page size bound as a parameter".to_string(),
+                dialect: dialect.name().to_string(),
+            });
+        }
+    };
+
+    let mut elements = Vec::new();
+    if let (true, Some(first)) = (command.keyset, columns.first()) {
+        elements.push(Element::If(format!("after_{first}")));
+        elements.push(text("\nWHERE "));
+        let uniform = (1..columns.len()).all(|i| descending(i) == descending(0));
+        if capabilities.row_values && columns.len() > 1 && uniform {
+            elements.push(text(&format!("({}){}(", columns.join(", "), after_op(0))));
+            for (i, column) in columns.iter().enumerate() {
+                if i > 0 {
+                    elements.push(text(", "));
+                }
+                elements.push(after(column));
+            }
+            elements.push(text(")"));
+        } else {
+            for (i, column) in columns.iter().enumerate() {
+                if i > 0 {
+                    elements.push(text(" OR ("));
+                }
+                for earlier in &columns[..i] {
+                    elements.extend([text(&format!("{earlier} = ")), after(earlier)]);
+                    elements.push(text(" AND "));
+                }
+                elements.extend([text(&format!("{column}{}", after_op(i))), after(column)]);
+                if i > 0 {
+                    elements.push(text(")"));
+                }
+            }
+        }
+        elements.push(Element::EndIf);
+    }
+    let order: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| match descending(i) {
+            true => format!("{column} DESC"),
+            false => column.clone(),
+        })
+        .collect();
+    elements.push(text(&format!("\nORDER BY {}\n", order.join(", "))));
+    elements.extend(limit);
+
+    Ok(Template {
+        elements,
+        source: TemplateSource::Literal(":paginate()".to_string()),
+    })
+}
+
+/// Placeholders for `count` values of `rows` starting at a 1-based index,
+/// grouped into parenthesized rows, e.g. `($1, $2), ($3, $4)`.
+fn placeholder_rows(
+    dialect: &dyn SqlDialect,
+    rows: &RowBinding,
+    start: usize,
+    count: usize,
+) -> String {
+    let indices: Vec<usize> = (start..start + count).collect();
+    let groups: Vec<String> = indices
+        .chunks(rows.columns.len())
+        .map(|row| {
+            let placeholders: Vec<String> = row
+                .iter()
+                .map(|&i| dialect.bind_placeholder(i, &value_name(&rows.name, i - start), None))
+                .collect();
+            format!("({})", placeholders.join(", "))
+        })
+        .collect();
+    groups.join(", ")
+}
+
+/// The name the 0-based `value` of the binding `name` is bound under by
+/// dialects with named placeholders: `name`, then `name_2`, `name_3`, ...
+fn value_name(name: &str, value: usize) -> String {
+    match value {
+        0 => name.to_string(),
+        _ => format!("{name}_{}", value + 1),
+    }
+}
+
+/// Rewrite a trailing `IN (` in `sql` to `= ANY(`, or `NOT IN (` to
+/// `<> ALL(`, ahead of an array placeholder. The template's own closing `)`
+/// then closes the call. Anything else is left alone, so an array binding
+/// can also be passed where SQL takes an array, as in `unnest(...)`.
+fn array_operator(sql: &mut String) {
+    let word_end = |text: &str| {
+        !text
+            .chars()
+            .next_back()
+            .is_some_and(|ch| ch.is_alphanumeric() || ch == '_')
+    };
+    let Some(rest) = sql.trim_end().strip_suffix('(') else {
+        return;
+    };
+    let rest = rest.trim_end();
+    if !rest.to_ascii_uppercase().ends_with("IN") {
+        return;
+    }
+    let before = &rest[..rest.len() - 2];
+    if !word_end(before) {
+        return;
+    }
+    let trimmed = before.trim_end();
+    let negated = trimmed.to_ascii_uppercase().ends_with("NOT")
+        && trimmed.len() < before.len()
+        && word_end(&trimmed[..trimmed.len() - 3]);
+    let (keep, operator) = if negated {
+        (trimmed.len() - 3, "<> ALL(")
+    } else {
+        (before.len(), "= ANY(")
+    };
+    sql.truncate(keep);
+    sql.push_str(operator);
+}
+
+/// A template with its `:compose()` references and commands expanded in
+/// place, leaving only SQL, bindings, identifiers and block markers.
+#[derive(Debug, Default)]
+pub(crate) struct Flat {
+    /// The expanded elements.
+    pub(crate) elements: Vec<Element>,
+    /// Where each element came from. The SQL a command wraps its sources in
+    /// comes from the command.
+    origins: Vec<Origin>,
+    /// The template instances the elements came from.
+    scopes: Vec<Scope>,
+}
+
+/// An element of a template instance in a [`Flat`] template.
+#[derive(Debug, Clone, Copy)]
+struct Origin {
+    /// The index of the template instance in [`Flat::scopes`].
+    scope: usize,
+    /// The index of the element in the template.
+    index: usize,
+}
+
+/// A template instance in a [`Flat`] template.
+#[derive(Debug)]
+struct Scope {
+    /// The template's source.
+    source: TemplateSource,
+    /// The text the template was parsed from and its element spans, when
+    /// errors are located.
+    source_map: Option<(Arc<str>, Arc<[Span]>)>,
+    /// The root template and the compose targets and command sources that
+    /// led here.
+    chain: Vec<String>,
+}
+
+impl Flat {
+    /// Add a template instance reached through `chain`, returning its index.
+    fn scope(
+        &mut self,
+        template: &Template,
+        source_map: Option<(Arc<str>, Arc<[Span]>)>,
+        chain: Vec<String>,
+    ) -> usize {
+        self.scopes.push(Scope {
+            source: template.source.clone(),
+            source_map,
+            chain,
+        });
+        self.scopes.len() - 1
+    }
+
+    /// The include chain to a template reached as `frame` from `origin`.
+    fn chain(&self, origin: Origin, frame: String) -> Vec<String> {
+        let mut chain = self.scopes[origin.scope].chain.clone();
+        chain.push(frame);
+        chain
+    }
+
+    /// Append an element from `origin`.
+    fn push(&mut self, element: Element, origin: Origin) {
+        self.elements.push(element);
+        self.origins.push(origin);
+    }
+
+    /// Drop trailing whitespace from the SQL appended so far.
+    fn trim_end(&mut self) {
+        while let Some(Element::Sql(text)) = self.elements.last_mut() {
+            let trimmed = text.trim_end().len();
+            if trimmed > 0 {
+                text.truncate(trimmed);
+                return;
+            }
+            self.elements.pop();
+            self.origins.pop();
+        }
+    }
+
+    /// The source of the template the element at `at` came from.
+    pub(crate) fn source(&self, at: usize) -> &TemplateSource {
+        &self.scopes[self.origins[at].scope].source
+    }
+
+    /// Attach the location of the element at `at` to an error raised while
+    /// composing it.
+    pub(crate) fn locate_element(&self, at: usize, err: Error) -> Error {
+        self.locate(self.origins[at], err, None)
+    }
+
+    /// Attach the location of the template element at `origin` to an error
+    /// raised while composing it, with the include chain that led there and
+    /// `frame`, the element's own compose target.
+    ///
+    /// Errors already located deeper in the tree keep their innermost
+    /// location. Errors from templates without a source map pass through
+    /// unchanged.
+    fn locate(&self, origin: Origin, err: Error, frame: Option<String>) -> Error {
+        if matches!(err, Error::Located { .. }) {
+            return err;
+        }
+
+        let scope = &self.scopes[origin.scope];
+        let Some((text, spans)) = &scope.source_map else {
+            return err;
+        };
+        let span = spans[origin.index];
+
+        let mut chain = scope.chain.clone();
+        chain.extend(frame);
+        Error::Located {
+            error: Box::new(err),
+            location: Location::new(scope.source.clone(), span, Some(text)),
+            chain,
+        }
+    }
+}
+
+/// What emission knows about the values a compose call was given.
+#[derive(Debug, Clone, Copy, Default)]
+struct Inputs<'a> {
+    /// Number of values per binding name; `None` for [`Composer::compose()`].
+    counts: Option<&'a BTreeMap<String, usize>>,
+    /// The identifier per `:ident()` name; `None` for [`Composer::compose()`].
+    idents: Option<&'a BTreeMap<String, String>>,
+}
+
+/// What pass 1 of a numbered compose gathers from the template tree.
+#[derive(Debug, Default)]
+struct Collected {
+    /// Every unique binding, by name.
+    params: BTreeMap<String, BindParam>,
+    /// Binding names in order of first appearance.
+    seen: Vec<String>,
+    /// Names listed by `:bind_order()`, in order.
+    declared: Vec<String>,
+}
+
+impl Collected {
+    /// Note an occurrence of `name`.
+    fn see(&mut self, name: &str) {
+        if !self.seen.iter().any(|seen| seen == name) {
+            self.seen.push(name.to_string());
+        }
+    }
+
+    /// The binding names in the order `ordering` numbers them.
+    fn ordered(&self, ordering: PlaceholderOrdering) -> Vec<String> {
+        placeholder_order(ordering, &self.seen, &self.declared)
+    }
+}
+
+/// The names in `seen` (every binding, by first appearance) in the order
+/// `ordering` numbers them. Under [`PlaceholderOrdering::Explicit`],
+/// `declared` names come first; those never bound are skipped.
+fn placeholder_order(
+    ordering: PlaceholderOrdering,
+    seen: &[String],
+    declared: &[String],
+) -> Vec<String> {
+    match ordering {
+        PlaceholderOrdering::Alphabetical => {
+            let mut names = seen.to_vec();
+            names.sort();
+            names
+        }
+        PlaceholderOrdering::FirstAppearance => seen.to_vec(),
+        PlaceholderOrdering::Explicit => {
+            let mut names: Vec<String> = Vec::new();
+            let declared = declared.iter().filter(|name| seen.contains(name));
+            for name in declared.chain(seen) {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            names
+        }
+    }
+}
+
+/// The branches taken so far while walking nested `:if` blocks in order.
+#[derive(Debug, Default)]
+struct Blocks {
+    /// Whether the current branch of each open block is emitted, innermost
+    /// last.
+    taken: Vec<bool>,
+}
+
+impl Blocks {
+    /// Enter an `:if` block whose first branch is taken when `present`.
+    fn open(&mut self, present: bool) {
+        self.taken.push(present);
+    }
+
+    /// Switch the innermost block to its `:else` branch.
+    fn flip(&mut self) {
+        if let Some(taken) = self.taken.last_mut() {
+            *taken = !*taken;
+        }
+    }
+
+    /// Leave the innermost block.
+    fn close(&mut self) {
+        self.taken.pop();
+    }
+
+    /// Whether elements at the current position are emitted.
+    fn active(&self) -> bool {
+        self.taken.iter().all(|&taken| taken)
+    }
+}
+
+/// The SQL for `value` chosen for `ident`: quoted with
+/// [`SqlDialect::quote_ident()`], or bare for the sort directions `ASC` and
+/// `DESC`.
+fn identifier(
+    dialect: &dyn SqlDialect,
+    ident: &Identifier,
+    value: Option<&str>,
+    template: &TemplateSource,
+) -> Result<String> {
+    let value = value.ok_or_else(|| Error::MissingIdent {
+        name: ident.name.clone(),
+        template: template.clone(),
+    })?;
+    if !ident.allowed.iter().any(|allowed| allowed == value) {
+        return Err(Error::IdentNotAllowed {
+            name: ident.name.clone(),
+            template: template.clone(),
+            value: value.to_string(),
+            allowed: ident.allowed.clone(),
+        });
+    }
+
+    if value.eq_ignore_ascii_case("ASC") || value.eq_ignore_ascii_case("DESC") {
+        Ok(value.to_ascii_uppercase())
+    } else {
+        Ok(dialect.quote_ident(value))
+    }
+}
+
+/// The SQL for `binding` when it has no values: its `DEFAULT` literal, cast
+/// like a placeholder when `cast` is set, or `NULL`.
+fn no_values(dialect: &dyn SqlDialect, binding: &Binding, cast: bool) -> Result<String> {
+    let Some(default) = &binding.default else {
+        return Ok("NULL".to_string());
+    };
+    let literal = default.to_sql_with(dialect);
+    match &binding.sql_type {
+        Some(sql_type) if cast => {
+            dialect
+                .cast(&literal, sql_type)
+                .ok_or_else(|| Error::UnsupportedBindType {
+                    name: binding.name.clone(),
+                    sql_type: sql_type.clone(),
+                    dialect: dialect.name().to_string(),
+                })
+        }
+        _ => Ok(literal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::types::{Binding, ComposeTarget, Dialect, Element, SlotAssignment, TemplateSource};
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compose_plain_sql() {
+        let composer = Composer::new(Dialect::Postgres);
+        let template = Template {
+            elements: vec![Element::Sql("SELECT 1".into())],
+            source: TemplateSource::Literal("test".into()),
+        };
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(result.sql, "SELECT 1");
+        assert!(result.bind_params.is_empty());
+    }
+
+    #[test]
+    fn test_compose_with_bindings_postgres() {
+        let composer = Composer::new(Dialect::Postgres);
+        let template = Template {
+            elements: vec![
+                Element::Sql("SELECT * FROM users WHERE id = ".into()),
+                Element::Bind(Binding {
+                    name: "user_id".into(),
+                    ..Default::default()
+                }),
+                Element::Sql(" AND active = ".into()),
+                Element::Bind(Binding {
+                    name: "active".into(),
+                    ..Default::default()
+                }),
+            ],
+            source: TemplateSource::Literal("test".into()),
+        };
+        let result = composer.compose(&template).unwrap();
+        // Alphabetical: active=$1, user_id=$2
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM users WHERE id = $2 AND active = $1"
+        );
+        assert_eq!(result.bind_params, vec!["active", "user_id"]);
+    }
+
+    #[test]
+    fn test_compose_with_bindings_mysql() {
+        let composer = Composer::new(Dialect::Mysql);
+        let template = Template {
+            elements: vec![
+                Element::Sql("SELECT * FROM users WHERE id = ".into()),
+                Element::Bind(Binding {
+                    name: "user_id".into(),
+                    ..Default::default()
+                }),
+                Element::Sql(" AND active = ".into()),
+                Element::Bind(Binding {
+                    name: "active".into(),
+                    ..Default::default()
+                }),
+            ],
+            source: TemplateSource::Literal("test".into()),
+        };
+        let result = composer.compose(&template).unwrap();
+        // MySQL: document order, bare ?
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM users WHERE id = ? AND active = ?"
+        );
+        assert_eq!(result.bind_params, vec!["user_id", "active"]);
     }
 
     #[test]
@@ -821,16 +1843,12 @@ mod tests {
                 Element::Sql("SELECT * FROM users WHERE id = ".into()),
                 Element::Bind(Binding {
                     name: "user_id".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(" AND active = ".into()),
                 Element::Bind(Binding {
                     name: "active".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
             ],
             source: TemplateSource::Literal("test".into()),
@@ -862,9 +1880,7 @@ mod tests {
                 Element::Sql("SELECT * FROM users WHERE id = ".into()),
                 Element::Bind(Binding {
                     name: "user_id".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
             ],
             source: TemplateSource::Literal("test".into()),
@@ -884,8 +1900,7 @@ mod tests {
                 Element::Bind(Binding {
                     name: "ids".into(),
                     min_values: Some(1),
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(")".into()),
             ],
@@ -906,8 +1921,7 @@ mod tests {
                 Element::Bind(Binding {
                     name: "ids".into(),
                     min_values: Some(1),
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(")".into()),
             ],
@@ -928,15 +1942,12 @@ mod tests {
                 Element::Bind(Binding {
                     name: "ids".into(),
                     min_values: Some(1),
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(") AND status = ".into()),
                 Element::Bind(Binding {
                     name: "status".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
             ],
             source: TemplateSource::Literal("test".into()),
@@ -962,16 +1973,12 @@ mod tests {
                 Element::Sql("SELECT ".into()),
                 Element::Bind(Binding {
                     name: "z_param".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(", ".into()),
                 Element::Bind(Binding {
                     name: "a_param".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
             ],
             source: TemplateSource::Literal("test".into()),
@@ -990,16 +1997,12 @@ mod tests {
                 Element::Sql("SELECT ".into()),
                 Element::Bind(Binding {
                     name: "z_param".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(", ".into()),
                 Element::Bind(Binding {
                     name: "a_param".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
             ],
             source: TemplateSource::Literal("test".into()),
@@ -1019,16 +2022,12 @@ mod tests {
                 Element::Sql("WHERE a = ".into()),
                 Element::Bind(Binding {
                     name: "x".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(" AND b = ".into()),
                 Element::Bind(Binding {
                     name: "x".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
             ],
             source: TemplateSource::Literal("test".into()),
@@ -1048,15 +2047,13 @@ mod tests {
                 Element::Bind(Binding {
                     name: "ids".into(),
                     min_values: Some(1),
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(") AND b IN (".into()),
                 Element::Bind(Binding {
                     name: "ids".into(),
                     min_values: Some(1),
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(")".into()),
             ],
@@ -1077,23 +2074,18 @@ mod tests {
                 Element::Sql("WHERE active = ".into()),
                 Element::Bind(Binding {
                     name: "active".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(" AND id IN (".into()),
                 Element::Bind(Binding {
                     name: "ids".into(),
                     min_values: Some(1),
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(") AND user_id = ".into()),
                 Element::Bind(Binding {
                     name: "user_id".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
             ],
             source: TemplateSource::Literal("test".into()),
@@ -1123,16 +2115,12 @@ mod tests {
                 Element::Sql("WHERE a = ".into()),
                 Element::Bind(Binding {
                     name: "x".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
                 Element::Sql(" AND b = ".into()),
                 Element::Bind(Binding {
                     name: "x".into(),
-                    min_values: None,
-                    max_values: None,
-                    nullable: false,
+                    ..Default::default()
                 }),
             ],
             source: TemplateSource::Literal("test".into()),
@@ -1150,28 +2138,591 @@ mod tests {
         assert!(!Dialect::Mysql.supports_numbered_placeholders());
     }
 
-    // ── Slot tests ────────────────────────────────────────────────────
+    // ── Constraint tests ──────────────────────────────────────────────
 
-    /// Helper: write a temp file and return its path.
-    fn write_temp_file(dir: &TempDir, name: &str, content: &str) -> PathBuf {
-        let path = dir.path().join(name);
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent).unwrap();
+    fn constrained_template(min: Option<u32>, max: Option<u32>, nullable: bool) -> Template {
+        Template {
+            elements: vec![
+                Element::Sql("SELECT * FROM users WHERE id IN (".into()),
+                Element::Bind(Binding {
+                    name: "ids".into(),
+                    min_values: min,
+                    max_values: max,
+                    nullable,
+                    ..Default::default()
+                }),
+                Element::Sql(")".into()),
+            ],
+            source: TemplateSource::Literal("test".into()),
         }
-        let mut f = std::fs::File::create(&path).unwrap();
-        f.write_all(content.as_bytes()).unwrap();
-        path
     }
 
     #[test]
-    fn test_slot_resolution_numbered() {
-        let dir = TempDir::new().unwrap();
+    fn test_expecting_range_too_few() {
+        let composer = Composer::new(Dialect::Postgres);
+        let template = constrained_template(Some(2), Some(3), false);
+        let values: BTreeMap<String, Vec<i32>> = BTreeMap::from([("ids".into(), vec![10])]);
+        let err = composer
+            .compose_with_values(&template, &values)
+            .unwrap_err();
+        match err {
+            Error::ValueCountOutOfRange {
+                name,
+                template,
+                min,
+                max,
+                count,
+            } => {
+                assert_eq!(name, "ids");
+                assert_eq!(template, TemplateSource::Literal("test".into()));
+                assert_eq!((min, max, count), (Some(2), Some(3), 1));
+            }
+            other => panic!("expected ValueCountOutOfRange, got {:?}", other),
+        }
+    }
 
-        // Filter template: standalone query
-        write_temp_file(
-            &dir,
-            "filter.sqlc",
-            "SELECT part_num FROM parts WHERE color = :bind(color)",
+    #[test]
+    fn test_expecting_range_too_many_mysql() {
+        let composer = Composer::new(Dialect::Mysql);
+        let template = constrained_template(Some(1), Some(2), false);
+        let values: BTreeMap<String, Vec<i32>> = BTreeMap::from([("ids".into(), vec![10, 20, 30])]);
+        let err = composer
+            .compose_with_values(&template, &values)
+            .unwrap_err();
+        assert!(matches!(err, Error::ValueCountOutOfRange { count: 3, .. }));
+        assert_eq!(
+            err.to_string(),
+            "binding 'ids' in <test> expects 1..2 value(s), got 3"
+        );
+    }
+
+    #[test]
+    fn test_expecting_min_only() {
+        let composer = Composer::new(Dialect::Sqlite);
+        let template = constrained_template(Some(2), None, false);
+        let values: BTreeMap<String, Vec<i32>> = BTreeMap::from([("ids".into(), vec![1, 2, 3, 4])]);
+        let result = composer.compose_with_values(&template, &values).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM users WHERE id IN (?1, ?2, ?3, ?4)"
+        );
+    }
+
+    #[test]
+    fn test_non_nullable_without_values() {
+        let composer = Composer::new(Dialect::Postgres);
+        let template = constrained_template(Some(1), None, false);
+        let values: BTreeMap<String, Vec<i32>> = BTreeMap::from([("ids".into(), vec![])]);
+        let err = composer
+            .compose_with_values(&template, &values)
+            .unwrap_err();
+        assert!(matches!(err, Error::NullBinding { ref name, .. } if name == "ids"));
+
+        let err = composer
+            .compose_with_values(&template, &BTreeMap::<String, Vec<i32>>::new())
+            .unwrap_err();
+        assert!(matches!(err, Error::NullBinding { .. }));
+    }
+
+    #[test]
+    fn test_nullable_without_values_emits_null() {
+        let template = Template {
+            elements: vec![
+                Element::Sql("UPDATE users SET email = ".into()),
+                Element::Bind(Binding {
+                    name: "email".into(),
+                    nullable: true,
+                    ..Default::default()
+                }),
+                Element::Sql(" WHERE id = ".into()),
+                Element::Bind(Binding {
+                    name: "id".into(),
+                    ..Default::default()
+                }),
+            ],
+            source: TemplateSource::Literal("test".into()),
+        };
+        let values: BTreeMap<String, Vec<i32>> = BTreeMap::from([("id".into(), vec![7])]);
+
+        let result = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(result.sql, "UPDATE users SET email = NULL WHERE id = $1");
+        assert_eq!(result.bind_params, vec!["id"]);
+
+        let result = Composer::new(Dialect::Mysql)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(result.sql, "UPDATE users SET email = NULL WHERE id = ?");
+        assert_eq!(result.bind_params, vec!["id"]);
+    }
+
+    #[test]
+    fn test_default_without_values_inlines_literal() {
+        let sql = "SELECT * FROM posts WHERE status = :bind(status DEFAULT 'it''s') \
+                   AND id > :bind(after) LIMIT :bind(limit AS int8 DEFAULT 100)";
+        let template = parser::parse_template(sql, TemplateSource::Literal("test".into())).unwrap();
+        let values = crate::bind_values!("after" => [7]);
+
+        let result = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM posts WHERE status = 'it''s' AND id > $1 LIMIT 100::int8"
+        );
+        assert_eq!(result.bind_params, vec!["after"]);
+        assert_eq!(
+            result.params["limit"].default,
+            Some(BindDefault::Number("100".into()))
+        );
+
+        let result = Composer::new(Dialect::Mysql)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM posts WHERE status = 'it''s' AND id > ? LIMIT CAST(100 AS SIGNED)"
+        );
+
+        // A supplied value replaces the default.
+        let values = crate::bind_values!("after" => [7], "limit" => [10]);
+        let result = Composer::new(Dialect::Sqlite)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM posts WHERE status = 'it''s' AND id > ?1 LIMIT CAST(?2 AS INTEGER)"
+        );
+        assert_eq!(result.bind_params, vec!["after", "limit"]);
+    }
+
+    #[test]
+    fn test_ident_substitutes_allowlisted_identifier() {
+        let sql = "SELECT * FROM sets WHERE year > :bind(year) \
+                   ORDER BY :ident(sort ONE OF name, year) :ident(dir ONE OF ASC, DESC)";
+        let template = parser::parse_template(sql, TemplateSource::Literal("test".into())).unwrap();
+        let values = crate::bind_values!("year" => [2000]);
+        let idents = BTreeMap::from([
+            ("sort".to_string(), "name".to_string()),
+            ("dir".to_string(), "DESC".to_string()),
+        ]);
+
+        let result = Composer::new(Dialect::Postgres)
+            .compose_with_idents(&template, &values, &idents)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM sets WHERE year > $1 ORDER BY \"name\" DESC"
+        );
+        let result = Composer::new(Dialect::Mysql)
+            .compose_with_idents(&template, &values, &idents)
+            .unwrap();
+        assert!(result.sql.ends_with("ORDER BY `name` DESC"));
+
+        let idents = BTreeMap::from([
+            ("sort".to_string(), "name; DROP TABLE sets".to_string()),
+            ("dir".to_string(), "ASC".to_string()),
+        ]);
+        let err = Composer::new(Dialect::Postgres)
+            .compose_with_idents(&template, &values, &idents)
+            .unwrap_err();
+        assert!(matches!(
+            err.root(),
+            Error::IdentNotAllowed { name, allowed, .. } if name == "sort" && allowed.len() == 2
+        ));
+
+        let err = Composer::new(Dialect::Sqlite)
+            .compose_with_values(&template, &values)
+            .unwrap_err();
+        assert!(matches!(err.root(), Error::MissingIdent { name, .. } if name == "sort"));
+    }
+
+    #[test]
+    fn test_bind_rows_expands_one_group_per_row() {
+        let sql = "INSERT INTO parts (part_num, color_id, quantity) \
+                   VALUES :bind_rows(items (part_num, color_id, quantity)) \
+                   RETURNING :bind(batch)";
+        let template = parser::parse_template(sql, TemplateSource::Literal("test".into())).unwrap();
+        let values = crate::bind_values!("items" => [1, 2, 3, 4, 5, 6], "batch" => [7]);
+
+        let result = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert!(result
+            .sql
+            .ends_with("VALUES ($2, $3, $4), ($5, $6, $7) RETURNING $1"));
+        assert_eq!(result.params["items"].indices, vec![2, 3, 4, 5, 6, 7]);
+        assert_eq!(result.params["items"].columns.as_ref().unwrap().len(), 3);
+
+        let result = Composer::new(Dialect::Mysql)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert!(result
+            .sql
+            .ends_with("VALUES (?, ?, ?), (?, ?, ?) RETURNING ?"));
+        assert_eq!(result.bind_params.len(), 7);
+
+        let result = Composer::new(Dialect::Sqlite).compose(&template).unwrap();
+        assert!(result.sql.ends_with("VALUES (?2, ?3, ?4) RETURNING ?1"));
+        assert_eq!(result.bind_params, vec!["batch", "items", "items", "items"]);
+
+        let values = crate::bind_values!("items" => [1, 2, 3, 4], "batch" => [7]);
+        let err = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap_err();
+        assert!(matches!(
+            err.root(),
+            Error::IncompleteRow { name, columns: 3, count: 4, .. } if name == "items"
+        ));
+    }
+
+    #[test]
+    fn test_array_binding_composes_one_placeholder() {
+        let sql = "SELECT * FROM t WHERE id IN (:bind(ids AS ARRAY)) \
+                   AND tag NOT IN (:bind(tags AS ARRAY)) \
+                   AND code IN (SELECT unnest(:bind(ids AS ARRAY)))";
+        let template = parser::parse_template(sql, TemplateSource::Literal("test".into())).unwrap();
+        let values = crate::bind_values!("ids" => [1, 2, 3], "tags" => [4, 5]);
+
+        let result = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM t WHERE id = ANY($1) AND tag <> ALL($2) \
+             AND code IN (SELECT unnest($1))"
+        );
+        assert_eq!(result.bind_params, vec!["ids", "tags"]);
+        assert_eq!(result.params["ids"].indices, vec![1]);
+        assert!(result.params["ids"].array);
+
+        let result = Composer::new(Dialect::Postgres).compose(&template).unwrap();
+        assert!(result.sql.starts_with("SELECT * FROM t WHERE id = ANY($1)"));
+
+        let result = Composer::new(Dialect::Sqlite)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert!(result
+            .sql
+            .starts_with("SELECT * FROM t WHERE id IN (?1, ?2, ?3)"));
+        assert!(!result.params["ids"].array);
+
+        let template = parser::parse_template(
+            "SELECT * FROM t WHERE a IN (:bind(ids AS ARRAY)) OR b IN (:bind(ids))",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let err = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap_err();
+        assert!(matches!(
+            err.root(),
+            Error::ConflictingArrayBinding { name } if name == "ids"
+        ));
+    }
+
+    #[test]
+    fn test_array_binding_of_type() {
+        let template = parser::parse_template(
+            "SELECT * FROM t WHERE id IN (:bind(ids AS ARRAY OF int8))",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let values = crate::bind_values!("ids" => [1, 2, 3]);
+
+        let result = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(result.sql, "SELECT * FROM t WHERE id = ANY($1::int8[])");
+        let int8_array = BindType::Array(Box::new(BindType::Int8));
+        assert_eq!(result.bind_type("ids"), Some(&int8_array));
+
+        let result = Composer::new(Dialect::Sqlite)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM t WHERE id IN (CAST(?1 AS INTEGER), CAST(?2 AS INTEGER), CAST(?3 AS INTEGER))"
+        );
+        assert_eq!(result.bind_type("ids"), Some(&BindType::Int8));
+    }
+
+    #[test]
+    fn test_array_type_rejects_several_values() {
+        let template = parser::parse_template(
+            "SELECT * FROM t WHERE tags && :bind(tags AS text[])",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let composer = Composer::new(Dialect::Postgres);
+
+        let result = composer
+            .compose_with_values(&template, &crate::bind_values!("tags" => [1]))
+            .unwrap();
+        assert_eq!(result.sql, "SELECT * FROM t WHERE tags && $1::text[]");
+
+        let err = composer
+            .compose_with_values(&template, &crate::bind_values!("tags" => [1, 2]))
+            .unwrap_err();
+        assert!(matches!(
+            err.root(),
+            Error::ExpandedArrayType { name, .. } if name == "tags"
+        ));
+    }
+
+    #[test]
+    fn test_placeholder_ordering() {
+        let compose = |sql: &str, ordering| {
+            let template =
+                parser::parse_template(sql, TemplateSource::Literal("test".into())).unwrap();
+            let mut composer = Composer::new(Dialect::Postgres);
+            composer.ordering = ordering;
+            composer.compose(&template).unwrap()
+        };
+        let sql = "SELECT * FROM t WHERE org = :bind(org) AND id = :bind(id) OR org = :bind(org)";
+
+        let result = compose(sql, PlaceholderOrdering::Alphabetical);
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM t WHERE org = $2 AND id = $1 OR org = $2"
+        );
+        assert_eq!(result.ordering, PlaceholderOrdering::Alphabetical);
+
+        let result = compose(sql, PlaceholderOrdering::FirstAppearance);
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM t WHERE org = $1 AND id = $2 OR org = $1"
+        );
+        assert_eq!(result.bind_params, vec!["org", "id"]);
+        assert_eq!(result.ordering, PlaceholderOrdering::FirstAppearance);
+
+        // A binding added at the end doesn't renumber those before it.
+        let result = compose(
+            &format!("{sql} AND a = :bind(aaa)"),
+            PlaceholderOrdering::FirstAppearance,
+        );
+        assert_eq!(result.bind_params, vec!["org", "id", "aaa"]);
+
+        let sql = ":bind_order(id, missing, org)\nSELECT :bind(x), :bind(org), :bind(id)";
+        let result = compose(sql, PlaceholderOrdering::Explicit);
+        assert_eq!(result.sql, "SELECT $3, $2, $1");
+        assert_eq!(result.bind_params, vec!["id", "org", "x"]);
+
+        let template = parser::parse_template(sql, TemplateSource::Literal("test".into())).unwrap();
+        let mut composer = Composer::new(Dialect::Mysql);
+        composer.ordering = PlaceholderOrdering::Explicit;
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(result.sql, "SELECT ?, ?, ?");
+        assert_eq!(result.bind_params, vec!["x", "org", "id"]);
+        assert_eq!(result.ordering, PlaceholderOrdering::FirstAppearance);
+    }
+
+    #[test]
+    fn test_compose_chunked_splits_oversized_bindings() {
+        let sql = "SELECT * FROM t WHERE org = :bind(org) AND id IN (:bind(ids)) \
+                   OR parent IN (:bind(ids))";
+        let template = parser::parse_template(sql, TemplateSource::Literal("test".into())).unwrap();
+        let values = crate::bind_values!("ids" => [1, 2, 3, 4, 5, 6, 7], "org" => [0]);
+
+        let mut composer = Composer::new(Dialect::Mysql);
+        composer.max_bind_params = 6;
+        let err = composer
+            .compose_with_values(&template, &values)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TooManyBindParams {
+                count: 15,
+                limit: 6,
+                ..
+            }
+        ));
+
+        // Positional: each id takes two placeholders, leaving room for 2 per chunk.
+        let chunks = composer.compose_chunked(&template, &values).unwrap();
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(
+            chunks[0].composed.sql,
+            "SELECT * FROM t WHERE org = ? AND id IN (?, ?) OR parent IN (?, ?)"
+        );
+        assert_eq!(chunks[3].ranges["ids"], 6..7);
+        assert_eq!(chunks[3].ranges["org"], 0..1);
+
+        // Numbered: ids share one run, so 5 fit beside org.
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.max_bind_params = 6;
+        let chunks = composer.compose_chunked(&template, &values).unwrap();
+        let ranges: Vec<_> = chunks.iter().map(|c| c.ranges["ids"].clone()).collect();
+        assert_eq!(ranges, vec![0..5, 5..7]);
+        assert!(chunks[1]
+            .composed
+            .sql
+            .ends_with("id IN ($1, $2) OR parent IN ($1, $2)"));
+
+        // Rows are never split.
+        let template = parser::parse_template(
+            "INSERT INTO t (a, b) VALUES :bind_rows(items (a, b))",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let values = crate::bind_values!("items" => [1, 2, 3, 4, 5, 6]);
+        composer.max_bind_params = 5;
+        let chunks = composer.compose_chunked(&template, &values).unwrap();
+        let ranges: Vec<_> = chunks.iter().map(|c| c.ranges["items"].clone()).collect();
+        assert_eq!(ranges, vec![0..4, 4..6]);
+
+        composer.max_bind_params = 1;
+        let err = composer.compose_chunked(&template, &values).unwrap_err();
+        assert!(matches!(err, Error::TooManyBindParams { .. }));
+    }
+
+    #[test]
+    fn test_if_blocks_follow_binding_presence() {
+        let sql = "SELECT * FROM users\nWHERE org = :bind(org)\n\
+                   :if(name)\n  AND name = :bind(name)\n\
+                   :else\n  AND active = :bind(active)\n\
+                   :endif\nAND age > :bind(age)";
+        let template = parser::parse_template(sql, TemplateSource::Literal("test".into())).unwrap();
+
+        // Dropped bindings take no placeholder and skip their NULL checks.
+        let values = crate::bind_values!("org" => [1], "active" => [2], "age" => [3]);
+        let result = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM users\nWHERE org = $3\n  AND active = $1\nAND age > $2"
+        );
+        assert_eq!(result.bind_params, vec!["active", "age", "org"]);
+        assert!(!result.params.contains_key("name"));
+
+        let values = crate::bind_values!("org" => [1], "name" => [2], "age" => [3]);
+        let result = Composer::new(Dialect::Mysql)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM users\nWHERE org = ?\n  AND name = ?\nAND age > ?"
+        );
+        assert_eq!(result.bind_params, vec!["org", "name", "age"]);
+
+        // Without values every `:if` branch is taken.
+        let result = Composer::new(Dialect::Sqlite).compose(&template).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM users\nWHERE org = ?3\n  AND name = ?2\nAND age > ?1"
+        );
+    }
+
+    #[test]
+    fn test_command_sources_see_values_mysql() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(
+            &dir,
+            "parts.sqlc",
+            "SELECT id FROM parts WHERE id IN (:bind(ids))\n\
+             :if(color)AND color = :bind(color):else AND color IS NULL:endif",
+        );
+        write_temp_file(&dir, "root.sqlc", ":count(parts.sqlc)");
+
+        let mut composer = Composer::new(Dialect::Mysql);
+        composer.add_search_path(dir.path().to_path_buf());
+        let root = composer.template(Path::new("root.sqlc")).unwrap();
+
+        let values = crate::bind_values!("ids" => [1, 2, 3]);
+        let result = composer.compose_with_values(&root, &values).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT COUNT(*) FROM (\nSELECT id FROM parts WHERE id IN (?, ?, ?)\n \
+             AND color IS NULL\n) AS _count_sub"
+        );
+        assert_eq!(result.bind_params, vec!["ids", "ids", "ids"]);
+        assert_eq!(result.params["ids"].indices, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_command_sources_see_idents_mysql() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(
+            &dir,
+            "sets.sqlc",
+            "SELECT name FROM sets ORDER BY :ident(sort ONE OF name, year)",
+        );
+        write_temp_file(&dir, "root.sqlc", ":json(name OF sets.sqlc)");
+
+        let mut composer = Composer::new(Dialect::Mysql);
+        composer.add_search_path(dir.path().to_path_buf());
+        let root = composer.template(Path::new("root.sqlc")).unwrap();
+
+        let values: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+        let idents = BTreeMap::from([("sort".to_string(), "year".to_string())]);
+        let result = composer
+            .compose_with_idents(&root, &values, &idents)
+            .unwrap();
+        assert!(result.sql.contains("ORDER BY `year`"), "{}", result.sql);
+
+        let err = composer.compose(&root).unwrap_err();
+        assert!(matches!(
+            err.root(),
+            Error::MissingIdent { name, template }
+                if name == "sort" && *template == TemplateSource::File(dir.path().join("sets.sqlc"))
+        ));
+    }
+
+    #[test]
+    fn test_constraint_error_names_composed_template() {
+        let dir = TempDir::new().unwrap();
+        let child = write_temp_file(
+            &dir,
+            "child.sqlc",
+            "SELECT id FROM t WHERE id IN (:bind(ids EXPECTING 1..2))",
+        );
+
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.add_search_path(dir.path().to_path_buf());
+
+        let template = Template {
+            elements: vec![Element::Compose(ComposeRef {
+                target: ComposeTarget::Path(PathBuf::from("child.sqlc")),
+                slots: vec![],
+            })],
+            source: TemplateSource::Literal("test".into()),
+        };
+        let values: BTreeMap<String, Vec<i32>> = BTreeMap::from([("ids".into(), vec![1, 2, 3])]);
+        let err = composer
+            .compose_with_values(&template, &values)
+            .unwrap_err();
+        match err.root() {
+            Error::ValueCountOutOfRange { template, .. } => {
+                assert_eq!(*template, TemplateSource::File(child));
+            }
+            other => panic!("expected ValueCountOutOfRange, got {:?}", other),
+        }
+    }
+
+    // ── Slot tests ────────────────────────────────────────────────────
+
+    /// Helper: write a temp file and return its path.
+    fn write_temp_file(dir: &TempDir, name: &str, content: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        let mut f = std::fs::File::create(&path).unwrap();
+        f.write_all(content.as_bytes()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_slot_resolution_numbered() {
+        let dir = TempDir::new().unwrap();
+
+        // Filter template: standalone query
+        write_temp_file(
+            &dir,
+            "filter.sqlc",
+            "SELECT part_num FROM parts WHERE color = :bind(color)",
         );
 
         // Base template with @filter slot
@@ -1242,6 +2793,23 @@ mod tests {
         assert_eq!(result.bind_params, vec!["color"]);
     }
 
+    #[test]
+    fn test_template_store_follows_backslash_escapes() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(&dir, "q.sqlc", "SELECT 'it\\'s' AS a, :bind(x)");
+
+        let mut composer = Composer::new(Dialect::Mysql);
+        composer.add_search_path(dir.path().to_path_buf());
+        let template = composer.template(Path::new("q.sqlc")).unwrap();
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(result.sql, "SELECT 'it\\'s' AS a, ?");
+
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.add_search_path(dir.path().to_path_buf());
+        let err = composer.template(Path::new("q.sqlc")).unwrap_err();
+        assert!(matches!(err, Error::Parse { .. }));
+    }
+
     #[test]
     fn test_missing_slot_error() {
         let dir = TempDir::new().unwrap();
@@ -1264,6 +2832,24 @@ mod tests {
             source: TemplateSource::Literal("test".into()),
         };
 
+        let err = composer.compose(&template).unwrap_err();
+        assert!(matches!(err.root(), Error::MissingSlot { .. }));
+        match err {
+            Error::Located {
+                location, chain, ..
+            } => {
+                assert_eq!(location.source, TemplateSource::File(dir.path().join("base.sqlc")));
+                assert_eq!((location.line, location.column), (2, 5));
+                assert_eq!(
+                    location.snippet.as_deref(),
+                    Some("  |\n2 |     :compose(@filter)\n  |     ^")
+                );
+                assert_eq!(chain, vec!["<test>", "base.sqlc", "@filter"]);
+            }
+            other => panic!("expected Located, got {:?}", other),
+        }
+
+        composer.locate_errors = false;
         let err = composer.compose(&template).unwrap_err();
         match err {
             Error::MissingSlot { name } => assert_eq!(name, "filter"),
@@ -1302,7 +2888,7 @@ mod tests {
         };
 
         let err = composer.compose(&template).unwrap_err();
-        match err {
+        match err.root() {
             Error::MissingSlot { name } => assert_eq!(name, "deep"),
             other => panic!("expected MissingSlot, got {:?}", other),
         }
@@ -1366,7 +2952,34 @@ mod tests {
 
         let template = parser::parse_template_file(&dir.path().join("a.sqlc")).unwrap();
         let err = composer.compose(&template).unwrap_err();
-        assert!(matches!(err, Error::CircularReference { .. }));
+        assert!(matches!(err.root(), Error::CircularReference { .. }));
+    }
+
+    #[test]
+    fn test_command_source_circular_reference() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(&dir, "self.sqlc", "SELECT 1 UNION :count(self.sqlc)");
+        write_temp_file(&dir, "a.sqlc", ":union(b.sqlc, c.sqlc)");
+        write_temp_file(&dir, "b.sqlc", "SELECT 2");
+        write_temp_file(&dir, "c.sqlc", ":compose(a.sqlc)");
+
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.add_search_path(dir.path().to_path_buf());
+
+        for path in ["self.sqlc", "a.sqlc"] {
+            let template = composer.template(Path::new(path)).unwrap();
+            let err = composer.compose(&template).unwrap_err();
+            assert!(
+                matches!(err.root(), Error::CircularReference { .. }),
+                "{path}: {err:?}"
+            );
+        }
+
+        // The same source twice in one command is not a cycle.
+        write_temp_file(&dir, "twice.sqlc", ":union(ALL b.sqlc, b.sqlc)");
+        let template = composer.template(Path::new("twice.sqlc")).unwrap();
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(result.sql, "SELECT 2\nUNION ALL\nSELECT 2");
     }
 
     #[test]
@@ -1474,14 +3087,27 @@ mod tests {
         let err = composer.compose(&template).unwrap_err();
         assert!(matches!(err, Error::MissingSlot { .. }));
 
-        // Now compose with slots provided via internal API
-        let mut visited = HashSet::new();
-        let mut slots = HashMap::new();
-        slots.insert("source".into(), PathBuf::from("inner.sqlc"));
-        let result = composer
-            .compose_inner(&template, &slots, &mut visited)
-            .unwrap();
-        assert_eq!(result.sql, "WITH cte AS (\n    SELECT 42\n)\nSELECT * FROM cte");
+        // Now compose it with the slot provided
+        write_temp_file(
+            &dir,
+            "outer.sqlc",
+            "WITH cte AS (\n    :compose(@source)\n)\nSELECT * FROM cte",
+        );
+        let template = Template {
+            elements: vec![Element::Compose(ComposeRef {
+                target: ComposeTarget::Path(PathBuf::from("outer.sqlc")),
+                slots: vec![SlotAssignment {
+                    name: "source".into(),
+                    path: PathBuf::from("inner.sqlc"),
+                }],
+            })],
+            source: TemplateSource::Literal("test".into()),
+        };
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(
+            result.sql,
+            "WITH cte AS (\n    SELECT 42\n)\nSELECT * FROM cte"
+        );
     }
 
     #[test]
@@ -1528,4 +3154,629 @@ mod tests {
         );
         assert_eq!(result.bind_params, vec!["active"]);
     }
+
+    // ── Mock tests ────────────────────────────────────────────────────
+
+    fn users_mock() -> MockTable {
+        let mut mock = MockTable::new("users");
+        mock.rows = crate::mock_rows![
+            {"id" => "1", "name" => "Alice"},
+            {"id" => "2", "name" => "Bob"},
+        ];
+        mock
+    }
+
+    #[test]
+    fn test_mock_substitution_numbered() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(
+            &dir,
+            "active.sqlc",
+            "SELECT u.id FROM users u WHERE u.name = :bind(name)",
+        );
+
+        let mut composer = Composer::new(Dialect::Sqlite);
+        composer.add_search_path(dir.path().to_path_buf());
+        composer.add_mock_table(users_mock());
+
+        let template = parser::parse_template(
+            "SELECT COUNT(*) FROM (:compose(active.sqlc))",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT COUNT(*) FROM (SELECT u.id FROM (SELECT '1' AS id, 'Alice' AS name\nUNION ALL\nSELECT '2', 'Bob') u WHERE u.name = ?1)"
+        );
+        assert_eq!(result.bind_params, vec!["name"]);
+    }
+
+    #[test]
+    fn test_mock_substitution_positional() {
+        let mut composer = Composer::new(Dialect::Mysql);
+        composer.add_mock_table(users_mock());
+
+        let template = parser::parse_template(
+            "SELECT name FROM users WHERE id IN (:bind(ids))",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let values: BTreeMap<String, Vec<i32>> = BTreeMap::from([("ids".into(), vec![1, 2])]);
+        let result = composer.compose_with_values(&template, &values).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT name FROM (SELECT '1' AS id, 'Alice' AS name\nUNION ALL\nSELECT '2', 'Bob') AS users WHERE id IN (?, ?)"
+        );
+    }
+
+    #[test]
+    fn test_no_mocks_leaves_sql_unchanged() {
+        let composer = Composer::new(Dialect::Postgres);
+        let template = parser::parse_template(
+            "SELECT name FROM users u",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(result.sql, "SELECT name FROM users u");
+    }
+
+    // ── Typed binding tests ───────────────────────────────────────────
+
+    fn typed_template() -> Template {
+        parser::parse_template(
+            "SELECT * FROM users WHERE id = :bind(user_id AS int8) AND name = :bind(name)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_typed_bind_casts() {
+        let template = typed_template();
+
+        let pg = Composer::new(Dialect::Postgres).compose(&template).unwrap();
+        assert_eq!(
+            pg.sql,
+            "SELECT * FROM users WHERE id = $2::int8 AND name = $1"
+        );
+        assert_eq!(pg.bind_type("user_id"), Some(&BindType::Int8));
+        assert_eq!(pg.bind_type("name"), None);
+
+        let sqlite = Composer::new(Dialect::Sqlite).compose(&template).unwrap();
+        assert_eq!(
+            sqlite.sql,
+            "SELECT * FROM users WHERE id = CAST(?2 AS INTEGER) AND name = ?1"
+        );
+
+        let mysql = Composer::new(Dialect::Mysql).compose(&template).unwrap();
+        assert_eq!(
+            mysql.sql,
+            "SELECT * FROM users WHERE id = CAST(? AS SIGNED) AND name = ?"
+        );
+    }
+
+    #[test]
+    fn test_typed_bind_multi_value_casts_each_placeholder() {
+        let template = parser::parse_template(
+            "WHERE id IN (:bind(ids AS int4))",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let values: BTreeMap<String, Vec<i32>> = BTreeMap::from([("ids".into(), vec![1, 2])]);
+        let result = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(result.sql, "WHERE id IN ($1::int4, $2::int4)");
+    }
+
+    #[test]
+    fn test_typed_bind_without_casts() {
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.emit_casts = false;
+        let result = composer.compose(&typed_template()).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM users WHERE id = $2 AND name = $1"
+        );
+        assert_eq!(result.bind_type("user_id"), Some(&BindType::Int8));
+    }
+
+    #[test]
+    fn test_array_type_unsupported_on_sqlite() {
+        let template = parser::parse_template(
+            "WHERE tags = :bind(tags AS text[])",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+
+        let pg = Composer::new(Dialect::Postgres).compose(&template).unwrap();
+        assert_eq!(pg.sql, "WHERE tags = $1::text[]");
+
+        let err = Composer::new(Dialect::Sqlite)
+            .compose(&template)
+            .unwrap_err();
+        assert!(matches!(
+            err.root(),
+            Error::UnsupportedBindType { name, dialect, .. } if name == "tags" && dialect == "Sqlite"
+        ));
+    }
+
+    #[test]
+    fn test_conflicting_bind_types() {
+        let template = parser::parse_template(
+            "WHERE a = :bind(id AS int8) OR b = :bind(id AS text)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+
+        for dialect in [Dialect::Postgres, Dialect::Mysql] {
+            let err = Composer::new(dialect).compose(&template).unwrap_err();
+            assert!(matches!(
+                err.root(),
+                Error::ConflictingBindTypes {
+                    first: BindType::Int8,
+                    second: BindType::Text,
+                    ..
+                }
+            ));
+        }
+    }
+
+    // ── Parameter map tests ───────────────────────────────────────────
+
+    #[test]
+    fn test_params_numbered() {
+        let dir = TempDir::new().unwrap();
+        let filter = write_temp_file(
+            &dir,
+            "filter.sqlc",
+            "WHERE id IN (:bind(ids EXPECTING 1..3))",
+        );
+        let sql = format!(
+            "SELECT :bind(ids), :bind(a) FROM t :compose({})",
+            filter.display()
+        );
+        let template =
+            parser::parse_template(&sql, TemplateSource::Literal("root".into())).unwrap();
+        let values = crate::bind_values!("a" => [1], "ids" => [2, 3]);
+
+        let result = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(result.sql, "SELECT $2, $3, $1 FROM t WHERE id IN ($2, $3)");
+
+        let ids = &result.params["ids"];
+        assert_eq!(ids.indices, vec![2, 3]);
+        assert_eq!(ids.occurrences, 2);
+        assert_eq!(ids.template, TemplateSource::Literal("root".into()));
+        assert_eq!((ids.min_values, ids.max_values), (None, None));
+        assert_eq!(result.params["a"].indices, vec![1]);
+    }
+
+    #[test]
+    fn test_params_positional_compose_offsets() {
+        let dir = TempDir::new().unwrap();
+        let filter = write_temp_file(&dir, "filter.sqlc", "WHERE id = :bind(id EXPECTING 1..1)");
+        let sql = format!(
+            "SELECT :bind(a), :bind(id) FROM t :compose({})",
+            filter.display()
+        );
+        let template =
+            parser::parse_template(&sql, TemplateSource::Literal("root".into())).unwrap();
+
+        let result = Composer::new(Dialect::Mysql).compose(&template).unwrap();
+        assert_eq!(result.bind_params, vec!["a", "id", "id"]);
+        assert_eq!(result.params["a"].indices, vec![1]);
+
+        let id = &result.params["id"];
+        assert_eq!(id.indices, vec![2, 3]);
+        assert_eq!(id.occurrences, 2);
+        assert_eq!(id.template, TemplateSource::Literal("root".into()));
+    }
+
+    // ── Template cache tests ──────────────────────────────────────────
+
+    #[test]
+    fn test_shared_template_parsed_once() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(&dir, "shared.sqlc", "SELECT id FROM t WHERE a = :bind(a)");
+        write_temp_file(
+            &dir,
+            "root.sqlc",
+            ":compose(shared.sqlc)\nUNION ALL\n:compose(shared.sqlc)",
+        );
+
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.add_search_path(dir.path().to_path_buf());
+
+        let root = composer.template(Path::new("root.sqlc")).unwrap();
+        let result = composer.compose(&root).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT id FROM t WHERE a = $1\nUNION ALL\nSELECT id FROM t WHERE a = $1"
+        );
+        assert_eq!(composer.templates.len(), 2);
+
+        let shared = composer.template(Path::new("shared.sqlc")).unwrap();
+        assert!(Arc::ptr_eq(
+            &shared,
+            &composer.template(Path::new("shared.sqlc")).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_comment_style_applies_to_composed_files() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(
+            &dir,
+            "shared.sqlc",
+            "## keys\nSELECT data #>> '{k}' FROM t WHERE a = :bind(a)",
+        );
+        write_temp_file(&dir, "root.sqlc", ":compose(shared.sqlc)");
+
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.add_search_path(dir.path().to_path_buf());
+        composer.set_comments(CommentStyle::DoubleHash);
+        assert_eq!(composer.comments(), CommentStyle::DoubleHash);
+
+        let root = composer.template(Path::new("root.sqlc")).unwrap();
+        let result = composer.compose(&root).unwrap();
+        assert_eq!(result.sql, "SELECT data #>> '{k}' FROM t WHERE a = $1");
+    }
+
+    #[test]
+    fn test_dependency_graph() {
+        let dir = TempDir::new().unwrap();
+        let filter = write_temp_file(&dir, "filter.sqlc", "WHERE a = :bind(a)");
+        let base = write_temp_file(&dir, "base.sqlc", "SELECT 1 :compose(@filter)");
+        let other = write_temp_file(&dir, "other.sqlc", "SELECT 2");
+        let root = write_temp_file(
+            &dir,
+            "root.sqlc",
+            ":compose(base.sqlc, @filter = filter.sqlc)\n:union(other.sqlc, missing.sqlc)",
+        );
+
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.add_search_path(dir.path().to_path_buf());
+        composer.template(Path::new("root.sqlc")).unwrap();
+
+        let graph = composer.dependency_graph().unwrap();
+        assert_eq!(
+            graph.dependencies(&root),
+            &[
+                Dependency {
+                    path: base.clone(),
+                    kind: DependencyKind::Compose,
+                },
+                Dependency {
+                    path: filter.clone(),
+                    kind: DependencyKind::Slot("filter".into()),
+                },
+                Dependency {
+                    path: other.clone(),
+                    kind: DependencyKind::Command(CommandKind::Union),
+                },
+                Dependency {
+                    path: PathBuf::from("missing.sqlc"),
+                    kind: DependencyKind::Command(CommandKind::Union),
+                },
+            ]
+        );
+        assert!(graph.dependencies(&base).is_empty());
+        assert_eq!(graph.dependents(&filter), vec![root.as_path()]);
+        assert!(graph.closure(&root).contains(&other));
+    }
+
+    /// Compose `root` against `a.sqlc` and `b.sqlc` for each dialect.
+    fn compose_commands(root: &str, dialect: Dialect) -> Result<String> {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(&dir, "a.sqlc", "SELECT x, y FROM a WHERE k = :bind(k)");
+        write_temp_file(&dir, "b.sqlc", "SELECT x, y FROM b");
+        write_temp_file(&dir, "root.sqlc", root);
+
+        let mut composer = Composer::new(dialect);
+        composer.add_search_path(dir.path().to_path_buf());
+        let root = composer.template(Path::new("root.sqlc"))?;
+        Ok(composer.compose(&root)?.sql)
+    }
+
+    #[test]
+    fn test_union_distinct_per_dialect() {
+        let root = ":union(DISTINCT a.sqlc, b.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Postgres).unwrap(),
+            "SELECT x, y FROM a WHERE k = $1\nUNION DISTINCT\nSELECT x, y FROM b"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Sqlite).unwrap(),
+            "SELECT x, y FROM a WHERE k = ?1\nUNION\nSELECT x, y FROM b"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Mysql).unwrap(),
+            "SELECT x, y FROM a WHERE k = ?\nUNION DISTINCT\nSELECT x, y FROM b"
+        );
+
+        let root = ":union(a.sqlc, b.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::ClickHouse).unwrap(),
+            "SELECT x, y FROM a WHERE k = {k:String}\nUNION DISTINCT\nSELECT x, y FROM b"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Oracle).unwrap(),
+            "SELECT x, y FROM a WHERE k = :1\nUNION\nSELECT x, y FROM b"
+        );
+    }
+
+    #[test]
+    fn test_intersect_except_per_dialect() {
+        let root = ":intersect(a.sqlc, b.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Postgres).unwrap(),
+            "SELECT x, y FROM a WHERE k = $1\nINTERSECT\nSELECT x, y FROM b"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::ClickHouse).unwrap(),
+            "SELECT x, y FROM a WHERE k = {k:String}\nINTERSECT DISTINCT\nSELECT x, y FROM b"
+        );
+
+        let root = ":except(ALL a.sqlc, b.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Mysql).unwrap(),
+            "SELECT x, y FROM a WHERE k = ?\nEXCEPT ALL\nSELECT x, y FROM b"
+        );
+        let err = compose_commands(root, Dialect::Sqlite).unwrap_err();
+        assert_eq!(
+            err.root().to_string(),
+            ":except() needs EXCEPT ALL, which Sqlite does not support"
+        );
+
+        let root = ":except(DISTINCT a.sqlc, b.sqlc, a.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Oracle).unwrap(),
+            "SELECT x, y FROM a WHERE k = :1\nMINUS\nSELECT x, y FROM b\n\
+             MINUS\nSELECT x, y FROM a WHERE k = :2"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Mysql).unwrap(),
+            "SELECT x, y FROM a WHERE k = ?\nEXCEPT DISTINCT\nSELECT x, y FROM b\n\
+             EXCEPT DISTINCT\nSELECT x, y FROM a WHERE k = ?"
+        );
+    }
+
+    #[test]
+    fn test_exists_correlated_through_slot() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(
+            &dir,
+            "parts.sqlc",
+            "SELECT p.part_num FROM parts p WHERE p.color = :bind(color) AND :compose(@match)",
+        );
+        let by_set = write_temp_file(&dir, "by_set.sqlc", "p.set_num = s.set_num");
+        let root = write_temp_file(
+            &dir,
+            "root.sqlc",
+            "SELECT s.set_num FROM sets s WHERE s.year = :bind(year)\n\
+             AND :exists(parts.sqlc, @match = by_set.sqlc)",
+        );
+
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.add_search_path(dir.path().to_path_buf());
+        let template = composer.template(Path::new("root.sqlc")).unwrap();
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT s.set_num FROM sets s WHERE s.year = $2\nAND EXISTS (\n\
+             SELECT p.part_num FROM parts p WHERE p.color = $1 AND p.set_num = s.set_num\n)"
+        );
+        assert_eq!(result.bind_params, vec!["color", "year"]);
+
+        let graph = composer.dependency_graph().unwrap();
+        assert!(graph.dependencies(&root).contains(&Dependency {
+            path: by_set,
+            kind: DependencyKind::Slot("match".into()),
+        }));
+
+        let mut composer = Composer::new(Dialect::Mysql);
+        composer.add_search_path(dir.path().to_path_buf());
+        write_temp_file(
+            &dir,
+            "root.sqlc",
+            "SELECT s.set_num FROM sets s WHERE s.year = :bind(year)\n\
+             AND :not_exists(parts.sqlc, @match = by_set.sqlc)",
+        );
+        let template = composer.template(Path::new("root.sqlc")).unwrap();
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT s.set_num FROM sets s WHERE s.year = ?\nAND NOT EXISTS (\n\
+             SELECT p.part_num FROM parts p WHERE p.color = ? AND p.set_num = s.set_num\n)"
+        );
+        assert_eq!(result.bind_params, vec!["year", "color"]);
+    }
+
+    #[test]
+    fn test_json_per_dialect() {
+        let root = ":json(x, y OF a.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Postgres).unwrap(),
+            "SELECT COALESCE(json_agg(json_build_object('x', x, 'y', y)), '[]') FROM (\n\
+             SELECT x, y FROM a WHERE k = $1\n) AS _json_sub"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Sqlite).unwrap(),
+            "SELECT json_group_array(json_object('x', x, 'y', y)) FROM (\n\
+             SELECT x, y FROM a WHERE k = ?1\n) AS _json_sub"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Mysql).unwrap(),
+            "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('x', x, 'y', y)), JSON_ARRAY()) FROM (\n\
+             SELECT x, y FROM a WHERE k = ?\n) AS _json_sub"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Oracle).unwrap(),
+            "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('x' VALUE x, 'y' VALUE y) RETURNING CLOB), \
+             TO_CLOB('[]')) FROM (\nSELECT x, y FROM a WHERE k = :1\n) \"_json_sub\""
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::SqlServer).unwrap(),
+            "SELECT COALESCE((SELECT x, y FROM (\nSELECT x, y FROM a WHERE k = @p1\n) \
+             AS _json_sub FOR JSON PATH, INCLUDE_NULL_VALUES), '[]')"
+        );
+        let err = compose_commands(root, Dialect::ClickHouse).unwrap_err();
+        assert_eq!(
+            err.root().to_string(),
+            ":json() needs a JSON array aggregate, which ClickHouse does not support"
+        );
+
+        let root = ":json(a.sqlc, b.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Postgres).unwrap(),
+            "SELECT COALESCE(json_agg(row_to_json(_json_sub)), '[]') FROM (\n\
+             SELECT x, y FROM a WHERE k = $1\nUNION\nSELECT x, y FROM b\n) AS _json_sub"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::SqlServer).unwrap(),
+            "SELECT COALESCE((SELECT * FROM (\n\
+             SELECT x, y FROM a WHERE k = @p1\nUNION\nSELECT x, y FROM b\n) \
+             AS _json_sub FOR JSON PATH, INCLUDE_NULL_VALUES), '[]')"
+        );
+        let err = compose_commands(root, Dialect::Sqlite).unwrap_err();
+        assert_eq!(
+            err.root().to_string(),
+            ":json() needs a JSON array of whole rows, which Sqlite does not support"
+        );
+    }
+
+    #[test]
+    fn test_paginate_per_dialect() {
+        let root = ":paginate(x, y OF a.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Postgres).unwrap(),
+            "SELECT * FROM (\nSELECT x, y FROM a WHERE k = $1\n) AS _page_sub\n\
+             ORDER BY x, y\nLIMIT $3 OFFSET $2"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::SqlServer).unwrap(),
+            "SELECT * FROM (\nSELECT x, y FROM a WHERE k = @p1\n) AS _page_sub\n\
+             ORDER BY x, y\nOFFSET @p2 ROWS FETCH NEXT @p3 ROWS ONLY"
+        );
+
+        let root = ":paginate(KEYSET x, y OF a.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Sqlite).unwrap(),
+            "SELECT * FROM (\nSELECT x, y FROM a WHERE k = ?3\n) AS _page_sub\n\
+             WHERE (x, y) > (?1, ?2)\nORDER BY x, y\nLIMIT ?4"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Mysql).unwrap(),
+            "SELECT * FROM (\nSELECT x, y FROM a WHERE k = ?\n) AS _page_sub\n\
+             WHERE (x, y) > (?, ?)\nORDER BY x, y\nLIMIT ?"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Oracle).unwrap(),
+            "SELECT * FROM (\nSELECT x, y FROM a WHERE k = :1\n) \"_page_sub\"\n\
+             WHERE x > :2 OR (x = :3 AND y > :4)\nORDER BY x, y\n\
+             OFFSET 0 ROWS FETCH NEXT :5 ROWS ONLY"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::ClickHouse).unwrap(),
+            "SELECT * FROM (\nSELECT x, y FROM a WHERE k = {k:String}\n) AS _page_sub\n\
+             WHERE (x, y) > ({after_x:String}, {after_y:String})\nORDER BY x, y\n\
+             LIMIT {page_size:Int64}"
+        );
+
+        let root = ":paginate(KEYSET x DESC, y DESC OF a.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Postgres).unwrap(),
+            "SELECT * FROM (\nSELECT x, y FROM a WHERE k = $3\n) AS _page_sub\n\
+             WHERE (x, y) < ($1, $2)\nORDER BY x DESC, y DESC\nLIMIT $4"
+        );
+        let root = ":paginate(KEYSET x DESC, y OF a.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Postgres).unwrap(),
+            "SELECT * FROM (\nSELECT x, y FROM a WHERE k = $3\n) AS _page_sub\n\
+             WHERE x < $1 OR (x = $1 AND y > $2)\nORDER BY x DESC, y\nLIMIT $4"
+        );
+    }
+
+    #[test]
+    fn test_paginate_adds_bind_params() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(&dir, "a.sqlc", "SELECT id FROM a WHERE k = :bind(k)");
+        let sql = "SELECT * FROM b WHERE id IN (:paginate(KEYSET id OF a.sqlc))";
+        let root = parser::parse_template(sql, TemplateSource::Literal("test".into())).unwrap();
+
+        let mut composer = Composer::new(Dialect::Mysql);
+        composer.add_search_path(dir.path().to_path_buf());
+        let result = composer.compose(&root).unwrap();
+        assert_eq!(result.bind_params, vec!["k", "after_id", "page_size"]);
+
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.add_search_path(dir.path().to_path_buf());
+        let values = crate::bind_values!("k" => [1], "after_id" => [20]);
+        let err = composer.compose_with_values(&root, &values).unwrap_err();
+        assert!(matches!(
+            err.root(),
+            Error::NullBinding { name, .. } if name == "page_size"
+        ));
+
+        let values = crate::bind_values!("k" => [1], "after_id" => [20], "page_size" => [10]);
+        let result = composer.compose_with_values(&root, &values).unwrap();
+        assert_eq!(result.bind_params, vec!["after_id", "k", "page_size"]);
+        let page = "WHERE id > $1\nORDER BY id\nLIMIT $3)";
+        assert!(result.sql.ends_with(page));
+
+        // The first page has no cursor.
+        let values = crate::bind_values!("k" => [1], "page_size" => [10]);
+        let result = composer.compose_with_values(&root, &values).unwrap();
+        assert_eq!(result.bind_params, vec!["k", "page_size"]);
+        let page = ") AS _page_sub\nORDER BY id\nLIMIT $2)";
+        assert!(result.sql.ends_with(page));
+    }
+
+    #[test]
+    fn test_count_distinct_per_dialect() {
+        let root = ":count(DISTINCT x, y OF a.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Postgres).unwrap(),
+            "SELECT COUNT(DISTINCT (x, y)) FROM (\nSELECT x, y FROM a WHERE k = $1\n) AS _count_sub"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Mysql).unwrap(),
+            "SELECT COUNT(DISTINCT x, y) FROM (\nSELECT x, y FROM a WHERE k = ?\n) AS _count_sub"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Sqlite).unwrap(),
+            "SELECT COUNT(*) FROM (\nSELECT DISTINCT x, y FROM (\n\
+             SELECT x, y FROM a WHERE k = ?1\n) AS _distinct_sub\n) AS _count_sub"
+        );
+        assert_eq!(
+            compose_commands(root, Dialect::Oracle).unwrap(),
+            "SELECT COUNT(*) FROM (\nSELECT DISTINCT x, y FROM (\n\
+             SELECT x, y FROM a WHERE k = :1\n) \"_distinct_sub\"\n) \"_count_sub\""
+        );
+
+        let root = ":count(DISTINCT x OF a.sqlc, b.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Sqlite).unwrap(),
+            "SELECT COUNT(DISTINCT x) FROM (\nSELECT x, y FROM a WHERE k = ?1\n\
+             UNION\nSELECT x, y FROM b\n) AS _count_sub"
+        );
+
+        let root = ":count(x, y OF a.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Postgres).unwrap(),
+            "SELECT COUNT(*) FROM (\nSELECT x, y FROM a WHERE k = $1\n) AS _count_sub\n\
+             WHERE x IS NOT NULL AND y IS NOT NULL"
+        );
+
+        let root = ":count(DISTINCT a.sqlc)";
+        assert_eq!(
+            compose_commands(root, Dialect::Mysql).unwrap(),
+            "SELECT COUNT(*) FROM (\nSELECT DISTINCT * FROM (\n\
+             SELECT x, y FROM a WHERE k = ?\n) AS _distinct_sub\n) AS _count_sub"
+        );
+    }
 }
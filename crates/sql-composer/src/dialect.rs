@@ -0,0 +1,773 @@
+//! The [`SqlDialect`] trait: everything the composer needs to know about a
+//! database engine's SQL.
+//!
+//! [`Dialect`] implements it for the built-in engines. Implement it for your
+//! own type to compose for an engine sql-composer doesn't know about:
+//!
+//! ```
+//! use sql_composer::composer::Composer;
+//! use sql_composer::dialect::SqlDialect;
+//! use sql_composer::parser::parse_template;
+//! use sql_composer::types::TemplateSource;
+//!
+//! /// Named `@p1` placeholders, quoted with brackets.
+//! struct Bracketed;
+//!
+//! impl SqlDialect for Bracketed {
+//!     fn name(&self) -> &str {
+//!         "Bracketed"
+//!     }
+//!
+//!     fn placeholder(&self, index: usize) -> String {
+//!         format!("@p{index}")
+//!     }
+//!
+//!     fn supports_numbered_placeholders(&self) -> bool {
+//!         true
+//!     }
+//!
+//!     fn max_bind_params(&self) -> usize {
+//!         2100
+//!     }
+//!
+//!     fn quote_ident(&self, name: &str) -> String {
+//!         format!("[{}]", name.replace(']', "]]"))
+//!     }
+//! }
+//!
+//! let template = parse_template(
+//!     "SELECT * FROM t WHERE a = :bind(a) OR b = :bind(a)",
+//!     TemplateSource::Literal("example".into()),
+//! )
+//! .unwrap();
+//! let result = Composer::new(Bracketed).compose(&template).unwrap();
+//! assert_eq!(result.sql, "SELECT * FROM t WHERE a = @p1 OR b = @p1");
+//! ```
+
+use std::any::Any;
+
+use crate::types::{BindType, Dialect};
+
+/// How to write SQL for one database engine.
+///
+/// Only the placeholder syntax, its numbering and the bind parameter limit
+/// are required; the other methods default to standard SQL.
+pub trait SqlDialect: Any + Send + Sync {
+    /// The dialect's name, as reported in errors: `Postgres`.
+    fn name(&self) -> &str;
+
+    /// Format a placeholder for the given 1-based parameter index.
+    fn placeholder(&self, index: usize) -> String;
+
+    /// Format the placeholder for a value of a binding declared `AS ty`, for
+    /// dialects whose placeholders carry a name or a type. `name` is the
+    /// name the value is bound under: the binding's own name, suffixed `_2`,
+    /// `_3`, ... for the later values of a binding that takes several.
+    /// Defaults to [`placeholder()`](Self::placeholder); any cast is added
+    /// separately.
+    fn bind_placeholder(&self, index: usize, name: &str, ty: Option<&BindType>) -> String {
+        let _ = (name, ty);
+        self.placeholder(index)
+    }
+
+    /// Whether placeholders are numbered (`$1`, `?1`) rather than positional
+    /// (`?`).
+    ///
+    /// Numbered dialects give every occurrence of a name the same
+    /// placeholders, numbered in the composer's
+    /// [`PlaceholderOrdering`](crate::types::PlaceholderOrdering). Positional
+    /// dialects give each occurrence its own placeholders, in document order.
+    fn supports_numbered_placeholders(&self) -> bool;
+
+    /// The most bind parameters one statement may carry.
+    fn max_bind_params(&self) -> usize;
+
+    /// Quote `name` as an identifier. Defaults to `"order"`, doubling any
+    /// embedded `"`.
+    fn quote_ident(&self, name: &str) -> String {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+
+    /// Quote `value` as a string literal. Defaults to `'it''s'`, doubling any
+    /// embedded `'`.
+    fn quote_literal(&self, value: &str) -> String {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+
+    /// A boolean literal. Defaults to `TRUE` and `FALSE`.
+    fn bool_literal(&self, value: bool) -> String {
+        if value { "TRUE" } else { "FALSE" }.to_string()
+    }
+
+    /// The constructs the dialect accepts where engines disagree, consulted
+    /// when composing `:count()`, `:paginate()` and the set operation
+    /// commands. Defaults to [`Capabilities::STANDARD`].
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::STANDARD
+    }
+
+    /// Wrap a placeholder in a cast to `ty`, or `None` if the dialect has no
+    /// equivalent type. Defaults to `None`, so typed bindings compose only
+    /// with [`Composer::emit_casts`](crate::composer::Composer::emit_casts)
+    /// turned off.
+    fn cast(&self, placeholder: &str, ty: &BindType) -> Option<String> {
+        let _ = (placeholder, ty);
+        None
+    }
+
+    /// The SQL before and after the derived table `alias` that turns its
+    /// rows into a JSON array of objects keyed by `columns`, for `:json()`,
+    /// or `None` if the dialect can't build one, e.g. `SELECT json_agg(...)
+    /// FROM ` and nothing. With no `columns`, the objects hold whole rows. An
+    /// empty table should give `[]`. Defaults to `None`.
+    fn json_array(&self, alias: &str, columns: &[String]) -> Option<(String, String)> {
+        let _ = (alias, columns);
+        None
+    }
+
+    /// A one-row `SELECT` of `columns`, as used by
+    /// [`MockTable::to_sql()`](crate::mock::MockTable::to_sql). Defaults to
+    /// `SELECT {columns}`.
+    fn select_row(&self, columns: &str) -> String {
+        format!("SELECT {columns}")
+    }
+
+    /// Whether a binding declared `AS ARRAY` is bound as one array
+    /// parameter, so that `IN (:bind(ids AS ARRAY))` composes to
+    /// `= ANY($1)`. Defaults to `false`: the binding expands to one
+    /// placeholder per value as usual.
+    fn supports_array_binds(&self) -> bool {
+        false
+    }
+}
+
+/// The SQL constructs a dialect accepts where engines disagree.
+///
+/// The composer rewrites `:count()`, `:paginate()` and the set operation
+/// commands (`:union()`, `:intersect()`, `:except()`) to what the dialect
+/// accepts, and fails with
+/// [`Error::UnsupportedConstruct`](crate::Error::UnsupportedConstruct) when
+/// nothing it accepts is equivalent. A custom dialect overrides the fields
+/// that differ from [`Capabilities::STANDARD`]:
+///
+/// ```
+/// use sql_composer::dialect::Capabilities;
+///
+/// let capabilities = Capabilities {
+///     distinct_set_ops: false,
+///     ..Capabilities::STANDARD
+/// };
+/// assert!(capabilities.bare_set_ops);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `UNION DISTINCT`, `INTERSECT DISTINCT` and `EXCEPT DISTINCT` are
+    /// accepted. Without it a distinct set operation is written bare.
+    pub distinct_set_ops: bool,
+    /// A bare `UNION`, `INTERSECT` or `EXCEPT` is accepted and deduplicates.
+    /// Without it a plain set operation is written with `DISTINCT`.
+    pub bare_set_ops: bool,
+    /// `INTERSECT ALL` and `EXCEPT ALL` are accepted. `UNION ALL` always is.
+    pub all_set_ops: bool,
+    /// The keyword of `:except()`: `EXCEPT`, or `MINUS` on Oracle.
+    pub except_keyword: &'static str,
+    /// How to count distinct combinations of several columns.
+    pub count_distinct: CountDistinct,
+    /// A derived table's alias may follow `AS`.
+    pub derived_table_as: bool,
+    /// Unquoted identifiers may start with `_`. Without it the composer's own
+    /// aliases, such as `_count_sub`, are quoted.
+    pub leading_underscore: bool,
+    /// How to limit a query to one page of rows.
+    pub paging: Paging,
+    /// The page size and offset are bound as `int8`. Needed where a
+    /// placeholder carries a type and an untyped one is not a number, as on
+    /// ClickHouse, whose `LIMIT` rejects a `String`.
+    pub typed_paging: bool,
+    /// Row values compare in order, as in `(a, b) > (?, ?)`. Without it a
+    /// keyset predicate is written `a > ? OR (a = ? AND b > ?)`.
+    pub row_values: bool,
+    /// A backslash escapes the next character in a string literal, as in
+    /// `'it\'s'`. The composer's template store follows it, so that such a
+    /// string is skipped whole when scanning for macros.
+    pub backslash_escapes: bool,
+}
+
+impl Capabilities {
+    /// Standard SQL.
+    pub const STANDARD: Capabilities = Capabilities {
+        distinct_set_ops: true,
+        bare_set_ops: true,
+        all_set_ops: true,
+        except_keyword: "EXCEPT",
+        count_distinct: CountDistinct::Subquery,
+        derived_table_as: true,
+        leading_underscore: true,
+        paging: Paging::OffsetFetch,
+        typed_paging: false,
+        row_values: true,
+        backslash_escapes: false,
+    };
+}
+
+/// How a dialect counts distinct combinations of several columns, as in
+/// `:count(DISTINCT a, b OF source)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountDistinct {
+    /// `COUNT(DISTINCT a, b)`
+    List,
+    /// `COUNT(DISTINCT (a, b))`, counting row values
+    Row,
+    /// `COUNT(*)` over a `SELECT DISTINCT a, b` subquery
+    Subquery,
+}
+
+/// How a dialect limits a query to one page of rows, as in `:paginate()`,
+/// with the page size and offset bound as parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Paging {
+    /// `LIMIT ? OFFSET ?`
+    LimitOffset,
+    /// `OFFSET ? ROWS FETCH NEXT ? ROWS ONLY`
+    OffsetFetch,
+    /// No page size can be bound as a parameter.
+    Unsupported,
+}
+
+impl dyn SqlDialect {
+    /// The built-in [`Dialect`] this is, or `None` for any other
+    /// implementation.
+    pub fn builtin(&self) -> Option<Dialect> {
+        (self as &dyn Any).downcast_ref::<Dialect>().copied()
+    }
+}
+
+impl SqlDialect for Dialect {
+    fn name(&self) -> &str {
+        match self {
+            Dialect::Postgres => "Postgres",
+            Dialect::Mysql => "Mysql",
+            Dialect::Sqlite => "Sqlite",
+            Dialect::SqlServer => "SqlServer",
+            Dialect::Oracle => "Oracle",
+            Dialect::ClickHouse => "ClickHouse",
+        }
+    }
+
+    fn placeholder(&self, index: usize) -> String {
+        match self {
+            Dialect::Postgres => format!("${index}"),
+            Dialect::Mysql => "?".to_string(),
+            Dialect::Sqlite => format!("?{index}"),
+            Dialect::SqlServer => format!("@p{index}"),
+            Dialect::Oracle => format!(":{index}"),
+            Dialect::ClickHouse => format!("{{p{index}:String}}"),
+        }
+    }
+
+    /// ClickHouse placeholders name the parameter and its type:
+    /// `{id:Int64}`, set with `param_id`. Untyped bindings are `String`,
+    /// which ClickHouse converts on use.
+    fn bind_placeholder(&self, index: usize, name: &str, ty: Option<&BindType>) -> String {
+        match self {
+            Dialect::ClickHouse => {
+                let ty = ty.map_or_else(|| "String".to_string(), clickhouse_type);
+                format!("{{{name}:{ty}}}")
+            }
+            _ => self.placeholder(index),
+        }
+    }
+
+    /// MySQL's `?` is positional, and so is Oracle's `:1` here: every
+    /// occurrence of a binding takes the next index, so Oracle parameters
+    /// can be bound by position.
+    fn supports_numbered_placeholders(&self) -> bool {
+        !matches!(self, Dialect::Mysql | Dialect::Oracle)
+    }
+
+    /// 65535 on Postgres (the wire protocol's 16-bit count), MySQL (prepared
+    /// statements) and Oracle, 32766 on SQLite (`SQLITE_MAX_VARIABLE_NUMBER`
+    /// since 3.32), 2100 on SQL Server. ClickHouse has no limit.
+    fn max_bind_params(&self) -> usize {
+        match self {
+            Dialect::Postgres | Dialect::Mysql | Dialect::Oracle => 65535,
+            Dialect::Sqlite => 32766,
+            Dialect::SqlServer => 2100,
+            Dialect::ClickHouse => usize::MAX,
+        }
+    }
+
+    /// `` `order` `` on MySQL, `[order]` on SQL Server and `"order"`
+    /// elsewhere, doubling any embedded closing quote character.
+    fn quote_ident(&self, name: &str) -> String {
+        match self {
+            Dialect::Mysql => format!("`{}`", name.replace('`', "``")),
+            Dialect::SqlServer => format!("[{}]", name.replace(']', "]]")),
+            Dialect::Postgres | Dialect::Sqlite | Dialect::Oracle | Dialect::ClickHouse => {
+                format!("\"{}\"", name.replace('"', "\"\""))
+            }
+        }
+    }
+
+    /// MySQL and ClickHouse also treat backslash as an escape character in
+    /// string literals, so backslashes are doubled there.
+    fn quote_literal(&self, value: &str) -> String {
+        let escaped = value.replace('\'', "''");
+        match self {
+            Dialect::Mysql | Dialect::ClickHouse => {
+                format!("'{}'", escaped.replace('\\', "\\\\"))
+            }
+            Dialect::Postgres | Dialect::Sqlite | Dialect::SqlServer | Dialect::Oracle => {
+                format!("'{escaped}'")
+            }
+        }
+    }
+
+    /// SQLite, SQL Server and Oracle have no boolean literals and write `1`
+    /// and `0`.
+    fn bool_literal(&self, value: bool) -> String {
+        match (self, value) {
+            (Dialect::Sqlite | Dialect::SqlServer | Dialect::Oracle, true) => "1".to_string(),
+            (Dialect::Sqlite | Dialect::SqlServer | Dialect::Oracle, false) => "0".to_string(),
+            (_, true) => "TRUE".to_string(),
+            (_, false) => "FALSE".to_string(),
+        }
+    }
+
+    /// `$1::int8` on Postgres, `CAST(?1 AS INTEGER)` on SQLite,
+    /// `CAST(? AS SIGNED)` on MySQL, `CAST(@p1 AS BIGINT)` on SQL Server and
+    /// `CAST(:1 AS NUMBER(19))` on Oracle. ClickHouse placeholders are
+    /// already typed and are left as they are. Arrays cast only on Postgres
+    /// and ClickHouse.
+    fn cast(&self, placeholder: &str, ty: &BindType) -> Option<String> {
+        match self {
+            Dialect::Postgres => Some(format!("{placeholder}::{ty}")),
+            Dialect::Sqlite => {
+                let affinity = match ty {
+                    BindType::Bool | BindType::Int2 | BindType::Int4 | BindType::Int8 => "INTEGER",
+                    BindType::Float4 | BindType::Float8 => "REAL",
+                    BindType::Numeric => "NUMERIC",
+                    BindType::Bytea => "BLOB",
+                    BindType::Text
+                    | BindType::Date
+                    | BindType::Time
+                    | BindType::Timestamp
+                    | BindType::Timestamptz
+                    | BindType::Uuid
+                    | BindType::Json
+                    | BindType::Jsonb => "TEXT",
+                    BindType::Array(_) => return None,
+                };
+                Some(format!("CAST({placeholder} AS {affinity})"))
+            }
+            Dialect::Mysql => {
+                let target = match ty {
+                    BindType::Bool | BindType::Int2 | BindType::Int4 | BindType::Int8 => "SIGNED",
+                    BindType::Float4 => "FLOAT",
+                    BindType::Float8 => "DOUBLE",
+                    BindType::Numeric => "DECIMAL(65, 30)",
+                    BindType::Text => "CHAR",
+                    BindType::Bytea => "BINARY",
+                    BindType::Date => "DATE",
+                    BindType::Time => "TIME(6)",
+                    BindType::Timestamp | BindType::Timestamptz => "DATETIME(6)",
+                    BindType::Uuid => "CHAR(36)",
+                    BindType::Json | BindType::Jsonb => "JSON",
+                    BindType::Array(_) => return None,
+                };
+                Some(format!("CAST({placeholder} AS {target})"))
+            }
+            Dialect::SqlServer => {
+                let target = match ty {
+                    BindType::Bool => "BIT",
+                    BindType::Int2 => "SMALLINT",
+                    BindType::Int4 => "INT",
+                    BindType::Int8 => "BIGINT",
+                    BindType::Float4 => "REAL",
+                    BindType::Float8 => "FLOAT",
+                    BindType::Numeric => "DECIMAL(38, 18)",
+                    BindType::Text | BindType::Json | BindType::Jsonb => "NVARCHAR(MAX)",
+                    BindType::Bytea => "VARBINARY(MAX)",
+                    BindType::Date => "DATE",
+                    BindType::Time => "TIME",
+                    BindType::Timestamp => "DATETIME2",
+                    BindType::Timestamptz => "DATETIMEOFFSET",
+                    BindType::Uuid => "UNIQUEIDENTIFIER",
+                    BindType::Array(_) => return None,
+                };
+                Some(format!("CAST({placeholder} AS {target})"))
+            }
+            Dialect::Oracle => {
+                let target = match ty {
+                    BindType::Bool => "NUMBER(1)",
+                    BindType::Int2 => "NUMBER(5)",
+                    BindType::Int4 => "NUMBER(10)",
+                    BindType::Int8 => "NUMBER(19)",
+                    BindType::Float4 => "BINARY_FLOAT",
+                    BindType::Float8 => "BINARY_DOUBLE",
+                    BindType::Numeric => "NUMBER",
+                    BindType::Text | BindType::Uuid | BindType::Json | BindType::Jsonb => {
+                        "VARCHAR2(4000)"
+                    }
+                    BindType::Bytea => "RAW(2000)",
+                    BindType::Date => "DATE",
+                    BindType::Timestamp => "TIMESTAMP",
+                    BindType::Timestamptz => "TIMESTAMP WITH TIME ZONE",
+                    BindType::Time | BindType::Array(_) => return None,
+                };
+                Some(format!("CAST({placeholder} AS {target})"))
+            }
+            Dialect::ClickHouse => Some(placeholder.to_string()),
+        }
+    }
+
+    /// SQLite, SQL Server and Oracle lack `UNION DISTINCT` and
+    /// `INTERSECT ALL`. ClickHouse rejects a bare `UNION` by default and
+    /// reads a bare `EXCEPT` as `EXCEPT ALL`. Oracle spells `EXCEPT` as
+    /// `MINUS`. MySQL and ClickHouse count `DISTINCT a, b`, Postgres counts
+    /// `DISTINCT (a, b)`, and the others count a `SELECT DISTINCT` subquery.
+    /// Oracle takes no `AS` before a table alias. Oracle takes no leading `_`
+    /// in an unquoted identifier. SQL Server and Oracle page with
+    /// `FETCH NEXT`, the others with `LIMIT`. ClickHouse types the page size,
+    /// since it binds an untyped placeholder as a `String`. SQL Server and
+    /// Oracle have no row value comparisons. MySQL and ClickHouse take
+    /// backslash escapes in string literals.
+    fn capabilities(&self) -> Capabilities {
+        let standard = Capabilities::STANDARD;
+        match self {
+            Dialect::Postgres => Capabilities {
+                count_distinct: CountDistinct::Row,
+                paging: Paging::LimitOffset,
+                ..standard
+            },
+            Dialect::Mysql => Capabilities {
+                count_distinct: CountDistinct::List,
+                paging: Paging::LimitOffset,
+                backslash_escapes: true,
+                ..standard
+            },
+            Dialect::Sqlite => Capabilities {
+                distinct_set_ops: false,
+                all_set_ops: false,
+                paging: Paging::LimitOffset,
+                ..standard
+            },
+            Dialect::SqlServer => Capabilities {
+                distinct_set_ops: false,
+                all_set_ops: false,
+                row_values: false,
+                ..standard
+            },
+            Dialect::Oracle => Capabilities {
+                distinct_set_ops: false,
+                all_set_ops: false,
+                except_keyword: "MINUS",
+                derived_table_as: false,
+                leading_underscore: false,
+                row_values: false,
+                ..standard
+            },
+            Dialect::ClickHouse => Capabilities {
+                bare_set_ops: false,
+                count_distinct: CountDistinct::List,
+                paging: Paging::LimitOffset,
+                typed_paging: true,
+                backslash_escapes: true,
+                ..standard
+            },
+        }
+    }
+
+    /// `json_agg()` on Postgres, `json_group_array()` on SQLite,
+    /// `JSON_ARRAYAGG()` on MySQL and Oracle, and a `FOR JSON PATH` subquery
+    /// on SQL Server. Only Postgres and SQL Server turn whole rows into
+    /// objects; ClickHouse has no JSON aggregate.
+    fn json_array(&self, alias: &str, columns: &[String]) -> Option<(String, String)> {
+        let pairs = |separator: &str| {
+            columns
+                .iter()
+                .map(|column| format!("{}{separator}{column}", self.quote_literal(column)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if *self == Dialect::SqlServer {
+            let list = if columns.is_empty() {
+                "*".to_string()
+            } else {
+                columns.join(", ")
+            };
+            return Some((
+                format!("SELECT COALESCE((SELECT {list} FROM "),
+                " FOR JSON PATH, INCLUDE_NULL_VALUES), '[]')".to_string(),
+            ));
+        }
+        let aggregate = match self {
+            Dialect::Postgres if columns.is_empty() => {
+                Some(format!("COALESCE(json_agg(row_to_json({alias})), '[]')"))
+            }
+            Dialect::Postgres => Some(format!(
+                "COALESCE(json_agg(json_build_object({})), '[]')",
+                pairs(", ")
+            )),
+            _ if columns.is_empty() => None,
+            Dialect::Sqlite => Some(format!("json_group_array(json_object({}))", pairs(", "))),
+            Dialect::Mysql => Some(format!(
+                "COALESCE(JSON_ARRAYAGG(JSON_OBJECT({})), JSON_ARRAY())",
+                pairs(", ")
+            )),
+            Dialect::Oracle => Some(format!(
+                "COALESCE(JSON_ARRAYAGG(JSON_OBJECT({}) RETURNING CLOB), TO_CLOB('[]'))",
+                pairs(" VALUE ")
+            )),
+            Dialect::SqlServer | Dialect::ClickHouse => None,
+        }?;
+        Some((format!("SELECT {aggregate} FROM "), String::new()))
+    }
+
+    /// Oracle selects from `DUAL`.
+    fn select_row(&self, columns: &str) -> String {
+        match self {
+            Dialect::Oracle => format!("SELECT {columns} FROM DUAL"),
+            _ => format!("SELECT {columns}"),
+        }
+    }
+
+    /// Only Postgres binds arrays.
+    fn supports_array_binds(&self) -> bool {
+        matches!(self, Dialect::Postgres)
+    }
+}
+
+/// The ClickHouse type named in a placeholder for a binding declared `AS ty`.
+fn clickhouse_type(ty: &BindType) -> String {
+    match ty {
+        BindType::Bool => "Bool".to_string(),
+        BindType::Int2 => "Int16".to_string(),
+        BindType::Int4 => "Int32".to_string(),
+        BindType::Int8 => "Int64".to_string(),
+        BindType::Float4 => "Float32".to_string(),
+        BindType::Float8 => "Float64".to_string(),
+        BindType::Numeric => "Decimal(38, 10)".to_string(),
+        BindType::Text | BindType::Bytea | BindType::Time | BindType::Json | BindType::Jsonb => {
+            "String".to_string()
+        }
+        BindType::Date => "Date".to_string(),
+        BindType::Timestamp => "DateTime64(6)".to_string(),
+        BindType::Timestamptz => "DateTime64(6, 'UTC')".to_string(),
+        BindType::Uuid => "UUID".to_string(),
+        BindType::Array(inner) => format!("Array({})", clickhouse_type(inner)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::composer::Composer;
+    use crate::error::Error;
+    use crate::parser::parse_template;
+    use crate::types::TemplateSource;
+
+    /// Positional `?` placeholders, bracket quoting and no set operations
+    /// but `UNION ALL`.
+    struct Legacy;
+
+    impl SqlDialect for Legacy {
+        fn name(&self) -> &str {
+            "Legacy"
+        }
+
+        fn placeholder(&self, _index: usize) -> String {
+            "?".to_string()
+        }
+
+        fn supports_numbered_placeholders(&self) -> bool {
+            false
+        }
+
+        fn max_bind_params(&self) -> usize {
+            3
+        }
+
+        fn quote_ident(&self, name: &str) -> String {
+            format!("[{name}]")
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                distinct_set_ops: false,
+                bare_set_ops: false,
+                ..Capabilities::STANDARD
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_dialect_composes() {
+        let template = parse_template(
+            "SELECT * FROM t WHERE a IN (:bind(a)) AND b = :bind(b DEFAULT 'x') \
+             ORDER BY :ident(sort ONE OF name, year)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let composer = Composer::new(Legacy);
+        let idents = BTreeMap::from([("sort".to_string(), "year".to_string())]);
+
+        let values = crate::bind_values!("a" => [1, 2]);
+        let result = composer
+            .compose_with_idents(&template, &values, &idents)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM t WHERE a IN (?, ?) AND b = 'x' ORDER BY [year]"
+        );
+
+        let values = crate::bind_values!("a" => [1, 2, 3, 4]);
+        let err = composer
+            .compose_with_idents(&template, &values, &idents)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "4 bind parameters exceed the Legacy limit of 3; \
+             use Composer::compose_chunked() to split the values"
+        );
+
+        let template = parse_template(
+            "SELECT :bind(n AS int8)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let err = composer.compose(&template).unwrap_err();
+        assert!(matches!(
+            err.root(),
+            Error::UnsupportedBindType { dialect, .. } if dialect == "Legacy"
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_union() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.sqlc"), "SELECT 1").unwrap();
+        let mut composer = Composer::new(Legacy);
+        composer.add_search_path(dir.path().to_path_buf());
+
+        let template = parse_template(
+            ":union(a.sqlc, a.sqlc)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let err = composer.compose(&template).unwrap_err();
+        assert_eq!(
+            err.root().to_string(),
+            ":union() needs UNION, which Legacy does not support"
+        );
+
+        let template = parse_template(
+            ":union(ALL a.sqlc, a.sqlc)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let result = composer.compose(&template).unwrap();
+        assert_eq!(result.sql, "SELECT 1\nUNION ALL\nSELECT 1");
+    }
+
+    #[test]
+    fn test_capabilities() {
+        assert!(Dialect::Postgres.capabilities().distinct_set_ops);
+        assert!(!Dialect::Sqlite.capabilities().distinct_set_ops);
+        assert!(!Dialect::ClickHouse.capabilities().bare_set_ops);
+        assert_eq!(Dialect::Oracle.capabilities().except_keyword, "MINUS");
+        assert_eq!(
+            Dialect::Postgres.capabilities().count_distinct,
+            CountDistinct::Row
+        );
+        assert_eq!(
+            Legacy.capabilities().count_distinct,
+            CountDistinct::Subquery
+        );
+        assert_eq!(Dialect::Sqlite.capabilities().paging, Paging::LimitOffset);
+        assert_eq!(Legacy.capabilities().paging, Paging::OffsetFetch);
+        assert!(Dialect::ClickHouse.capabilities().typed_paging);
+        assert!(!Dialect::Postgres.capabilities().typed_paging);
+        assert!(!Dialect::Oracle.capabilities().row_values);
+        assert!(Dialect::Mysql.capabilities().backslash_escapes);
+        assert!(!Dialect::Postgres.capabilities().backslash_escapes);
+    }
+
+    #[test]
+    fn test_numbered_dialects_dedup() {
+        let template = parse_template(
+            "SELECT * FROM t WHERE a = :bind(a) AND b IN (:bind(b)) OR c = :bind(a)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let values = crate::bind_values!("a" => [1], "b" => [2, 3]);
+        for (dialect, sql) in [
+            (
+                Dialect::SqlServer,
+                "SELECT * FROM t WHERE a = @p1 AND b IN (@p2, @p3) OR c = @p1",
+            ),
+            (
+                Dialect::ClickHouse,
+                "SELECT * FROM t WHERE a = {a:String} AND b IN ({b:String}, {b_2:String}) \
+                 OR c = {a:String}",
+            ),
+        ] {
+            let result = Composer::new(dialect)
+                .compose_with_values(&template, &values)
+                .unwrap();
+            assert_eq!(result.sql, sql, "{dialect:?}");
+            assert_eq!(result.bind_params, vec!["a", "b", "b"], "{dialect:?}");
+        }
+
+        // Oracle numbers every occurrence, so parameters bind by position.
+        let result = Composer::new(Dialect::Oracle)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT * FROM t WHERE a = :1 AND b IN (:2, :3) OR c = :4"
+        );
+        assert_eq!(result.bind_params, vec!["a", "b", "b", "a"]);
+    }
+
+    #[test]
+    fn test_typed_placeholders() {
+        let template = parse_template(
+            "SELECT :bind(id AS int8), :bind(at AS timestamptz), :bind(tags AS text[])",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let result = Composer::new(Dialect::ClickHouse)
+            .compose(&template)
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT {id:Int64}, {at:DateTime64(6, 'UTC')}, {tags:Array(String)}"
+        );
+
+        let template = parse_template(
+            "SELECT :bind(id AS int8), :bind(flag AS bool)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let result = Composer::new(Dialect::SqlServer)
+            .compose(&template)
+            .unwrap();
+        assert_eq!(result.sql, "SELECT CAST(@p2 AS BIGINT), CAST(@p1 AS BIT)");
+        let result = Composer::new(Dialect::Oracle).compose(&template).unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT CAST(:1 AS NUMBER(19)), CAST(:2 AS NUMBER(1))"
+        );
+    }
+
+    #[test]
+    fn test_builtin() {
+        let dialect: &dyn SqlDialect = &Dialect::Sqlite;
+        assert_eq!(dialect.builtin(), Some(Dialect::Sqlite));
+        let dialect: &dyn SqlDialect = &Legacy;
+        assert_eq!(dialect.builtin(), None);
+    }
+}
@@ -5,10 +5,11 @@
 //! This module contains no database dependencies — only the interface.
 
 use std::collections::BTreeMap;
+use std::fmt;
 
-use crate::composer::{ComposedSql, Composer};
+use crate::composer::{ComposedChunk, ComposedSql, Composer};
 use crate::error::{Error, Result};
-use crate::types::Template;
+use crate::types::{BindType, Template};
 
 /// Trait for synchronous database drivers that can compose and prepare SQL.
 ///
@@ -72,35 +73,265 @@ pub trait ComposerConnectionAsync {
     > + Send;
 }
 
-/// Given a [`ComposedSql`] with ordered bind param names and a map of named values,
-/// produce the ordered value vector matching placeholder order.
+/// Given a [`ComposedSql`] and a map of named values, produce the ordered
+/// value vector matching placeholder order.
 ///
-/// For single-value bindings, each name maps to one value.
-/// For multi-value bindings (e.g. IN clauses), the composed SQL already has
-/// the correct number of placeholders per binding name, so we flatten all
-/// values for each occurrence.
+/// Each binding in [`ComposedSql::params`] takes one value per placeholder
+/// index, in order, from the front of its list: one value for a
+/// single-value binding, and one per placeholder for a multi-value binding
+/// expanded by [`Composer::compose_with_values()`]. Fails with
+/// [`Error::MissingBinding`] if a binding has fewer values than placeholders
+/// ([`Error::DefaultNotInlined`] if it declares a `DEFAULT`, which only
+/// [`Composer::compose_with_values()`] applies), and with
+/// [`Error::ArrayBindingUnsupported`] for an `AS ARRAY` binding; see
+/// [`resolve_values_with()`].
 pub fn resolve_values<V>(
     composed: &ComposedSql,
     values: &mut BTreeMap<String, Vec<V>>,
 ) -> Result<Vec<V>> {
-    let mut result = Vec::with_capacity(composed.bind_params.len());
+    resolve_values_with(composed, values, |name, _| {
+        Err(Error::ArrayBindingUnsupported {
+            name: name.to_string(),
+        })
+    })
+}
+
+/// Like [`resolve_values()`], binding all the values of an `AS ARRAY`
+/// binding as the single value `bundle` makes of them.
+///
+/// Drivers for dialects that [bind arrays](crate::SqlDialect::supports_array_binds)
+/// pass a `bundle` that wraps the values in their array type.
+pub fn resolve_values_with<V>(
+    composed: &ComposedSql,
+    values: &mut BTreeMap<String, Vec<V>>,
+    mut bundle: impl FnMut(&str, Vec<V>) -> Result<V>,
+) -> Result<Vec<V>> {
+    let mut slots: Vec<Option<V>> = std::iter::repeat_with(|| None)
+        .take(composed.bind_params.len())
+        .collect();
+
+    for (name, param) in &composed.params {
+        if param.indices.is_empty() {
+            continue;
+        }
+
+        let missing = || match &param.default {
+            Some(default) => Error::DefaultNotInlined {
+                name: name.clone(),
+                default: default.clone(),
+            },
+            None => Error::MissingBinding { name: name.clone() },
+        };
+        let vs = values.get_mut(name).ok_or_else(missing)?;
+        if param.array {
+            let slot = param.indices[0]
+                .checked_sub(1)
+                .and_then(|i| slots.get_mut(i))
+                .ok_or_else(missing)?;
+            *slot = Some(bundle(name, std::mem::take(vs))?);
+            continue;
+        }
+        if vs.len() < param.indices.len() {
+            return Err(missing());
+        }
+
+        for (&index, value) in param.indices.iter().zip(vs.drain(..param.indices.len())) {
+            let slot = index
+                .checked_sub(1)
+                .and_then(|i| slots.get_mut(i))
+                .ok_or_else(missing)?;
+            *slot = Some(value);
+        }
+    }
+
+    slots
+        .into_iter()
+        .zip(&composed.bind_params)
+        .map(|(value, name)| value.ok_or_else(|| Error::MissingBinding { name: name.clone() }))
+        .collect()
+}
+
+/// Order the values one [`ComposedChunk`] binds to match its placeholders,
+/// borrowing them from the full `values` map.
+///
+/// Each binding takes its values from the chunk's range of its list, as
+/// [`resolve_values()`] would from a map holding only those. Fails with
+/// [`Error::MissingBinding`] if a list is shorter than the chunk expects,
+/// and with [`Error::ArrayBindingUnsupported`] for an `AS ARRAY` binding;
+/// see [`resolve_chunk_values_with()`].
+pub fn resolve_chunk_values<'v, V>(
+    chunk: &ComposedChunk,
+    values: &'v BTreeMap<String, Vec<V>>,
+) -> Result<Vec<&'v V>> {
+    resolve_chunk_values_with(chunk, values, |name, _| {
+        Err(Error::ArrayBindingUnsupported {
+            name: name.to_string(),
+        })
+    })
+}
 
-    for name in &composed.bind_params {
-        let vs = values
-            .get_mut(name)
+/// Like [`resolve_chunk_values()`], binding the chunk's values of an
+/// `AS ARRAY` binding as the single value `bundle` makes of them.
+///
+/// The other values are borrowed and converted with `W::from`, so that a
+/// driver can hold borrowed values and the bundles it makes in one type.
+pub fn resolve_chunk_values_with<'v, V, W>(
+    chunk: &ComposedChunk,
+    values: &'v BTreeMap<String, Vec<V>>,
+    mut bundle: impl FnMut(&str, Vec<&'v V>) -> Result<W>,
+) -> Result<Vec<W>>
+where
+    W: From<&'v V>,
+{
+    let mut slices: BTreeMap<String, Vec<W>> = BTreeMap::new();
+    for (name, range) in &chunk.ranges {
+        let slice = values
+            .get(name)
+            .and_then(|vs| vs.get(range.clone()))
             .ok_or_else(|| Error::MissingBinding { name: name.clone() })?;
+        let array = chunk.composed.params.get(name).is_some_and(|p| p.array);
+        let slice = if array {
+            vec![bundle(name, slice.iter().collect())?]
+        } else {
+            slice.iter().map(W::from).collect()
+        };
+        slices.insert(name.clone(), slice);
+    }
+    // Array bindings were bundled above, into their one value.
+    resolve_values_with(&chunk.composed, &mut slices, |name, bundled| {
+        bundled
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::MissingBinding {
+                name: name.to_string(),
+            })
+    })
+}
 
-        if vs.is_empty() {
-            return Err(Error::MissingBinding { name: name.clone() });
+/// Flatten rows of values into the row-after-row list a
+/// `:bind_rows(name (col, ...))` binding takes.
+///
+/// ```
+/// use sql_composer::driver::flatten_rows;
+///
+/// let items = flatten_rows([["3001", "red"], ["3002", "blue"]]);
+/// assert_eq!(items, vec!["3001", "red", "3002", "blue"]);
+/// ```
+pub fn flatten_rows<V, R>(rows: impl IntoIterator<Item = R>) -> Vec<V>
+where
+    R: IntoIterator<Item = V>,
+{
+    rows.into_iter().flatten().collect()
+}
+
+/// The kind of a bind value as seen by a driver, used to check values against
+/// a binding's declared [`BindType`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// SQL NULL; accepted for any type.
+    Null,
+    /// A boolean.
+    Bool,
+    /// An integer.
+    Integer,
+    /// A floating-point number.
+    Float,
+    /// An exact decimal number.
+    Decimal,
+    /// A string.
+    Text,
+    /// Raw bytes.
+    Bytes,
+    /// A calendar date.
+    Date,
+    /// A time of day.
+    Time,
+    /// A date and time.
+    Timestamp,
+    /// A UUID.
+    Uuid,
+    /// A JSON document.
+    Json,
+    /// A value the driver cannot classify; accepted for any type.
+    Unknown,
+}
+
+impl ValueKind {
+    /// Whether a value of this kind can be bound to a parameter declared as `ty`.
+    ///
+    /// Strings are accepted wherever databases parse them from text (dates,
+    /// UUIDs, JSON, decimals).
+    pub fn fits(self, ty: &BindType) -> bool {
+        use ValueKind as K;
+        match self {
+            K::Null | K::Unknown => return true,
+            _ => {}
         }
+        match ty {
+            BindType::Bool => matches!(self, K::Bool | K::Integer),
+            BindType::Int2 | BindType::Int4 | BindType::Int8 => self == K::Integer,
+            BindType::Float4 | BindType::Float8 => matches!(self, K::Float | K::Integer),
+            BindType::Numeric => matches!(self, K::Decimal | K::Integer | K::Float | K::Text),
+            BindType::Text => self == K::Text,
+            BindType::Bytea => self == K::Bytes,
+            BindType::Date => matches!(self, K::Date | K::Text),
+            BindType::Time => matches!(self, K::Time | K::Text),
+            BindType::Timestamp | BindType::Timestamptz => {
+                matches!(self, K::Timestamp | K::Date | K::Text)
+            }
+            BindType::Uuid => matches!(self, K::Uuid | K::Text | K::Bytes),
+            BindType::Json | BindType::Jsonb => matches!(self, K::Json | K::Text),
+            BindType::Array(_) => false,
+        }
+    }
+}
 
-        // Take the first value — each placeholder in bind_params corresponds
-        // to exactly one value. Multi-value bindings have been expanded by
-        // compose_with_values() so each placeholder gets its own entry.
-        result.push(vs.remove(0));
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ValueKind::Null => "NULL",
+            ValueKind::Bool => "a boolean",
+            ValueKind::Integer => "an integer",
+            ValueKind::Float => "a float",
+            ValueKind::Decimal => "a decimal",
+            ValueKind::Text => "text",
+            ValueKind::Bytes => "bytes",
+            ValueKind::Date => "a date",
+            ValueKind::Time => "a time",
+            ValueKind::Timestamp => "a timestamp",
+            ValueKind::Uuid => "a UUID",
+            ValueKind::Json => "JSON",
+            ValueKind::Unknown => "a value of unknown type",
+        };
+        f.write_str(name)
     }
+}
 
-    Ok(result)
+/// Check resolved values against the declared types in `composed`.
+///
+/// `values` must be in placeholder order, as returned by [`resolve_values()`].
+/// `kind` classifies a driver value; values of typed bindings whose kind does
+/// not [fit](ValueKind::fits) the declared type are rejected with
+/// [`Error::BindTypeMismatch`].
+pub fn check_value_types<V>(
+    composed: &ComposedSql,
+    values: &[V],
+    kind: impl Fn(&V) -> ValueKind,
+) -> Result<()> {
+    for (name, value) in composed.bind_params.iter().zip(values) {
+        let Some(expected) = composed.bind_type(name) else {
+            continue;
+        };
+        let found = kind(value);
+        if !found.fits(expected) {
+            return Err(Error::BindTypeMismatch {
+                name: name.clone(),
+                expected: expected.clone(),
+                found: found.to_string(),
+            });
+        }
+    }
+    Ok(())
 }
 
 /// Build a `BTreeMap<String, Vec<V>>` of named bind values.
@@ -131,13 +362,17 @@ macro_rules! bind_values {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::parse_template;
+    use crate::types::{Dialect, TemplateSource};
+
+    fn composed(sql: &str, bind_params: &[&str]) -> ComposedSql {
+        let bind_params = bind_params.iter().map(ToString::to_string).collect();
+        ComposedSql::from_bind_params(sql, bind_params, TemplateSource::Literal("test".into()))
+    }
 
     #[test]
     fn test_resolve_values_basic() {
-        let composed = ComposedSql {
-            sql: "SELECT * FROM t WHERE a = $1 AND b = $2".into(),
-            bind_params: vec!["a".into(), "b".into()],
-        };
+        let composed = composed("SELECT * FROM t WHERE a = $1 AND b = $2", &["a", "b"]);
         let mut values: BTreeMap<String, Vec<&str>> = BTreeMap::new();
         values.insert("a".into(), vec!["hello"]);
         values.insert("b".into(), vec!["world"]);
@@ -148,24 +383,40 @@ mod tests {
 
     #[test]
     fn test_resolve_values_missing_binding() {
-        let composed = ComposedSql {
-            sql: "SELECT * FROM t WHERE a = $1".into(),
-            bind_params: vec!["missing".into()],
-        };
+        let composed = composed("SELECT * FROM t WHERE a = $1", &["missing"]);
         let mut values: BTreeMap<String, Vec<&str>> = BTreeMap::new();
 
         let err = resolve_values(&composed, &mut values).unwrap_err();
         assert!(matches!(err, Error::MissingBinding { ref name } if name == "missing"));
     }
 
+    #[test]
+    fn test_resolve_values_default_not_inlined() {
+        let template = parse_template(
+            "SELECT * FROM t LIMIT :bind(limit DEFAULT 100)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let composer = Composer::new(Dialect::Postgres);
+        let mut values: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+
+        let composed = composer.compose(&template).unwrap();
+        let err = resolve_values(&composed, &mut values).unwrap_err();
+        assert!(matches!(err, Error::DefaultNotInlined { ref name, .. } if name == "limit"));
+
+        let composed = composer.compose_with_values(&template, &values).unwrap();
+        assert_eq!(composed.sql, "SELECT * FROM t LIMIT 100");
+        assert!(resolve_values(&composed, &mut values).unwrap().is_empty());
+    }
+
     #[test]
     fn test_resolve_values_multi_value_expanded() {
         // After compose_with_values, a multi-value binding like ids=[1,2,3]
         // produces bind_params = ["ids", "ids", "ids"] with placeholders $1, $2, $3.
-        let composed = ComposedSql {
-            sql: "SELECT * FROM t WHERE id IN ($1, $2, $3)".into(),
-            bind_params: vec!["ids".into(), "ids".into(), "ids".into()],
-        };
+        let composed = composed(
+            "SELECT * FROM t WHERE id IN ($1, $2, $3)",
+            &["ids", "ids", "ids"],
+        );
         let mut values: BTreeMap<String, Vec<i32>> = BTreeMap::new();
         values.insert("ids".into(), vec![10, 20, 30]);
 
@@ -173,6 +424,174 @@ mod tests {
         assert_eq!(result, vec![10, 20, 30]);
     }
 
+    #[test]
+    fn test_resolve_values_follows_placeholder_indices() {
+        // MySQL gives each occurrence its own placeholder, in document order.
+        let template = parse_template(
+            "SELECT :bind(b), :bind(a), :bind(b)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let mut values = bind_values!("a" => [1], "b" => [2, 3]);
+        let composed = Composer::new(Dialect::Mysql).compose(&template).unwrap();
+        assert_eq!(composed.params["b"].indices, vec![1, 3]);
+
+        let result = resolve_values(&composed, &mut values).unwrap();
+        assert_eq!(result, vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn test_resolve_values_skips_null_binding() {
+        let template = parse_template(
+            "SELECT :bind(a NULL), :bind(b)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let mut values = bind_values!("b" => [7]);
+        let composed = Composer::new(Dialect::Postgres)
+            .compose_with_values(&template, &values)
+            .unwrap();
+        assert_eq!(composed.sql, "SELECT NULL, $1");
+        assert!(composed.params["a"].indices.is_empty());
+
+        let result = resolve_values(&composed, &mut values).unwrap();
+        assert_eq!(result, vec![7]);
+    }
+
+    #[test]
+    fn test_check_value_types() {
+        let mut composed = composed(
+            "SELECT * FROM t WHERE id = $1::int8 AND name = $2",
+            &["id", "name"],
+        );
+        composed.params.get_mut("id").unwrap().sql_type = Some(BindType::Int8);
+        let kind = |v: &&str| match v.parse::<i64>() {
+            Ok(_) => ValueKind::Integer,
+            Err(_) => ValueKind::Text,
+        };
+
+        check_value_types(&composed, &["7", "x"], kind).unwrap();
+
+        let err = check_value_types(&composed, &["seven", "x"], kind).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::BindTypeMismatch { ref name, expected: BindType::Int8, ref found }
+                if name == "id" && found == "text"
+        ));
+    }
+
+    #[test]
+    fn test_resolve_values_bind_rows() {
+        let tpl = parse_template(
+            "INSERT INTO t (a, b) VALUES :bind_rows(items (a, b)) RETURNING :bind(tag)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let mut values: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+        values.insert("items".into(), flatten_rows([[1, 2], [3, 4]]));
+        values.insert("tag".into(), vec![9]);
+
+        for dialect in [Dialect::Postgres, Dialect::Mysql] {
+            let composed = Composer::new(dialect)
+                .compose_with_values(&tpl, &values)
+                .unwrap();
+            let result = resolve_values(&composed, &mut values.clone()).unwrap();
+            assert_eq!(result, vec![1, 2, 3, 4, 9]);
+        }
+    }
+
+    #[test]
+    fn test_resolve_values_with_bundles_arrays() {
+        let tpl = parse_template(
+            "SELECT * FROM t WHERE org = :bind(org) AND id IN (:bind(ids AS ARRAY))",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let mut values: BTreeMap<String, Vec<Vec<i32>>> = BTreeMap::new();
+        values.insert("ids".into(), vec![vec![1], vec![2], vec![3]]);
+        values.insert("org".into(), vec![vec![9]]);
+
+        let composed = Composer::new(Dialect::Postgres)
+            .compose_with_values(&tpl, &values)
+            .unwrap();
+        let bundle = |_: &str, vs: Vec<Vec<i32>>| Ok(vs.concat());
+        let result = resolve_values_with(&composed, &mut values.clone(), bundle).unwrap();
+        assert_eq!(result, vec![vec![1, 2, 3], vec![9]]);
+
+        let err = resolve_values(&composed, &mut values).unwrap_err();
+        assert!(matches!(err, Error::ArrayBindingUnsupported { ref name } if name == "ids"));
+    }
+
+    #[test]
+    fn test_resolve_chunk_values() {
+        let tpl = parse_template(
+            "SELECT * FROM t WHERE org = :bind(org) AND id IN (:bind(ids))",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.max_bind_params = 3;
+        let values: BTreeMap<String, Vec<i32>> = bind_values!(
+            "ids" => [1, 2, 3, 4, 5],
+            "org" => [9],
+        );
+
+        let chunks = composer.compose_chunked(&tpl, &values).unwrap();
+        let resolved: Vec<Vec<&i32>> = chunks
+            .iter()
+            .map(|chunk| resolve_chunk_values(chunk, &values).unwrap())
+            .collect();
+        assert_eq!(
+            resolved,
+            vec![vec![&1, &2, &9], vec![&3, &4, &9], vec![&5, &9]]
+        );
+    }
+
+    #[test]
+    fn test_resolve_chunk_values_with_bundles_arrays() {
+        #[derive(Debug, PartialEq)]
+        enum Param<'v> {
+            One(&'v i32),
+            Sum(i32),
+        }
+        impl<'v> From<&'v i32> for Param<'v> {
+            fn from(v: &'v i32) -> Self {
+                Param::One(v)
+            }
+        }
+
+        let tpl = parse_template(
+            "SELECT * FROM t WHERE org IN (:bind(orgs)) AND id = ANY(:bind(ids AS ARRAY))",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap();
+        let mut composer = Composer::new(Dialect::Postgres);
+        composer.max_bind_params = 3;
+        let values: BTreeMap<String, Vec<i32>> = bind_values!(
+            "ids" => [7, 8],
+            "orgs" => [1, 2, 3, 4, 5],
+        );
+
+        let chunks = composer.compose_chunked(&tpl, &values).unwrap();
+        let bundle = |_: &str, vs: Vec<&i32>| Ok(Param::Sum(vs.into_iter().sum()));
+        let resolved: Vec<Vec<Param>> = chunks
+            .iter()
+            .map(|chunk| resolve_chunk_values_with(chunk, &values, bundle).unwrap())
+            .collect();
+        use Param::{One, Sum};
+        assert_eq!(
+            resolved,
+            vec![
+                vec![Sum(15), One(&1), One(&2)],
+                vec![Sum(15), One(&3), One(&4)],
+                vec![Sum(15), One(&5)],
+            ]
+        );
+
+        let err = resolve_chunk_values(&chunks[0], &values).unwrap_err();
+        assert!(matches!(err, Error::ArrayBindingUnsupported { ref name } if name == "ids"));
+    }
+
     #[test]
     fn test_bind_values_macro() {
         let values: BTreeMap<String, Vec<i32>> = bind_values!(
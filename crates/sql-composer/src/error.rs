@@ -1,7 +1,66 @@
 //! Error types for sql-composer.
 
+use std::fmt;
 use std::path::PathBuf;
 
+use crate::types::{BindDefault, BindType, Span, TemplateSource};
+
+/// A position within a template, with an optional rendered excerpt of the
+/// offending source line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    /// The template the position refers to.
+    pub source: TemplateSource,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column, in characters.
+    pub column: usize,
+    /// The source line with a caret under `column`, when the text is available.
+    pub snippet: Option<String>,
+}
+
+impl Location {
+    /// Build a location for `span`, rendering a snippet from `text` if given.
+    pub fn new(source: TemplateSource, span: Span, text: Option<&str>) -> Self {
+        let snippet = text.and_then(|text| render_snippet(text, span));
+        Self {
+            source,
+            line: span.line,
+            column: span.column,
+            snippet,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.source, self.line, self.column)
+    }
+}
+
+/// Render the line containing `span` with a caret marker underneath, e.g.
+///
+/// ```text
+///   |
+/// 3 | WHERE id = :bind(id EXPECTING)
+///   |            ^
+/// ```
+fn render_snippet(text: &str, span: Span) -> Option<String> {
+    let line = text.lines().nth(span.line - 1)?;
+    let number = span.line.to_string();
+    let gutter = " ".repeat(number.len());
+    let pad = " ".repeat(span.column - 1);
+    Some(format!("{gutter} |\n{number} | {line}\n{gutter} | {pad}^"))
+}
+
+/// Format an optional snippet as trailing lines of an error message.
+fn snippet_suffix(location: &Location) -> String {
+    match &location.snippet {
+        Some(snippet) => format!("\n{snippet}"),
+        None => String::new(),
+    }
+}
+
 /// The error type for sql-composer operations.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -13,10 +72,10 @@ pub enum Error {
     },
 
     /// A parse error occurred while processing a template.
-    #[error("parse error at {location}: {message}")]
+    #[error("parse error at {location}: {message}{}", snippet_suffix(location))]
     Parse {
-        /// Human-readable location description (e.g. line:col or offset).
-        location: String,
+        /// Where parsing failed, with a snippet of the offending line.
+        location: Location,
         /// Description of the parse failure.
         message: String,
     },
@@ -28,6 +87,182 @@ pub enum Error {
         name: String,
     },
 
+    /// A binding received a number of values outside its `EXPECTING` range.
+    #[error(
+        "binding '{name}' in {template} expects {} value(s), got {count}",
+        expected_range(*min, *max)
+    )]
+    ValueCountOutOfRange {
+        /// The name of the binding.
+        name: String,
+        /// The template containing the `:bind()`.
+        template: TemplateSource,
+        /// Minimum number of values declared by `EXPECTING`.
+        min: Option<u32>,
+        /// Maximum number of values declared by `EXPECTING`.
+        max: Option<u32>,
+        /// Number of values actually supplied.
+        count: usize,
+    },
+
+    /// A binding with a `DEFAULT` was given no values after composing with a
+    /// placeholder for it, so the default was never inlined.
+    #[error(
+        "binding '{name}' has no values; its DEFAULT {default} is only inlined \
+         by Composer::compose_with_values()"
+    )]
+    DefaultNotInlined {
+        /// The name of the binding.
+        name: String,
+        /// The binding's declared default.
+        default: BindDefault,
+    },
+
+    /// A binding not declared `NULL` was given no values.
+    #[error("binding '{name}' in {template} is not nullable but has no values")]
+    NullBinding {
+        /// The name of the binding.
+        name: String,
+        /// The template containing the `:bind()`.
+        template: TemplateSource,
+    },
+
+    /// A `:bind_rows()` was given a number of values that doesn't fill
+    /// whole rows.
+    #[error("binding '{name}' in {template} takes rows of {columns} value(s), got {count}")]
+    IncompleteRow {
+        /// The name of the binding.
+        name: String,
+        /// The template containing the `:bind_rows()`.
+        template: TemplateSource,
+        /// Number of values per row.
+        columns: usize,
+        /// Number of values actually supplied.
+        count: usize,
+    },
+
+    /// An `:ident()` was given a value outside its `ONE OF` list.
+    #[error(
+        "identifier '{name}' in {template} must be one of {}, got '{value}'",
+        allowed.join(", ")
+    )]
+    IdentNotAllowed {
+        /// The name of the identifier.
+        name: String,
+        /// The template containing the `:ident()`.
+        template: TemplateSource,
+        /// The value supplied.
+        value: String,
+        /// The identifiers the template allows.
+        allowed: Vec<String>,
+    },
+
+    /// No identifier was supplied for an `:ident()`.
+    #[error("identifier '{name}' in {template} was not supplied")]
+    MissingIdent {
+        /// The name of the identifier.
+        name: String,
+        /// The template containing the `:ident()`.
+        template: TemplateSource,
+    },
+
+    /// A composed statement needs more bind parameters than its dialect
+    /// allows.
+    #[error(
+        "{count} bind parameters exceed the {dialect} limit of {limit}; \
+         use Composer::compose_chunked() to split the values"
+    )]
+    TooManyBindParams {
+        /// The target dialect's [name](crate::SqlDialect::name).
+        dialect: String,
+        /// Number of bind parameters the statement needs.
+        count: usize,
+        /// The most the composer allows per statement.
+        limit: usize,
+    },
+
+    /// A typed binding declares a type the dialect cannot cast to.
+    #[error("binding '{name}' is declared AS {sql_type}, which {dialect} cannot cast to")]
+    UnsupportedBindType {
+        /// The name of the binding.
+        name: String,
+        /// The declared type.
+        sql_type: BindType,
+        /// The target dialect's [name](crate::SqlDialect::name).
+        dialect: String,
+    },
+
+    /// A command needs SQL that the dialect's
+    /// [capabilities](crate::SqlDialect::capabilities) can't express.
+    #[error("{command} needs {construct}, which {dialect} does not support")]
+    UnsupportedConstruct {
+        /// The command, e.g. `:union()`.
+        command: String,
+        /// The SQL it needs, e.g. `UNION DISTINCT`.
+        construct: String,
+        /// The target dialect's [name](crate::SqlDialect::name).
+        dialect: String,
+    },
+
+    /// Two occurrences of the same binding declare different types.
+    #[error("binding '{name}' is declared both AS {first} and AS {second}")]
+    ConflictingBindTypes {
+        /// The name of the binding.
+        name: String,
+        /// The type declared first.
+        first: BindType,
+        /// The conflicting type.
+        second: BindType,
+    },
+
+    /// A value supplied to a driver does not match its binding's declared type.
+    #[error("binding '{name}' is declared AS {expected} but was given {found}")]
+    BindTypeMismatch {
+        /// The name of the binding.
+        name: String,
+        /// The declared type.
+        expected: BindType,
+        /// Description of the supplied value.
+        found: String,
+    },
+
+    /// Some occurrences of a binding are declared `AS ARRAY` and others
+    /// are not.
+    #[error("binding '{name}' is declared AS ARRAY in one place but not another")]
+    ConflictingArrayBinding {
+        /// The name of the binding.
+        name: String,
+    },
+
+    /// A binding declared with an array type (`AS int8[]`) has several
+    /// values, each of which would be cast to the array type.
+    #[error(
+        "binding '{name}' is declared AS {sql_type} but expands to several placeholders; \
+         use AS ARRAY OF to bind its values as one array"
+    )]
+    ExpandedArrayType {
+        /// The name of the binding.
+        name: String,
+        /// The declared array type.
+        sql_type: BindType,
+    },
+
+    /// An `AS ARRAY` binding was resolved without a way to bundle its values
+    /// into one array value.
+    #[error("binding '{name}' is declared AS ARRAY, which needs driver::resolve_values_with()")]
+    ArrayBindingUnsupported {
+        /// The name of the binding.
+        name: String,
+    },
+
+    /// Code generation was given a composer whose dialect isn't a built-in
+    /// [`Dialect`](crate::Dialect), which generated code can't name.
+    #[error("code generation needs a built-in Dialect, got {dialect}")]
+    CustomDialect {
+        /// The dialect's [name](crate::SqlDialect::name).
+        dialect: String,
+    },
+
     /// A compose reference could not be resolved.
     #[error("compose reference not found: {path}")]
     ComposeNotFound {
@@ -66,6 +301,44 @@ pub enum Error {
         /// The name of the missing slot.
         name: String,
     },
+
+    /// An error raised while composing a macro, with the macro's location and
+    /// the chain of includes that led to it.
+    #[error(
+        "{error}\n  --> {location}{}\n  = included via {}",
+        snippet_suffix(location),
+        chain.join(" -> ")
+    )]
+    Located {
+        /// The underlying error.
+        #[source]
+        error: Box<Error>,
+        /// The macro that raised the error.
+        location: Location,
+        /// The root template followed by each `:compose()` target down to the
+        /// failing macro, e.g. `["a.sqlc", "shared/b.sqlc", "@filter"]`.
+        chain: Vec<String>,
+    },
+}
+
+impl Error {
+    /// The underlying error, with any [`Error::Located`] context removed.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Located { error, .. } => error.root(),
+            other => other,
+        }
+    }
+}
+
+/// Describe an `EXPECTING` range for error messages.
+fn expected_range(min: Option<u32>, max: Option<u32>) -> String {
+    match (min, max) {
+        (Some(min), Some(max)) => format!("{min}..{max}"),
+        (Some(min), None) => format!("at least {min}"),
+        (None, Some(max)) => format!("at most {max}"),
+        (None, None) => "any number of".to_string(),
+    }
 }
 
 /// A specialized `Result` type for sql-composer operations.
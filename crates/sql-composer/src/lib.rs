@@ -2,10 +2,19 @@
 //! with parameterized bindings.
 //!
 //! Templates use a simple macro syntax embedded in SQL:
-//! - `:bind(name)` — parameter placeholder
+//! - `:bind(name)` — parameter placeholder (`:bind(name AS int8)` adds a cast,
+//!   `:bind(name DEFAULT 100)` a fallback value)
+//! - `:bind_rows(name (a, b))` — one `(?, ?)` group per row of values
 //! - `:compose(path)` — include another template
 //! - `:count(sources...)` — count aggregate
 //! - `:union(sources...)` — union combinator
+//! - `:intersect(sources...)`, `:except(sources...)` — intersect and except
+//!   combinators
+//! - `:exists(sources...)`, `:not_exists(sources...)` — existence tests
+//! - `:json(columns OF sources...)` — JSON array aggregate
+//! - `:paginate(columns OF sources...)` — one page of rows, by offset or
+//!   `KEYSET`
+//! - `:ident(name ONE OF a, b)` — allowlisted identifier, quoted per dialect
 //!
 //! SQL text is treated as opaque literals and passed through unchanged.
 //! Only the macro syntax is parsed.
@@ -29,17 +38,23 @@
 //! ```
 
 mod clippy;
+pub mod codegen;
 pub mod composer;
+pub mod dialect;
 pub mod driver;
 pub mod error;
 pub mod mock;
 pub mod parser;
+pub mod store;
 pub mod types;
 
-pub use composer::{ComposedSql, Composer};
-pub use error::Error;
+pub use composer::{BindParam, ComposedChunk, ComposedSql, Composer};
+pub use dialect::{Capabilities, SqlDialect};
+pub use error::{Error, Location};
 pub use mock::MockTable;
+pub use store::{DependencyGraph, TemplateStore};
 pub use types::{
-    Binding, Command, CommandKind, ComposeRef, ComposeTarget, Dialect, Element, SlotAssignment,
-    Template, TemplateSource,
+    BindDefault, BindType, Binding, Command, CommandKind, CommentStyle, ComposeRef, ComposeTarget,
+    Dialect, Element, Identifier, PlaceholderOrdering, RowBinding, SlotAssignment, Span, Template,
+    TemplateSource,
 };
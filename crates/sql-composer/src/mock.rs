@@ -1,9 +1,13 @@
 //! Mock table system for generating test data as `SELECT ... UNION ALL SELECT ...`.
 //!
 //! When the composer encounters a table name that has a mock registered,
-//! it can substitute a generated SELECT statement that produces the mock data.
+//! it substitutes a generated SELECT statement that produces the mock data.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::dialect::SqlDialect;
+use crate::parser::template::quoted_len;
+use crate::types::Dialect;
 
 /// A mock table definition with column data for test substitution.
 ///
@@ -38,10 +42,21 @@ impl MockTable {
     /// Generate the mock SQL for this table.
     ///
     /// The first row includes `AS column_name` aliases, subsequent rows omit them.
-    /// Values are quoted as SQL string literals. Use `NULL` (without quotes) for null.
-    pub fn to_sql(&self) -> String {
+    /// Values are quoted with [`SqlDialect::quote_literal()`]. Column names
+    /// are written bare, so they fold like the unquoted names queries select,
+    /// unless they aren't plain identifiers (`unit price`), which are quoted
+    /// with [`SqlDialect::quote_ident()`]. Use `NULL` (without quotes) for
+    /// null. Each row is built with [`SqlDialect::select_row()`], so Oracle
+    /// rows select `FROM DUAL`.
+    pub fn to_sql(&self, dialect: Dialect) -> String {
+        self.to_sql_with(&dialect)
+    }
+
+    /// [`to_sql()`](Self::to_sql) for any [`SqlDialect`].
+    pub fn to_sql_with(&self, dialect: &dyn SqlDialect) -> String {
         if self.rows.is_empty() {
-            return format!("SELECT NULL WHERE 1=0 /* empty mock: {} */", self.name);
+            let row = dialect.select_row("NULL");
+            return format!("{row} WHERE 1=0 /* empty mock: {} */", self.name);
         }
 
         // Use the first row's keys to determine column order
@@ -54,26 +69,210 @@ impl MockTable {
                 .iter()
                 .map(|col| {
                     let val = row.get(*col).map(|s| s.as_str()).unwrap_or("NULL");
-                    if val == "NULL" {
-                        if i == 0 {
-                            format!("NULL AS {col}")
-                        } else {
-                            "NULL".to_string()
-                        }
+                    let val = if val == "NULL" {
+                        "NULL".to_string()
+                    } else {
+                        dialect.quote_literal(val)
+                    };
+                    if i == 0 && is_plain_ident(col) {
+                        format!("{val} AS {col}")
                     } else if i == 0 {
-                        format!("'{}' AS {col}", val.replace('\'', "''"))
+                        format!("{val} AS {}", dialect.quote_ident(col))
                     } else {
-                        format!("'{}'", val.replace('\'', "''"))
+                        val
                     }
                 })
                 .collect();
-            parts.push(format!("SELECT {}", values.join(", ")));
+            parts.push(dialect.select_row(&values.join(", ")));
         }
 
         parts.join("\nUNION ALL\n")
     }
 }
 
+/// Keywords that may directly follow a table reference and therefore can't be
+/// an alias for it.
+const NON_ALIAS_KEYWORDS: &[&str] = &[
+    "CROSS",
+    "EXCEPT",
+    "FETCH",
+    "FOR",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "INNER",
+    "INTERSECT",
+    "JOIN",
+    "LEFT",
+    "LIMIT",
+    "NATURAL",
+    "OFFSET",
+    "ON",
+    "ORDER",
+    "OUTER",
+    "RETURNING",
+    "RIGHT",
+    "UNION",
+    "USING",
+    "WHERE",
+    "WINDOW",
+];
+
+fn is_ident_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+fn is_ident_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Whether `name` is an identifier that needs no quoting.
+fn is_plain_ident(name: &str) -> bool {
+    name.starts_with(is_ident_start) && ident_len(name) == name.len()
+}
+
+/// Byte length of the identifier at the start of `s`.
+fn ident_len(s: &str) -> usize {
+    s.find(|ch: char| !is_ident_char(ch)).unwrap_or(s.len())
+}
+
+/// Whether the SQL following a table reference starts with an alias
+/// (`t`, or `AS t`).
+fn has_alias(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    if !rest.starts_with(is_ident_start) {
+        return false;
+    }
+    let word = &rest[..ident_len(rest)];
+    word.eq_ignore_ascii_case("AS")
+        || !NON_ALIAS_KEYWORDS
+            .iter()
+            .any(|kw| word.eq_ignore_ascii_case(kw))
+}
+
+/// Keywords that end the table list of a `FROM` clause.
+const CLAUSE_KEYWORDS: &[&str] = &[
+    "EXCEPT",
+    "FETCH",
+    "FOR",
+    "GROUP",
+    "HAVING",
+    "INTERSECT",
+    "LIMIT",
+    "OFFSET",
+    "ORDER",
+    "RETURNING",
+    "SELECT",
+    "SET",
+    "UNION",
+    "VALUES",
+    "WHERE",
+    "WINDOW",
+];
+
+/// Replace table references that follow `FROM` or `JOIN`, or a comma in a
+/// `FROM` list, with the SQL of a registered mock of the same name (compared
+/// case-insensitively).
+///
+/// The mock is wrapped in parentheses as a derived table. An existing alias
+/// is kept (`FROM users u` becomes `FROM (...) u`); otherwise the table name
+/// becomes the alias (`FROM users` becomes `FROM (...) AS users`, without the
+/// `AS` on dialects that take none before a table alias), so column
+/// references qualified by the table name keep working. String literals,
+/// quoted identifiers and SQL comments are copied unchanged, read as the
+/// template parser reads them for `dialect`.
+pub(crate) fn substitute_mocks(
+    sql: &str,
+    mocks: &HashMap<String, MockTable>,
+    dialect: &dyn SqlDialect,
+) -> String {
+    if mocks.is_empty() {
+        return sql.to_string();
+    }
+
+    let capabilities = dialect.capabilities();
+    let backslash_escapes = capabilities.backslash_escapes;
+    let mut out = String::with_capacity(sql.len());
+    let mut rest = sql;
+    // Whether a table reference may come next, and, per open parenthesis,
+    // whether it is inside a `FROM` list.
+    let mut expect_table = false;
+    let mut in_from = vec![false];
+
+    while let Some(ch) = rest.chars().next() {
+        if let Some(len) = quoted_len(&out, rest, backslash_escapes) {
+            // A comment between `FROM` and the table keeps it expected.
+            if !matches!(ch, '-' | '/') {
+                expect_table = false;
+            }
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+
+        if !is_ident_start(ch) {
+            match ch {
+                '(' => in_from.push(false),
+                ')' if in_from.len() > 1 => {
+                    in_from.pop();
+                }
+                _ => {}
+            }
+            if ch == ',' {
+                expect_table = in_from.last() == Some(&true);
+            } else if !ch.is_whitespace() {
+                expect_table = false;
+            }
+            out.push(ch);
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        }
+
+        let (word, tail) = rest.split_at(ident_len(rest));
+        rest = tail;
+
+        // A qualified name (`schema.table`) is never a mock reference.
+        let qualified = rest.starts_with('.');
+        let mock = mocks
+            .values()
+            .find(|m| m.name.eq_ignore_ascii_case(word))
+            .filter(|_| expect_table && !qualified);
+
+        match mock {
+            Some(mock) => {
+                out.push_str(&format!("({})", mock.to_sql_with(dialect)));
+                if !has_alias(rest) {
+                    let as_kw = if capabilities.derived_table_as {
+                        " AS"
+                    } else {
+                        ""
+                    };
+                    out.push_str(&format!("{as_kw} {word}"));
+                }
+                expect_table = false;
+            }
+            None => {
+                out.push_str(word);
+                expect_table =
+                    word.eq_ignore_ascii_case("FROM") || word.eq_ignore_ascii_case("JOIN");
+                let from = in_from
+                    .last_mut()
+                    .expect("the outermost level is never popped");
+                if expect_table {
+                    *from = true;
+                } else if CLAUSE_KEYWORDS
+                    .iter()
+                    .any(|kw| word.eq_ignore_ascii_case(kw))
+                {
+                    *from = false;
+                }
+            }
+        }
+    }
+
+    out
+}
+
 /// Convenience macro for building mock table rows.
 ///
 /// # Example
@@ -103,6 +302,8 @@ macro_rules! mock_rows {
 mod tests {
     use super::*;
 
+    const PG: Dialect = Dialect::Postgres;
+
     #[test]
     fn test_mock_table_single_row() {
         let mut mock = MockTable::new("users");
@@ -111,7 +312,7 @@ mod tests {
         row.insert("name".to_string(), "Alice".to_string());
         mock.add_row(row);
 
-        let sql = mock.to_sql();
+        let sql = mock.to_sql(PG);
         assert_eq!(sql, "SELECT '1' AS id, 'Alice' AS name");
     }
 
@@ -129,7 +330,7 @@ mod tests {
         row2.insert("name".to_string(), "Bob".to_string());
         mock.add_row(row2);
 
-        let sql = mock.to_sql();
+        let sql = mock.to_sql(PG);
         assert_eq!(
             sql,
             "SELECT '1' AS id, 'Alice' AS name\nUNION ALL\nSELECT '2', 'Bob'"
@@ -144,7 +345,7 @@ mod tests {
         row.insert("email".to_string(), "NULL".to_string());
         mock.add_row(row);
 
-        let sql = mock.to_sql();
+        let sql = mock.to_sql(PG);
         assert!(sql.contains("NULL AS email"));
         assert!(!sql.contains("'NULL'"));
     }
@@ -152,7 +353,7 @@ mod tests {
     #[test]
     fn test_mock_table_empty() {
         let mock = MockTable::new("users");
-        let sql = mock.to_sql();
+        let sql = mock.to_sql(PG);
         assert!(sql.contains("WHERE 1=0"));
     }
 
@@ -163,10 +364,162 @@ mod tests {
         row.insert("name".to_string(), "O'Brien".to_string());
         mock.add_row(row);
 
-        let sql = mock.to_sql();
+        let sql = mock.to_sql(PG);
         assert!(sql.contains("O''Brien"));
     }
 
+    #[test]
+    fn test_mock_table_quotes_per_dialect() {
+        let mut mock = MockTable::new("users");
+        mock.add_row(BTreeMap::from([(
+            "unit price".to_string(),
+            "C:\\tmp".to_string(),
+        )]));
+
+        assert_eq!(
+            mock.to_sql(Dialect::Mysql),
+            "SELECT 'C:\\\\tmp' AS `unit price`"
+        );
+        assert_eq!(
+            mock.to_sql(Dialect::Sqlite),
+            "SELECT 'C:\\tmp' AS \"unit price\""
+        );
+    }
+
+    #[test]
+    fn test_mock_table_rows_per_dialect() {
+        let mut mock = MockTable::new("users");
+        mock.add_row(BTreeMap::from([("id".to_string(), "1".to_string())]));
+        mock.add_row(BTreeMap::from([("id".to_string(), "2".to_string())]));
+
+        assert_eq!(
+            mock.to_sql(Dialect::Oracle),
+            "SELECT '1' AS id FROM DUAL\nUNION ALL\nSELECT '2' FROM DUAL"
+        );
+        assert_eq!(
+            mock.to_sql(Dialect::SqlServer),
+            "SELECT '1' AS id\nUNION ALL\nSELECT '2'"
+        );
+        assert_eq!(
+            MockTable::new("users").to_sql(Dialect::Oracle),
+            "SELECT NULL FROM DUAL WHERE 1=0 /* empty mock: users */"
+        );
+    }
+
+    fn mocks(names: &[&str]) -> HashMap<String, MockTable> {
+        names
+            .iter()
+            .map(|name| {
+                let mut mock = MockTable::new(*name);
+                mock.add_row(BTreeMap::from([("id".to_string(), "1".to_string())]));
+                (name.to_string(), mock)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_substitute_keeps_alias() {
+        let sql = substitute_mocks(
+            "SELECT s.id FROM users s WHERE s.id = 1",
+            &mocks(&["users"]),
+            &PG,
+        );
+        assert_eq!(sql, "SELECT s.id FROM (SELECT '1' AS id) s WHERE s.id = 1");
+
+        let sql = substitute_mocks("SELECT s.id FROM users AS s", &mocks(&["users"]), &PG);
+        assert_eq!(sql, "SELECT s.id FROM (SELECT '1' AS id) AS s");
+    }
+
+    #[test]
+    fn test_substitute_without_alias() {
+        let sql = substitute_mocks(
+            "SELECT users.id FROM users\nWHERE 1=1",
+            &mocks(&["users"]),
+            &PG,
+        );
+        assert_eq!(
+            sql,
+            "SELECT users.id FROM (SELECT '1' AS id) AS users\nWHERE 1=1"
+        );
+    }
+
+    #[test]
+    fn test_substitute_oracle_alias() {
+        let sql = substitute_mocks(
+            "SELECT users.id FROM users WHERE 1=1",
+            &mocks(&["users"]),
+            &Dialect::Oracle,
+        );
+        assert_eq!(
+            sql,
+            "SELECT users.id FROM (SELECT '1' AS id FROM DUAL) users WHERE 1=1"
+        );
+    }
+
+    #[test]
+    fn test_substitute_join() {
+        let sql = substitute_mocks(
+            "SELECT * FROM users u JOIN roles ON roles.id = u.id",
+            &mocks(&["users", "roles"]),
+            &PG,
+        );
+        assert_eq!(
+            sql,
+            "SELECT * FROM (SELECT '1' AS id) u JOIN (SELECT '1' AS id) AS roles ON roles.id = u.id"
+        );
+    }
+
+    #[test]
+    fn test_substitute_comma_join() {
+        let sql = substitute_mocks(
+            "SELECT a, users FROM accounts a, users WHERE (x, users) IN (SELECT 1, 2)",
+            &mocks(&["users"]),
+            &PG,
+        );
+        assert_eq!(
+            sql,
+            "SELECT a, users FROM accounts a, (SELECT '1' AS id) AS users WHERE (x, users) IN (SELECT 1, 2)"
+        );
+
+        let sql = substitute_mocks(
+            "SELECT * FROM (SELECT 1) t, users u",
+            &mocks(&["users"]),
+            &PG,
+        );
+        assert_eq!(sql, "SELECT * FROM (SELECT 1) t, (SELECT '1' AS id) u");
+    }
+
+    #[test]
+    fn test_substitute_skips_quoted_text() {
+        let mocks = mocks(&["users"]);
+        for sql in [
+            "SELECT 'FROM users' FROM t",
+            "SELECT * FROM t -- FROM users\n",
+            "SELECT * FROM t /* JOIN users */",
+            "SELECT * FROM \"users\"",
+        ] {
+            assert_eq!(substitute_mocks(sql, &mocks, &PG), sql);
+        }
+        assert_eq!(
+            substitute_mocks("SELECT * FROM /* mocked */ users", &mocks, &PG),
+            "SELECT * FROM /* mocked */ (SELECT '1' AS id) AS users"
+        );
+        // MySQL reads `\'` as an escaped quote, so the string runs on.
+        let sql = "SELECT 'it\\'s FROM users' FROM t";
+        assert_eq!(substitute_mocks(sql, &mocks, &Dialect::Mysql), sql);
+    }
+
+    #[test]
+    fn test_substitute_ignores_other_references() {
+        let mocks = mocks(&["users"]);
+        let sql = "INSERT INTO users SELECT users_id FROM other.users, accounts";
+        assert_eq!(substitute_mocks(sql, &mocks, &PG), sql);
+        assert_eq!(
+            substitute_mocks("SELECT * FROM USERS", &mocks, &PG),
+            "SELECT * FROM (SELECT '1' AS id) AS USERS"
+        );
+    }
+
     #[test]
     fn test_mock_rows_macro() {
         let rows = mock_rows![
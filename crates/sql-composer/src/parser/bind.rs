@@ -1,12 +1,13 @@
-//! Parser for `:bind(name [EXPECTING min[..max]] [NULL])` macros.
+//! Parser for `:bind(name [AS type | AS ARRAY [OF type]] [EXPECTING min[..max]] [NULL] [DEFAULT literal])`,
+//! `:bind_rows(name (col, ...))` and `:bind_order(name, ...)` macros.
 
-use winnow::combinator::{opt, preceded, trace};
+use winnow::combinator::{alt, delimited, opt, preceded, repeat, separated, trace};
 use winnow::error::ParserError;
 use winnow::stream::{AsBStr, AsChar, Compare, Stream, StreamIsPartial};
 use winnow::token::{literal, take_while};
 use winnow::Parser;
 
-use crate::types::Binding;
+use crate::types::{BindDefault, BindType, Binding, RowBinding};
 
 /// Parse a bind parameter name: one or more alphanumeric or underscore characters.
 pub fn bind_name<'i, Input, Error>(input: &mut Input) -> Result<String, Error>
@@ -29,7 +30,7 @@ where
 }
 
 /// Parse optional whitespace (spaces and tabs only, not newlines within macro parens).
-fn ws<'i, Input, Error>(input: &mut Input) -> Result<(), Error>
+pub(crate) fn ws<'i, Input, Error>(input: &mut Input) -> Result<(), Error>
 where
     Input: StreamIsPartial + Stream + Compare<&'i str>,
     <Input as Stream>::Slice: AsBStr,
@@ -63,6 +64,81 @@ where
     Ok(n)
 }
 
+/// Parse a required run of spaces or tabs.
+fn ws1<'i, Input, Error>(input: &mut Input) -> Result<(), Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    take_while(1.., |c: <Input as Stream>::Token| {
+        let ch = c.as_char();
+        ch == ' ' || ch == '\t'
+    })
+    .void()
+    .parse_next(input)
+}
+
+/// Parse `AS type` clause, e.g. `AS int8` or `AS text[]`.
+fn bind_type<'i, Input, Error>(input: &mut Input) -> Result<BindType, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("bind_type", move |input: &mut Input| {
+        literal("AS").parse_next(input)?;
+        ws1(input)?;
+        type_name(input)
+    })
+    .parse_next(input)
+}
+
+/// Parse a type name, e.g. `int8` or `text[]`.
+fn type_name<'i, Input, Error>(input: &mut Input) -> Result<BindType, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("type_name", move |input: &mut Input| {
+        let name = bind_name(input)?;
+        let array = opt(literal("[]")).parse_next(input)?;
+        let name = if array.is_some() {
+            format!("{name}[]")
+        } else {
+            name
+        };
+        BindType::parse(&name).ok_or_else(|| ParserError::from_input(input))
+    })
+    .parse_next(input)
+}
+
+/// Parse `AS ARRAY [OF type]` clause, returning the element type, e.g.
+/// `int8` for `AS ARRAY OF int8`. The element type can't be an array.
+fn array_clause<'i, Input, Error>(input: &mut Input) -> Result<Option<BindType>, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("array_clause", move |input: &mut Input| {
+        literal("AS").parse_next(input)?;
+        ws1(input)?;
+        literal("ARRAY").parse_next(input)?;
+        let element = opt(preceded((ws1, literal("OF"), ws1), type_name)).parse_next(input)?;
+        if let Some(BindType::Array(_)) = element {
+            return Err(ParserError::from_input(input));
+        }
+        Ok(element)
+    })
+    .parse_next(input)
+}
+
 /// Parse `EXPECTING min[..max]` clause.
 fn expecting<'i, Input, Error>(input: &mut Input) -> Result<(u32, Option<u32>), Error>
 where
@@ -81,7 +157,93 @@ where
     .parse_next(input)
 }
 
-/// Parse a complete `:bind(name [EXPECTING min[..max]] [NULL])` macro.
+/// Parse a default value literal: `NULL`, `TRUE`/`FALSE`, a number such as
+/// `100` or `-0.5`, or a single-quoted string with `''` escapes.
+pub fn default_value<'i, Input, Error>(input: &mut Input) -> Result<BindDefault, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("default_value", move |input: &mut Input| {
+        alt((
+            literal("NULL").value(BindDefault::Null),
+            alt((literal("TRUE"), literal("true"))).value(BindDefault::Bool(true)),
+            alt((literal("FALSE"), literal("false"))).value(BindDefault::Bool(false)),
+            number.map(BindDefault::Number),
+            quoted.map(BindDefault::Text),
+        ))
+        .parse_next(input)
+    })
+    .parse_next(input)
+}
+
+/// Parse a number: an optional `-`, digits, and an optional fraction.
+fn number<'i, Input, Error>(input: &mut Input) -> Result<String, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    let digits = |input: &mut Input| {
+        take_while(1.., |c: <Input as Stream>::Token| {
+            c.as_char().is_ascii_digit()
+        })
+        .parse_next(input)
+    };
+    let text = (
+        opt(literal("-")),
+        digits,
+        opt(preceded(literal("."), digits)),
+    )
+        .take()
+        .parse_next(input)?;
+    Ok(String::from_utf8_lossy(text.as_bstr()).to_string())
+}
+
+/// Parse a single-quoted string, unescaping doubled quotes.
+fn quoted<'i, Input, Error>(input: &mut Input) -> Result<String, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    let chunk = alt((
+        literal("''").value("'".to_string()),
+        take_while(1.., |c: <Input as Stream>::Token| c.as_char() != '\'')
+            .map(|s: <Input as Stream>::Slice| String::from_utf8_lossy(s.as_bstr()).to_string()),
+    ));
+    delimited(
+        literal("'"),
+        repeat(0.., chunk).fold(String::new, |mut acc, s: String| {
+            acc.push_str(&s);
+            acc
+        }),
+        literal("'"),
+    )
+    .parse_next(input)
+}
+
+/// Parse `DEFAULT literal` clause.
+fn default_clause<'i, Input, Error>(input: &mut Input) -> Result<BindDefault, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("default_clause", move |input: &mut Input| {
+        literal("DEFAULT").parse_next(input)?;
+        ws(input)?;
+        default_value(input)
+    })
+    .parse_next(input)
+}
+
+/// Parse a complete `:bind(name [AS type | AS ARRAY [OF type]] [EXPECTING min[..max]] [NULL] [DEFAULT literal])` macro.
 ///
 /// Assumes the `:bind(` prefix has already been consumed. Parses the contents
 /// up to and including the closing `)`.
@@ -96,12 +258,21 @@ where
         let name = bind_name(input)?;
         ws(input)?;
 
+        let (array, sql_type) = match opt(array_clause).parse_next(input)? {
+            Some(element) => (true, element),
+            None => (false, opt(bind_type).parse_next(input)?),
+        };
+        ws(input)?;
+
         let expecting_result = opt(expecting).parse_next(input)?;
         ws(input)?;
 
         let null_kw = opt(literal("NULL")).parse_next(input)?;
         ws(input)?;
 
+        let default = opt(default_clause).parse_next(input)?;
+        ws(input)?;
+
         literal(")").parse_next(input)?;
 
         let (min_values, max_values) = match expecting_result {
@@ -114,11 +285,73 @@ where
             min_values,
             max_values,
             nullable: null_kw.is_some(),
+            sql_type,
+            default,
+            array,
         })
     })
     .parse_next(input)
 }
 
+/// Parse a complete `:bind_rows(name (col, ...))` macro.
+///
+/// Assumes the `:bind_rows(` prefix has already been consumed. Parses the
+/// contents up to and including the closing `)`.
+pub fn bind_rows<'i, Input, Error>(input: &mut Input) -> Result<RowBinding, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("bind_rows", move |input: &mut Input| {
+        ws(input)?;
+        let name = bind_name(input)?;
+        ws(input)?;
+        literal("(").parse_next(input)?;
+        ws(input)?;
+        let columns: Vec<String> = separated(
+            1..,
+            bind_name,
+            (ws::<Input, Error>, literal(","), ws::<Input, Error>),
+        )
+        .parse_next(input)?;
+        ws(input)?;
+        literal(")").parse_next(input)?;
+        ws(input)?;
+        literal(")").parse_next(input)?;
+
+        Ok(RowBinding { name, columns })
+    })
+    .parse_next(input)
+}
+
+/// Parse a complete `:bind_order(name, ...)` declaration.
+///
+/// Assumes the `:bind_order(` prefix has already been consumed. Parses the
+/// contents up to and including the closing `)`.
+pub fn bind_order<'i, Input, Error>(input: &mut Input) -> Result<Vec<String>, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("bind_order", move |input: &mut Input| {
+        ws(input)?;
+        let names = separated(
+            1..,
+            bind_name,
+            (ws::<Input, Error>, literal(","), ws::<Input, Error>),
+        )
+        .parse_next(input)?;
+        ws(input)?;
+        literal(")").parse_next(input)?;
+        Ok(names)
+    })
+    .parse_next(input)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,6 +408,116 @@ mod tests {
         assert!(result.nullable);
     }
 
+    #[test]
+    fn test_bind_typed() {
+        let mut input: TestInput = "user_id AS int8)";
+        let result = bind::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(result.name, "user_id");
+        assert_eq!(result.sql_type, Some(BindType::Int8));
+        assert_eq!(input, "");
+    }
+
+    #[test]
+    fn test_bind_typed_array_with_constraints() {
+        let mut input: TestInput = "tags AS TEXT[] EXPECTING 1 NULL)";
+        let result = bind::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(
+            result.sql_type,
+            Some(BindType::Array(Box::new(BindType::Text)))
+        );
+        assert_eq!(result.min_values, Some(1));
+        assert!(result.nullable);
+    }
+
+    #[test]
+    fn test_bind_array() {
+        let mut input: TestInput = "ids AS ARRAY EXPECTING 1)";
+        let result = bind::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert!(result.array);
+        assert_eq!(result.sql_type, None);
+        assert_eq!(result.min_values, Some(1));
+        assert_eq!(input, "");
+    }
+
+    #[test]
+    fn test_bind_array_of_type() {
+        let mut input: TestInput = "ids AS ARRAY OF int8 EXPECTING 1)";
+        let result = bind::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert!(result.array);
+        assert_eq!(result.sql_type, Some(BindType::Int8));
+        assert_eq!(result.min_values, Some(1));
+        assert_eq!(input, "");
+
+        let mut input: TestInput = "ids AS ARRAY OF int8[])";
+        assert!(bind::<_, ContextError>.parse_next(&mut input).is_err());
+    }
+
+    #[test]
+    fn test_bind_unknown_type() {
+        let mut input: TestInput = "id AS widget)";
+        assert!(bind::<_, ContextError>.parse_next(&mut input).is_err());
+    }
+
+    #[test]
+    fn test_bind_default() {
+        let mut input: TestInput = "limit AS int8 DEFAULT 100)";
+        let result = bind::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(result.sql_type, Some(BindType::Int8));
+        assert_eq!(result.default, Some(BindDefault::Number("100".into())));
+        assert_eq!(input, "");
+
+        let mut input: TestInput = "email NULL DEFAULT NULL)";
+        let result = bind::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert!(result.nullable);
+        assert_eq!(result.default, Some(BindDefault::Null));
+    }
+
+    #[test]
+    fn test_default_values() {
+        let parse = |text: &str| default_value::<_, ContextError>.parse(text).ok();
+        assert_eq!(parse("-2.5"), Some(BindDefault::Number("-2.5".into())));
+        assert_eq!(parse("false"), Some(BindDefault::Bool(false)));
+        assert_eq!(
+            parse("'it''s (x)'"),
+            Some(BindDefault::Text("it's (x)".into()))
+        );
+        assert_eq!(parse("''"), Some(BindDefault::Text(String::new())));
+        assert_eq!(parse("'open"), None);
+        assert_eq!(parse("now()"), None);
+    }
+
+    #[test]
+    fn test_bind_rows() {
+        let mut input: TestInput = "items (part_num, color_id,quantity))";
+        let result = bind_rows::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(
+            result,
+            RowBinding {
+                name: "items".into(),
+                columns: vec!["part_num".into(), "color_id".into(), "quantity".into()],
+            }
+        );
+        assert_eq!(input, "");
+
+        let mut input: TestInput = "items ())";
+        assert!(bind_rows::<_, ContextError>.parse_next(&mut input).is_err());
+    }
+
+    #[test]
+    fn test_bind_order() {
+        let mut input: TestInput = " set_num, theme_id,year )";
+        let result = bind_order::<_, ContextError>
+            .parse_next(&mut input)
+            .unwrap();
+        assert_eq!(result, vec!["set_num", "theme_id", "year"]);
+        assert_eq!(input, "");
+
+        let mut input: TestInput = ")";
+        assert!(bind_order::<_, ContextError>
+            .parse_next(&mut input)
+            .is_err());
+    }
+
     #[test]
     fn test_bind_name_only() {
         let mut input: TestInput = "active";
@@ -1,4 +1,6 @@
-//! Parser for `:count(...)` and `:union(...)` command macros.
+//! Parser for `:count(...)`, `:union(...)`, `:intersect(...)`,
+//! `:except(...)`, `:exists(...)`, `:not_exists(...)`, `:json(...)` and
+//! `:paginate(...)` command macros.
 
 use std::path::PathBuf;
 
@@ -10,6 +12,8 @@ use winnow::Parser;
 
 use crate::types::{Command, CommandKind};
 
+use super::compose::slot_assignment;
+
 /// Parse optional whitespace within command parentheses.
 fn ws<'i, Input, Error>(input: &mut Input) -> Result<(), Error>
 where
@@ -58,6 +62,22 @@ where
     .parse_next(input)
 }
 
+/// Parse the KEYSET keyword.
+fn keyset_kw<'i, Input, Error>(input: &mut Input) -> Result<bool, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("keyset", move |input: &mut Input| {
+        literal("KEYSET").parse_next(input)?;
+        ws(input)?;
+        Ok(true)
+    })
+    .parse_next(input)
+}
+
 /// Parse a column name: alphanumeric + underscore + dot.
 fn column_name<'i, Input, Error>(input: &mut Input) -> Result<String, Error>
 where
@@ -75,6 +95,22 @@ where
     Ok(name)
 }
 
+/// Parse a column name with an optional `DESC`: `created_at DESC`.
+fn sort_column<'i, Input, Error>(input: &mut Input) -> Result<(String, bool), Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("sort_column", move |input: &mut Input| {
+        let name = column_name(input)?;
+        let descending = opt((ws, literal("DESC"))).parse_next(input)?.is_some();
+        Ok((name, descending))
+    })
+    .parse_next(input)
+}
+
 /// Parse a comma separator with optional surrounding whitespace.
 fn comma_sep<'i, Input, Error>(input: &mut Input) -> Result<(), Error>
 where
@@ -89,8 +125,8 @@ where
     Ok(())
 }
 
-/// Parse a column list followed by `OF`: `col1, col2, col3 OF`.
-fn columns_of<'i, Input, Error>(input: &mut Input) -> Result<Vec<String>, Error>
+/// Parse a column list followed by `OF`: `col1, col2 DESC, col3 OF`.
+fn columns_of<'i, Input, Error>(input: &mut Input) -> Result<Vec<(String, bool)>, Error>
 where
     Input: StreamIsPartial + Stream + Compare<&'i str>,
     <Input as Stream>::Slice: AsBStr,
@@ -98,7 +134,7 @@ where
     Error: ParserError<Input>,
 {
     trace("columns_of", move |input: &mut Input| {
-        let cols: Vec<String> = separated(1.., column_name, comma_sep).parse_next(input)?;
+        let cols: Vec<(String, bool)> = separated(1.., sort_column, comma_sep).parse_next(input)?;
         ws(input)?;
         literal("OF").parse_next(input)?;
         ws(input)?;
@@ -126,7 +162,8 @@ where
 
 /// Parse the command kind from the prefix keyword.
 ///
-/// This parses `count(` or `union(` and returns the command kind.
+/// This parses `count(`, `union(`, `intersect(`, `except(`, `exists(`,
+/// `not_exists(`, `json(` or `paginate(` and returns the command kind.
 pub fn command_kind<'i, Input, Error>(input: &mut Input) -> Result<CommandKind, Error>
 where
     Input: StreamIsPartial + Stream + Compare<&'i str>,
@@ -138,15 +175,24 @@ where
         alt((
             literal("count(").map(|_| CommandKind::Count),
             literal("union(").map(|_| CommandKind::Union),
+            literal("intersect(").map(|_| CommandKind::Intersect),
+            literal("except(").map(|_| CommandKind::Except),
+            literal("exists(").map(|_| CommandKind::Exists),
+            literal("not_exists(").map(|_| CommandKind::NotExists),
+            literal("json(").map(|_| CommandKind::Json),
+            literal("paginate(").map(|_| CommandKind::Paginate),
         ))
         .parse_next(input)
     })
     .parse_next(input)
 }
 
-/// Parse the body of a command after `count(` or `union(` has been consumed.
+/// Parse the body of a command after its `count(`, `union(`, etc. has been
+/// consumed.
+///
+/// Grammar: `[DISTINCT] [ALL] [KEYSET] [columns OF] source1[, source2, ...][, @slot = path, ...] )`
 ///
-/// Grammar: `[DISTINCT] [ALL] [columns OF] source1[, source2, ...] )`
+/// `KEYSET` and a required `columns OF` belong to `:paginate()`.
 pub fn command_body<'i, Input, Error>(
     input: &mut Input,
     kind: CommandKind,
@@ -161,8 +207,59 @@ where
         ws(input)?;
         let is_distinct = opt(distinct).parse_next(input)?.unwrap_or(false);
         let is_all = opt(all_kw).parse_next(input)?.unwrap_or(false);
-        let columns = opt(columns_of).parse_next(input)?;
-        let sources: Vec<PathBuf> = separated(1.., source_path, comma_sep).parse_next(input)?;
+        let paginate = kind == CommandKind::Paginate;
+        let is_keyset = paginate && opt(keyset_kw).parse_next(input)?.unwrap_or(false);
+        let (columns, descending) = match opt(columns_of).parse_next(input)? {
+            Some(columns) => {
+                let (columns, descending): (Vec<_>, Vec<_>) = columns.into_iter().unzip();
+                (Some(columns), descending)
+            }
+            None => (None, Vec::new()),
+        };
+        // Set operations join whole rows and existence tests only ask for
+        // one, so neither selects columns.
+        let whole_rows = matches!(
+            kind,
+            CommandKind::Union
+                | CommandKind::Intersect
+                | CommandKind::Except
+                | CommandKind::Exists
+                | CommandKind::NotExists
+        );
+        if (paginate && columns.is_none()) || (whole_rows && columns.is_some()) {
+            return Err(ParserError::from_input(input));
+        }
+        // Only a page sorts, and it names its cursor bindings after its
+        // columns, so they take no table qualifier.
+        let sorted = descending.contains(&true);
+        let qualified = columns.iter().flatten().any(|column| column.contains('.'));
+        if (sorted && !paginate) || (qualified && paginate) {
+            return Err(ParserError::from_input(input));
+        }
+        let mut sources = vec![source_path(input)?];
+        let mut slots = Vec::new();
+        while opt(comma_sep).parse_next(input)?.is_some() {
+            let checkpoint = input.checkpoint();
+            let is_slot = literal::<_, _, Error>("@").parse_next(input).is_ok();
+            input.reset(&checkpoint);
+            if is_slot {
+                slots.push(slot_assignment(input)?);
+            } else if slots.is_empty() {
+                sources.push(source_path(input)?);
+            } else {
+                return Err(ParserError::from_input(input));
+            }
+        }
+        // `DISTINCT` and `ALL` pick the set operation, and `DISTINCT` a
+        // distinct count. Other commands use them only to join several
+        // sources.
+        let modifiers = matches!(
+            kind,
+            CommandKind::Union | CommandKind::Intersect | CommandKind::Except | CommandKind::Count
+        ) || sources.len() > 1;
+        if (is_distinct && is_all) || ((is_distinct || is_all) && !modifiers) {
+            return Err(ParserError::from_input(input));
+        }
         ws(input)?;
         literal(")").parse_next(input)?;
 
@@ -170,8 +267,11 @@ where
             kind,
             distinct: is_distinct,
             all: is_all,
+            keyset: is_keyset,
             columns,
+            descending,
             sources,
+            slots,
         })
     })
     .parse_next(input)
@@ -180,6 +280,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::SlotAssignment;
     use winnow::error::ContextError;
 
     type TestInput<'a> = &'a str;
@@ -202,6 +303,145 @@ mod tests {
         assert_eq!(result, CommandKind::Union);
     }
 
+    #[test]
+    fn test_command_set_operations() {
+        let mut input: TestInput = "intersect(ALL a.tql, b.tql)";
+        let kind = command_kind::<_, ContextError>
+            .parse_next(&mut input)
+            .unwrap();
+        let result = command_body::<_, ContextError>(&mut input, kind).unwrap();
+        assert_eq!(result.kind, CommandKind::Intersect);
+        assert!(result.all);
+        assert_eq!(
+            result.sources,
+            vec![PathBuf::from("a.tql"), PathBuf::from("b.tql")]
+        );
+
+        let mut input: TestInput = "except(DISTINCT a.tql, b.tql, c.tql)";
+        let kind = command_kind::<_, ContextError>
+            .parse_next(&mut input)
+            .unwrap();
+        let result = command_body::<_, ContextError>(&mut input, kind).unwrap();
+        assert_eq!(result.kind, CommandKind::Except);
+        assert!(result.distinct);
+        assert_eq!(result.sources.len(), 3);
+
+        for body in ["intersect(id OF a.tql, b.tql)", "except(id, name OF a.tql)"] {
+            let mut input: TestInput = body;
+            let kind = command_kind::<_, ContextError>
+                .parse_next(&mut input)
+                .unwrap();
+            assert!(command_body::<_, ContextError>(&mut input, kind).is_err());
+        }
+    }
+
+    #[test]
+    fn test_command_exists_with_slots() {
+        let mut input: TestInput = "not_exists(parts.tql, @match = by_set.tql)";
+        let kind = command_kind::<_, ContextError>
+            .parse_next(&mut input)
+            .unwrap();
+        let result = command_body::<_, ContextError>(&mut input, kind).unwrap();
+        assert_eq!(result.kind, CommandKind::NotExists);
+        assert_eq!(result.sources, vec![PathBuf::from("parts.tql")]);
+        assert_eq!(
+            result.slots,
+            vec![SlotAssignment {
+                name: "match".into(),
+                path: PathBuf::from("by_set.tql"),
+            }]
+        );
+
+        for body in [
+            "exists(a.tql, @match = m.tql, b.tql)",
+            "exists(id OF a.tql)",
+        ] {
+            let mut input: TestInput = body;
+            let kind = command_kind::<_, ContextError>
+                .parse_next(&mut input)
+                .unwrap();
+            assert!(command_body::<_, ContextError>(&mut input, kind).is_err());
+        }
+    }
+
+    #[test]
+    fn test_command_json() {
+        let mut input: TestInput = "json(id, name OF users.tql)";
+        let kind = command_kind::<_, ContextError>
+            .parse_next(&mut input)
+            .unwrap();
+        let result = command_body::<_, ContextError>(&mut input, kind).unwrap();
+        assert_eq!(result.kind, CommandKind::Json);
+        assert_eq!(
+            result.columns,
+            Some(vec!["id".to_string(), "name".to_string()])
+        );
+
+        let mut input: TestInput = "json(ALL id OF a.tql, b.tql)";
+        let kind = command_kind::<_, ContextError>
+            .parse_next(&mut input)
+            .unwrap();
+        let result = command_body::<_, ContextError>(&mut input, kind).unwrap();
+        assert!(result.all);
+
+        for body in [
+            "json(DISTINCT id OF users.tql)",
+            "paginate(ALL id OF posts.tql)",
+            "union(DISTINCT ALL a.tql, b.tql)",
+        ] {
+            let mut input: TestInput = body;
+            let kind = command_kind::<_, ContextError>
+                .parse_next(&mut input)
+                .unwrap();
+            assert!(command_body::<_, ContextError>(&mut input, kind).is_err());
+        }
+    }
+
+    #[test]
+    fn test_command_paginate() {
+        let mut input: TestInput = "paginate(KEYSET created_at, id OF posts.tql)";
+        let kind = command_kind::<_, ContextError>
+            .parse_next(&mut input)
+            .unwrap();
+        let result = command_body::<_, ContextError>(&mut input, kind).unwrap();
+        assert_eq!(result.kind, CommandKind::Paginate);
+        assert!(result.keyset);
+        assert_eq!(
+            result.columns,
+            Some(vec!["created_at".to_string(), "id".to_string()])
+        );
+
+        let mut input: TestInput = "paginate(id OF posts.tql)";
+        let kind = command_kind::<_, ContextError>
+            .parse_next(&mut input)
+            .unwrap();
+        let result = command_body::<_, ContextError>(&mut input, kind).unwrap();
+        assert!(!result.keyset);
+
+        let mut input: TestInput = "paginate(KEYSET created_at DESC, id OF posts.tql)";
+        let kind = command_kind::<_, ContextError>
+            .parse_next(&mut input)
+            .unwrap();
+        let result = command_body::<_, ContextError>(&mut input, kind).unwrap();
+        assert_eq!(
+            result.columns,
+            Some(vec!["created_at".to_string(), "id".to_string()])
+        );
+        assert_eq!(result.descending, vec![true, false]);
+
+        for body in [
+            "paginate(posts.tql)",
+            "paginate(p.id OF posts.tql)",
+            "count(id DESC OF posts.tql)",
+        ] {
+            let mut input: TestInput = body;
+            let kind = command_kind::<_, ContextError>
+                .parse_next(&mut input)
+                .unwrap();
+            assert!(command_body::<_, ContextError>(&mut input, kind).is_err());
+        }
+    }
+
     #[test]
     fn test_command_simple_count() {
         let mut input: TestInput = "count(templates/get_user.tql)";
@@ -69,7 +69,7 @@ where
 }
 
 /// Parse a slot assignment: `@name = path`.
-fn slot_assignment<'i, Input, Error>(input: &mut Input) -> Result<SlotAssignment, Error>
+pub fn slot_assignment<'i, Input, Error>(input: &mut Input) -> Result<SlotAssignment, Error>
 where
     Input: StreamIsPartial + Stream + Compare<&'i str>,
     <Input as Stream>::Slice: AsBStr,
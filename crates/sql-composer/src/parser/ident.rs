@@ -0,0 +1,72 @@
+//! Parser for `:ident(name ONE OF a, b, ...)` macros.
+
+use winnow::combinator::{separated, trace};
+use winnow::error::ParserError;
+use winnow::stream::{AsBStr, AsChar, Compare, Stream, StreamIsPartial};
+use winnow::token::literal;
+use winnow::Parser;
+
+use crate::types::Identifier;
+
+use super::bind::{bind_name, ws};
+
+/// Parse a complete `:ident(name ONE OF a, b, ...)` macro.
+///
+/// Assumes the `:ident(` prefix has already been consumed. Parses the
+/// contents up to and including the closing `)`.
+pub fn ident<'i, Input, Error>(input: &mut Input) -> Result<Identifier, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("ident", move |input: &mut Input| {
+        ws(input)?;
+        let name = bind_name(input)?;
+        ws(input)?;
+        literal("ONE").parse_next(input)?;
+        ws(input)?;
+        literal("OF").parse_next(input)?;
+        ws(input)?;
+
+        let allowed: Vec<String> = separated(
+            1..,
+            bind_name,
+            (ws::<Input, Error>, literal(","), ws::<Input, Error>),
+        )
+        .parse_next(input)?;
+        ws(input)?;
+        literal(")").parse_next(input)?;
+
+        Ok(Identifier { name, allowed })
+    })
+    .parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winnow::error::ContextError;
+
+    type TestInput<'a> = &'a str;
+
+    #[test]
+    fn test_ident() {
+        let mut input: TestInput = "sort_col ONE OF name, year,num_parts )";
+        let result = ident::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(
+            result,
+            Identifier {
+                name: "sort_col".into(),
+                allowed: vec!["name".into(), "year".into(), "num_parts".into()],
+            }
+        );
+        assert_eq!(input, "");
+
+        let mut input: TestInput = "sort_col)";
+        assert!(ident::<_, ContextError>.parse_next(&mut input).is_err());
+        let mut input: TestInput = "sort_col ONE OF )";
+        assert!(ident::<_, ContextError>.parse_next(&mut input).is_err());
+    }
+}
@@ -1,47 +1,182 @@
 //! winnow 0.7 parsers for sql-composer template macros.
 //!
 //! The parser treats SQL text as opaque literals and only recognizes the
-//! template macro syntax: `:bind(...)`, `:compose(...)`, `:count(...)`,
-//! and `:union(...)`.
+//! template macro syntax: `:bind(...)`, `:bind_rows(...)`, `:bind_order(...)`,
+//! `:compose(...)`, `:count(...)`, `:union(...)`, `:intersect(...)`,
+//! `:except(...)`, `:exists(...)`, `:not_exists(...)`, `:json(...)`,
+//! `:paginate(...)`, `:ident(...)` and the `:if(...)`/`:else`/`:endif` block markers.
 
 pub mod bind;
 pub mod command;
 pub mod compose;
+pub mod ident;
 pub mod template;
 
 use winnow::error::ContextError;
-use winnow::Parser;
 
 use crate::error;
-use crate::types::{Element, Template, TemplateSource};
+use crate::types::{CommentStyle, Element, Span, Template, TemplateSource};
 
 /// Parse a template string into a [`Template`].
 ///
 /// This is the main entry point for parsing template content from a string.
+/// Parse failures report the line and column of the offending text.
 pub fn parse_template(input: &str, source: TemplateSource) -> error::Result<Template> {
+    parse_template_with(input, source, CommentStyle::default())
+}
+
+/// Parse a template string, recognizing template comments as `comments` says.
+pub fn parse_template_with(
+    input: &str,
+    source: TemplateSource,
+    comments: CommentStyle,
+) -> error::Result<Template> {
+    parse_spanned(input, source, comments, false).map(|(template, _)| template)
+}
+
+/// Parse a template string, returning the [`Span`] in `input` of each element
+/// alongside the template. With `backslash_escapes` set, a backslash escapes
+/// the next character in every string literal.
+pub(crate) fn parse_spanned(
+    input: &str,
+    source: TemplateSource,
+    comments: CommentStyle,
+    backslash_escapes: bool,
+) -> error::Result<(Template, Vec<Span>)> {
     let mut remaining = input;
-    let elements: Vec<Element> = template::template::<_, ContextError>
-        .parse_next(&mut remaining)
-        .map_err(|e| error::Error::Parse {
-            location: format!("offset {}", input.len() - remaining.len()),
-            message: e.to_string(),
-        })?;
-
-    Ok(Template { elements, source })
+    let parse_error = |remaining: &str, message: String| {
+        let offset = input.len() - remaining.len();
+        let span = Span::from_offsets(input, offset, offset);
+        error::Error::Parse {
+            location: error::Location::new(source.clone(), span, Some(input)),
+            message,
+        }
+    };
+
+    let spanned = template::spanned_template_with::<_, ContextError>(
+        &mut remaining,
+        comments,
+        backslash_escapes,
+    )
+    .map_err(|e| parse_error(remaining, e.to_string()))?;
+
+    // The template parser stops at the first macro it can't parse, or at a
+    // quote or comment that is never closed.
+    if let Some(unclosed) = unclosed(remaining) {
+        return Err(parse_error(remaining, format!("unterminated {unclosed}")));
+    }
+    if !remaining.is_empty() {
+        let name: String = remaining[1..]
+            .chars()
+            .take_while(|ch| ch.is_alphanumeric() || *ch == '_')
+            .collect();
+        return Err(parse_error(
+            remaining,
+            format!("malformed `:{name}(...)` macro"),
+        ));
+    }
+
+    // Block markers must pair up: each `:else` and `:endif` inside an open
+    // `:if`, at most one `:else` per block, and no block left open.
+    let mut open: Vec<(&str, bool)> = Vec::new();
+    for (element, range) in &spanned {
+        let at = &input[range.start..];
+        match element {
+            Element::If(_) => open.push((at, false)),
+            Element::Else => match open.last_mut() {
+                Some((_, seen_else @ false)) => *seen_else = true,
+                Some(_) => return Err(parse_error(at, "second `:else` in one `:if` block".into())),
+                None => return Err(parse_error(at, "`:else` without `:if`".into())),
+            },
+            Element::EndIf => {
+                open.pop()
+                    .ok_or_else(|| parse_error(at, "`:endif` without `:if`".into()))?;
+            }
+            _ => {}
+        }
+    }
+    if let Some((at, _)) = open.pop() {
+        return Err(parse_error(at, "`:if` without `:endif`".into()));
+    }
+
+    let (elements, spans) = spanned
+        .into_iter()
+        .map(|(element, range)| (element, Span::from_offsets(input, range.start, range.end)))
+        .unzip();
+
+    Ok((Template { elements, source }, spans))
+}
+
+/// What the unparsed `remaining` input opens without closing, if it starts
+/// with a quote, dollar quote or block comment.
+fn unclosed(remaining: &str) -> Option<&'static str> {
+    match remaining.chars().next()? {
+        '\'' => Some("string literal"),
+        '"' | '`' => Some("quoted identifier"),
+        '$' => Some("dollar-quoted string"),
+        '/' => Some("block comment"),
+        _ => None,
+    }
 }
 
 /// Parse a template from a file path.
 ///
 /// Reads the file content and parses it as a template.
 pub fn parse_template_file(path: &std::path::Path) -> error::Result<Template> {
+    parse_template_file_with(path, CommentStyle::default())
+}
+
+/// Parse a template from a file path, recognizing template comments as
+/// `comments` says.
+pub fn parse_template_file_with(
+    path: &std::path::Path,
+    comments: CommentStyle,
+) -> error::Result<Template> {
     let content = std::fs::read_to_string(path)?;
-    parse_template(&content, TemplateSource::File(path.to_path_buf()))
+    parse_template_with(&content, TemplateSource::File(path.to_path_buf()), comments)
+}
+
+/// The first line of `input` whose meaning changes when template comments
+/// are recognized as `to` says instead of `from`, or `None` if the template
+/// parses the same either way.
+///
+/// Use it before switching a project's [`CommentStyle`]: a flagged line holds
+/// a comment that would become SQL, or SQL that would become a comment.
+pub fn comment_style_change(input: &str, from: CommentStyle, to: CommentStyle) -> Option<usize> {
+    // Both parses agree up to the first comment one of them drops and the
+    // other doesn't, so that comment's start is where the meaning changes.
+    // Failing that, compare the elements and where each parse stopped.
+    let scan = |comments| {
+        let mut remaining = input;
+        let (elements, dropped) =
+            template::scan_template::<_, ContextError>(&mut remaining, comments, false)
+                .unwrap_or_default();
+        (dropped, elements, input.len() - remaining.len())
+    };
+    let (from_dropped, from_elements, from_end) = scan(from);
+    let (to_dropped, to_elements, to_end) = scan(to);
+
+    let offset = first_difference(&from_dropped, &to_dropped, |range| range.start)
+        .or_else(|| first_difference(&from_elements, &to_elements, |(_, range)| range.start))
+        .or_else(|| (from_end != to_end).then(|| from_end.min(to_end)))?;
+    Some(Span::from_offsets(input, offset, offset).line)
+}
+
+/// The smallest `start` of the first items at which `a` and `b` differ,
+/// counting an item only one of them has.
+fn first_difference<T: PartialEq>(a: &[T], b: &[T], start: impl Fn(&T) -> usize) -> Option<usize> {
+    let common = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    [a.get(common), b.get(common)]
+        .into_iter()
+        .flatten()
+        .map(start)
+        .min()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{Binding, Element};
+    use crate::types::Binding;
 
     #[test]
     fn test_parse_template_literal() {
@@ -60,14 +195,128 @@ mod tests {
             tpl.elements[1],
             Element::Bind(Binding {
                 name: "user_id".into(),
-                min_values: None,
-                max_values: None,
-                nullable: false,
+                ..Default::default()
             })
         );
         assert_eq!(tpl.elements[2], Element::Sql(";".into()));
     }
 
+    #[test]
+    fn test_parse_template_spans() {
+        let (tpl, spans) = parse_spanned(
+            "SELECT *\nFROM users\nWHERE id = :bind(user_id)",
+            TemplateSource::Literal("test".into()),
+            CommentStyle::default(),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(spans.len(), tpl.elements.len());
+        assert_eq!(
+            spans[1],
+            Span {
+                start: 31,
+                end: 45,
+                line: 3,
+                column: 12,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let err = parse_template(
+            "SELECT *\nFROM users\nWHERE id = :bind(user_id EXPECTING)",
+            TemplateSource::Literal("test".into()),
+        )
+        .unwrap_err();
+
+        match err {
+            error::Error::Parse { location, message } => {
+                assert_eq!(location.to_string(), "<test>:3:12");
+                assert_eq!(
+                    location.snippet.as_deref(),
+                    Some("  |\n3 | WHERE id = :bind(user_id EXPECTING)\n  |            ^")
+                );
+                assert_eq!(message, "malformed `:bind(...)` macro");
+            }
+            other => panic!("expected Parse, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unterminated_quote() {
+        let err = parse_template(
+            "SELECT :bind(a)\nFROM t WHERE name = 'x\nAND id = :bind(id)",
+            TemplateSource::Literal("t".into()),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parse error at <t>:2:21: unterminated string literal\n  |\n2 | FROM t WHERE name = 'x\n  |                     ^"
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_if_blocks() {
+        let message = |input: &str| match parse_template(input, TemplateSource::Literal("t".into()))
+        {
+            Err(error::Error::Parse { location, message }) => format!("{location} {message}"),
+            other => panic!("expected Parse, got {:?}", other),
+        };
+
+        assert_eq!(
+            message("SELECT 1\n:endif"),
+            "<t>:2:1 `:endif` without `:if`"
+        );
+        assert_eq!(message("SELECT 1 :else"), "<t>:1:10 `:else` without `:if`");
+        assert_eq!(
+            message(":if(a) x :else y :else z :endif"),
+            "<t>:1:18 second `:else` in one `:if` block"
+        );
+        assert_eq!(
+            message(":if(a) :if(b) x :endif"),
+            "<t>:1:1 `:if` without `:endif`"
+        );
+    }
+
+    #[test]
+    fn test_comment_style_change() {
+        let input = "## totals\nSELECT data #> '{a}' FROM t\nWHERE id = :bind(id) # trailing\n";
+        assert_eq!(
+            comment_style_change(input, CommentStyle::Hash, CommentStyle::DoubleHash),
+            Some(2)
+        );
+        assert_eq!(
+            comment_style_change(input, CommentStyle::Hash, CommentStyle::LineHash),
+            Some(2)
+        );
+
+        let input = "## totals\nSELECT data FROM t\n";
+        assert_eq!(
+            comment_style_change(input, CommentStyle::Hash, CommentStyle::DoubleHash),
+            None
+        );
+        assert_eq!(
+            comment_style_change(input, CommentStyle::Hash, CommentStyle::DashHash),
+            Some(1)
+        );
+
+        // A `#` inside a string spanning lines is text either way.
+        let input = "SELECT 'a\n# b'\nFROM t\n";
+        assert_eq!(
+            comment_style_change(input, CommentStyle::Hash, CommentStyle::DoubleHash),
+            None
+        );
+
+        // The change is found on its own line within a long SQL element.
+        let input = "SELECT 1\nFROM t\nWHERE data #> '{a}' = 'x\ny'\n";
+        assert_eq!(
+            comment_style_change(input, CommentStyle::Hash, CommentStyle::DoubleHash),
+            Some(3)
+        );
+    }
+
     #[test]
     fn test_parse_template_multiline() {
         let input = "SELECT id, name, email\nFROM users\nWHERE id = :bind(user_id)\n  AND active = :bind(active);";
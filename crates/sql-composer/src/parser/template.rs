@@ -1,27 +1,42 @@
 //! Top-level template parser that dispatches between macros and literal SQL.
 //!
 //! The key insight of this parser is that SQL is treated as opaque literal text.
-//! Only the `:bind(...)`, `:compose(...)`, `:count(...)`, and `:union(...)`
-//! macros are parsed; everything else passes through unchanged.
+//! Only the `:bind(...)`, `:bind_rows(...)`, `:bind_order(...)`, `:compose(...)`,
+//! `:count(...)`, `:union(...)`, `:intersect(...)`, `:except(...)`,
+//! `:exists(...)`, `:not_exists(...)`, `:json(...)`, `:paginate(...)` and
+//! `:ident(...)` macros and the `:if(...)`/`:else`/`:endif` block markers are
+//! parsed; everything else passes through unchanged.
 //!
 //! Lines or trailing portions beginning with `#` are template comments and are
 //! silently stripped during parsing — they never appear in composed SQL output.
+//!
+//! The literal scanner knows enough SQL lexing to skip over quoted strings,
+//! quoted identifiers, dollar-quoted bodies and SQL comments: a `#` or a
+//! macro inside them is passed through as text. Write `\:bind(` for a
+//! literal `:bind(` elsewhere. A quote, dollar quote or block comment left
+//! open stops the scan there, so the template fails to parse instead of
+//! swallowing the rest as text.
+
+use std::ops::Range;
 
 use winnow::combinator::{alt, repeat, trace};
-use winnow::error::ParserError;
+use winnow::error::{ContextError, ParserError};
 use winnow::stream::{AsBStr, AsChar, Compare, Stream, StreamIsPartial};
 use winnow::token::{any, literal};
 use winnow::Parser;
 
-use crate::types::Element;
+use crate::types::{CommentStyle, Element};
 
-use super::bind::bind;
+use super::bind::{bind, bind_name, bind_order, bind_rows, ws};
 use super::command::{command_body, command_kind};
 use super::compose::compose;
+use super::ident::ident;
 
 /// Parse a single macro invocation after the `:` prefix.
 ///
-/// Tries `bind(`, `compose(`, `count(`, or `union(` in order.
+/// Tries `bind(`, `bind_rows(`, `bind_order(`, `compose(`, `count(`, `union(`,
+/// `intersect(`, `except(`, `exists(`, `not_exists(`, `json(`, `paginate(`,
+/// `ident(`, then the `if(`, `else` and `endif` block markers.
 fn macro_invocation<'i, Input, Error>(input: &mut Input) -> Result<Element, Error>
 where
     Input: StreamIsPartial + Stream + Compare<&'i str>,
@@ -34,6 +49,12 @@ where
 
         alt((
             literal("bind(").flat_map(|_| bind).map(Element::Bind),
+            literal("bind_rows(")
+                .flat_map(|_| bind_rows)
+                .map(Element::BindRows),
+            literal("bind_order(")
+                .flat_map(|_| bind_order)
+                .map(Element::BindOrder),
             literal("compose(")
                 .flat_map(|_| compose)
                 .map(Element::Compose),
@@ -42,18 +63,342 @@ where
                 let cmd = command_body(input, kind)?;
                 Ok(Element::Command(cmd))
             },
+            literal("ident(").flat_map(|_| ident).map(Element::Ident),
+            literal("if(").flat_map(|_| condition).map(Element::If),
+            |input: &mut Input| keyword(input, "else").map(|_| Element::Else),
+            |input: &mut Input| keyword(input, "endif").map(|_| Element::EndIf),
         ))
         .parse_next(input)
     })
     .parse_next(input)
 }
 
+/// Parse the binding name of an `:if(name)` block, after the `:if(` prefix,
+/// up to and including the closing `)`.
+fn condition<'i, Input, Error>(input: &mut Input) -> Result<String, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("condition", move |input: &mut Input| {
+        ws(input)?;
+        let name = bind_name(input)?;
+        ws(input)?;
+        literal(")").parse_next(input)?;
+        Ok(name)
+    })
+    .parse_next(input)
+}
+
+/// Parse a bare block keyword (`else`, `endif`) that isn't the start of a
+/// longer identifier.
+fn keyword<'i, Input, Error>(input: &mut Input, word: &'i str) -> Result<(), Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("keyword", move |input: &mut Input| {
+        literal(word).parse_next(input)?;
+        let checkpoint = input.checkpoint();
+        let next = any::<_, Error>.parse_next(input);
+        input.reset(&checkpoint);
+        match next {
+            Ok(c) if is_ident_char(c.clone().as_char()) => Err(ParserError::from_input(input)),
+            _ => Ok(()),
+        }
+    })
+    .parse_next(input)
+}
+
+/// The macro names recognized after a `:`, each followed by `(`.
+const MACROS: &[&str] = &[
+    "bind(",
+    "bind_rows(",
+    "bind_order(",
+    "compose(",
+    "count(",
+    "union(",
+    "intersect(",
+    "except(",
+    "exists(",
+    "not_exists(",
+    "json(",
+    "paginate(",
+    "ident(",
+    "if(",
+];
+
+/// The block keywords recognized after a `:`, without parentheses.
+const KEYWORDS: &[&str] = &["else", "endif"];
+
+/// Whether `ch` can appear in an unquoted SQL identifier.
+fn is_ident_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Whether `text` comes next in the input, without consuming it.
+fn peek<'i, Input, Error>(input: &mut Input, text: &'i str) -> bool
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    Error: ParserError<Input>,
+{
+    let checkpoint = input.checkpoint();
+    let found = literal::<_, Input, Error>(text).parse_next(input).is_ok();
+    input.reset(&checkpoint);
+    found
+}
+
+/// Whether a macro invocation (`:` + a known macro name + `(`) or a block
+/// keyword (`:else`, `:endif`) comes next.
+fn at_macro<'i, Input, Error>(input: &mut Input) -> bool
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    let checkpoint = input.checkpoint();
+    let found = literal::<_, Input, Error>(":").parse_next(input).is_ok()
+        && (MACROS.iter().any(|name| peek::<_, Error>(input, name))
+            || KEYWORDS.iter().any(|word| {
+                let at_word = keyword::<_, Error>(input, word).is_ok();
+                input.reset(&checkpoint);
+                let _ = literal::<_, Input, Error>(":").parse_next(input);
+                at_word
+            }));
+    input.reset(&checkpoint);
+    found
+}
+
+/// Consume the rest of the line after a block marker if it is blank,
+/// including its line break. Returns whether it did.
+fn line_end<'i, Input, Error>(input: &mut Input) -> bool
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    let checkpoint = input.checkpoint();
+    loop {
+        match any::<_, Error>.parse_next(input) {
+            Ok(c) => match c.as_char() {
+                ' ' | '\t' | '\r' => continue,
+                '\n' => return true,
+                _ => break,
+            },
+            Err(_) => return true,
+        }
+    }
+    input.reset(&checkpoint);
+    false
+}
+
+/// Copy characters into `sql` up to and including `close`. Returns `false`
+/// if the input ends first.
+///
+/// A doubled `close` stands for itself (`'it''s'`), and with `backslash` set a
+/// backslash escapes the character after it (`E'it\'s'`).
+fn quoted<'i, Input, Error>(
+    input: &mut Input,
+    sql: &mut String,
+    close: char,
+    backslash: bool,
+) -> bool
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    while let Ok(c) = any::<_, Error>.parse_next(input) {
+        let ch = c.as_char();
+        sql.push(ch);
+        if backslash && ch == '\\' {
+            if let Ok(c) = any::<_, Error>.parse_next(input) {
+                sql.push(c.as_char());
+            }
+        } else if ch == close {
+            let checkpoint = input.checkpoint();
+            match any::<_, Error>.parse_next(input) {
+                Ok(c) if c.clone().as_char() == close => sql.push(close),
+                _ => {
+                    input.reset(&checkpoint);
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Whether a `'` following `before` opens an `E'...'` string, which takes
+/// backslash escapes.
+fn escape_string(before: &str) -> bool {
+    let mut prefix = before.chars().rev();
+    matches!(prefix.next(), Some('E' | 'e')) && !prefix.next().is_some_and(is_ident_char)
+}
+
+/// The byte length of the string literal, quoted identifier, dollar-quoted
+/// body or SQL comment at the start of `sql`, read the way the template
+/// scanner reads it, or `None` if none starts there. `before` is the SQL
+/// preceding it; one left open runs to the end of `sql`.
+pub(crate) fn quoted_len(before: &str, sql: &str, backslash_escapes: bool) -> Option<usize> {
+    let mut input = sql;
+    let mut text = String::new();
+    let ch = any::<_, ContextError>.parse_next(&mut input).ok()?;
+    match ch {
+        '\'' => {
+            let escapes = backslash_escapes || escape_string(before);
+            quoted::<_, ContextError>(&mut input, &mut text, ch, escapes);
+        }
+        '"' | '`' => {
+            quoted::<_, ContextError>(&mut input, &mut text, ch, false);
+        }
+        '$' if !before.chars().next_back().is_some_and(is_ident_char) => {
+            let tag = dollar_tag::<_, ContextError>(&mut input)?;
+            until::<_, ContextError>(&mut input, &mut text, &tag);
+        }
+        '-' if input.starts_with('-') => {
+            until::<_, ContextError>(&mut input, &mut text, "\n");
+        }
+        '/' if input.starts_with('*') => {
+            input = &input[1..];
+            block_comment::<_, ContextError>(&mut input, &mut text);
+        }
+        _ => return None,
+    }
+    Some(sql.len() - input.len())
+}
+
+/// Copy characters into `sql` up to and including `close`, or to the end of
+/// input. Returns whether `close` was found.
+fn until<'i, Input, Error>(input: &mut Input, sql: &mut String, close: &str) -> bool
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    let start = sql.len();
+    while let Ok(c) = any::<_, Error>.parse_next(input) {
+        sql.push(c.as_char());
+        if sql.len() >= start + close.len() && sql.ends_with(close) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Copy a `/* ... */` comment, already opened with `/*`, into `sql`.
+/// Comments nest, as in Postgres. Returns `false` if the input ends first.
+fn block_comment<'i, Input, Error>(input: &mut Input, sql: &mut String) -> bool
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    let mut depth = 1;
+    while depth > 0 {
+        if literal::<_, Input, Error>("/*").parse_next(input).is_ok() {
+            sql.push_str("/*");
+            depth += 1;
+        } else if literal::<_, Input, Error>("*/").parse_next(input).is_ok() {
+            sql.push_str("*/");
+            depth -= 1;
+        } else {
+            match any::<_, Error>.parse_next(input) {
+                Ok(c) => sql.push(c.as_char()),
+                Err(_) => return false,
+            }
+        }
+    }
+    true
+}
+
+/// Parse the rest of a dollar-quote tag after its opening `$`: an optional
+/// identifier and a closing `$`. Returns `None`, consuming nothing, if the
+/// `$` doesn't open a dollar quote (e.g. a `$1` placeholder).
+fn dollar_tag<'i, Input, Error>(input: &mut Input) -> Option<String>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    let checkpoint = input.checkpoint();
+    let mut tag = String::from("$");
+    while let Ok(c) = any::<_, Error>.parse_next(input) {
+        let ch = c.as_char();
+        if ch == '$' {
+            tag.push('$');
+            return Some(tag);
+        }
+        let starts_ok = tag.len() > 1 || !ch.is_ascii_digit();
+        if !(starts_ok && is_ident_char(ch)) {
+            break;
+        }
+        tag.push(ch);
+    }
+    input.reset(&checkpoint);
+    None
+}
+
+/// Whether `ch`, just consumed after the literal SQL in `sql`, starts a
+/// template comment under `comments`; consumes the rest of a multi-character
+/// marker if so.
+///
+/// `line_start` says whether `sql` begins at the start of a line.
+fn starts_comment<'i, Input, Error>(
+    input: &mut Input,
+    sql: &str,
+    ch: char,
+    comments: CommentStyle,
+    line_start: bool,
+) -> bool
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    Error: ParserError<Input>,
+{
+    match comments {
+        CommentStyle::Hash => ch == '#',
+        CommentStyle::LineHash => {
+            let (line_start, line) = match sql.rfind('\n') {
+                Some(i) => (true, &sql[i + 1..]),
+                None => (line_start, sql),
+            };
+            ch == '#' && line_start && line.chars().all(|c| c == ' ' || c == '\t')
+        }
+        CommentStyle::DoubleHash => {
+            ch == '#' && literal::<_, Input, Error>("#").parse_next(input).is_ok()
+        }
+        CommentStyle::DashHash => {
+            ch == '-' && literal::<_, Input, Error>("-#").parse_next(input).is_ok()
+        }
+    }
+}
+
 /// Parse literal SQL text: everything up to the next `:` that starts a macro,
 /// or to the end of input.
 ///
 /// Accumulates characters one at a time, stopping when we encounter a `:`
-/// followed by a known macro name and `(`.
-fn sql_literal<'i, Input, Error>(input: &mut Input) -> Result<Element, Error>
+/// followed by a known macro name and `(`. SQL string literals (`'...'`,
+/// `E'...'`), quoted identifiers (`"..."`, `` `...` ``), dollar-quoted bodies
+/// (`$$...$$`, `$tag$...$tag$`) and SQL comments (`-- ...`, `/* ... */`)
+/// are copied verbatim, so macro syntax and `#` inside them are plain text.
+/// With `backslash_escapes` set, a backslash escapes the next character in
+/// every `'...'` string, as in MySQL's `'it\'s'`. The scan stops before a
+/// quote or comment that is never closed.
+/// `\:` before a macro name writes a literal `:` (`\:bind(x)` is the text
+/// `:bind(x)`). Template comments, marked as `comments` says, are dropped
+/// and their byte ranges, relative to the start of the input, appended to
+/// `dropped`; `line_start` says whether the input begins at the start of a
+/// line.
+fn sql_literal<'i, Input, Error>(
+    input: &mut Input,
+    comments: CommentStyle,
+    backslash_escapes: bool,
+    line_start: bool,
+    dropped: &mut Vec<Range<usize>>,
+) -> Result<Element, Error>
 where
     Input: StreamIsPartial + Stream + Compare<&'i str>,
     <Input as Stream>::Slice: AsBStr,
@@ -61,52 +406,86 @@ where
     Error: ParserError<Input>,
 {
     trace("sql_literal", move |input: &mut Input| {
+        let start = input.checkpoint();
         let mut sql = String::new();
         let mut consumed_comment = false;
 
         loop {
-            // Check if we're at a macro start
-            let checkpoint = input.checkpoint();
-            if literal::<_, _, Error>(":").parse_next(input).is_ok() {
-                // Check if this is followed by a known macro name + "("
-                let is_macro = alt((
-                    literal::<_, Input, Error>("bind(").void(),
-                    literal::<_, Input, Error>("compose(").void(),
-                    literal::<_, Input, Error>("count(").void(),
-                    literal::<_, Input, Error>("union(").void(),
-                ))
-                .parse_next(input)
-                .is_ok();
-
-                // Reset to before the ":"
-                input.reset(&checkpoint);
+            if at_macro::<_, Error>(input) {
+                break;
+            }
 
-                if is_macro {
-                    break;
+            // Try to consume one character
+            let at = input.offset_from(&start);
+            let before = (input.checkpoint(), sql.len());
+            let Ok(c) = any::<_, Error>.parse_next(input) else {
+                break; // EOF
+            };
+            let ch = c.as_char();
+            if starts_comment::<_, Error>(input, &sql, ch, comments, line_start) {
+                // Comment: skip to end of line (or EOF)
+                consumed_comment = true;
+                loop {
+                    match any::<_, Error>.parse_next(input) {
+                        Ok(c) if c.clone().as_char() == '\n' => break,
+                        Ok(_) => continue,
+                        Err(_) => break, // EOF
+                    }
                 }
-            } else {
-                input.reset(&checkpoint);
+                dropped.push(at..input.offset_from(&start));
+                continue;
             }
 
-            // Try to consume one character
-            match any::<_, Error>.parse_next(input) {
-                Ok(c) => {
-                    let ch = c.as_char();
-                    if ch == '#' {
-                        // Comment: skip to end of line (or EOF)
-                        consumed_comment = true;
-                        loop {
-                            match any::<_, Error>.parse_next(input) {
-                                Ok(c) if c.clone().as_char() == '\n' => break,
-                                Ok(_) => continue,
-                                Err(_) => break, // EOF
-                            }
+            let closed = match ch {
+                '\\' if at_macro::<_, Error>(input) => {
+                    // Escaped macro: keep the `:` as text
+                    let _ = literal::<_, Input, Error>(":").parse_next(input);
+                    sql.push(':');
+                    true
+                }
+                '\'' => {
+                    let escapes = backslash_escapes || escape_string(&sql);
+                    sql.push(ch);
+                    quoted::<_, Error>(input, &mut sql, '\'', escapes)
+                }
+                '"' | '`' => {
+                    sql.push(ch);
+                    quoted::<_, Error>(input, &mut sql, ch, false)
+                }
+                '$' => {
+                    sql.push(ch);
+                    let tag = match sql.chars().rev().nth(1) {
+                        Some(prev) if is_ident_char(prev) => None,
+                        _ => dollar_tag::<_, Error>(input),
+                    };
+                    match tag {
+                        Some(tag) => {
+                            sql.push_str(&tag[1..]);
+                            until::<_, Error>(input, &mut sql, &tag)
                         }
-                    } else {
-                        sql.push(ch);
+                        None => true,
                     }
                 }
-                Err(_) => break, // EOF
+                '-' if peek::<_, Error>(input, "-") => {
+                    sql.push(ch);
+                    until::<_, Error>(input, &mut sql, "\n");
+                    true
+                }
+                '/' if peek::<_, Error>(input, "*") => {
+                    let _ = any::<_, Error>.parse_next(input);
+                    sql.push_str("/*");
+                    block_comment::<_, Error>(input, &mut sql)
+                }
+                _ => {
+                    sql.push(ch);
+                    true
+                }
+            };
+            if !closed {
+                // Leave the unclosed quote or comment for the caller to report.
+                input.reset(&before.0);
+                sql.truncate(before.1);
+                break;
             }
         }
 
@@ -119,8 +498,15 @@ where
     .parse_next(input)
 }
 
-/// Parse a single template element: either a macro invocation or literal SQL.
-fn element<'i, Input, Error>(input: &mut Input) -> Result<Element, Error>
+/// Parse a single template element: either a macro invocation or literal SQL,
+/// appending the ranges of any template comments it drops to `dropped`.
+fn element<'i, Input, Error>(
+    input: &mut Input,
+    comments: CommentStyle,
+    backslash_escapes: bool,
+    line_start: bool,
+    dropped: &mut Vec<Range<usize>>,
+) -> Result<Element, Error>
 where
     Input: StreamIsPartial + Stream + Compare<&'i str>,
     <Input as Stream>::Slice: AsBStr,
@@ -128,7 +514,10 @@ where
     Error: ParserError<Input>,
 {
     trace("element", move |input: &mut Input| {
-        alt((macro_invocation, sql_literal)).parse_next(input)
+        alt((macro_invocation, |input: &mut Input| {
+            sql_literal(input, comments, backslash_escapes, line_start, dropped)
+        }))
+        .parse_next(input)
     })
     .parse_next(input)
 }
@@ -137,6 +526,21 @@ where
 ///
 /// This is the top-level parser entry point for template content.
 pub fn template<'i, Input, Error>(input: &mut Input) -> Result<Vec<Element>, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    template_with(input, CommentStyle::default())
+}
+
+/// Parse a complete template, recognizing template comments as `comments`
+/// says.
+pub fn template_with<'i, Input, Error>(
+    input: &mut Input,
+    comments: CommentStyle,
+) -> Result<Vec<Element>, Error>
 where
     Input: StreamIsPartial + Stream + Compare<&'i str>,
     <Input as Stream>::Slice: AsBStr,
@@ -144,18 +548,128 @@ where
     Error: ParserError<Input>,
 {
     trace("template", move |input: &mut Input| {
-        let elements: Vec<Element> = repeat(0.., element).parse_next(input)?;
+        let elements = spanned_template_with(input, comments, false)?;
+        Ok(elements.into_iter().map(|(element, _)| element).collect())
+    })
+    .parse_next(input)
+}
+
+/// Parse a complete template into elements paired with the byte range each
+/// element was parsed from, relative to the start of the input.
+pub fn spanned_template<'i, Input, Error>(
+    input: &mut Input,
+) -> Result<Vec<(Element, Range<usize>)>, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    spanned_template_with(input, CommentStyle::default(), false)
+}
+
+/// Like [`spanned_template()`], recognizing template comments as `comments`
+/// says and, with `backslash_escapes` set, backslash escapes in every string
+/// literal (see [`Capabilities::backslash_escapes`](crate::Capabilities::backslash_escapes)).
+pub fn spanned_template_with<'i, Input, Error>(
+    input: &mut Input,
+    comments: CommentStyle,
+    backslash_escapes: bool,
+) -> Result<Vec<(Element, Range<usize>)>, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("spanned_template", move |input: &mut Input| {
+        let (elements, _) = scan_template(input, comments, backslash_escapes)?;
         Ok(elements)
     })
     .parse_next(input)
 }
 
+/// Like [`spanned_template_with()`], also returning the byte range of every
+/// template comment the parse dropped.
+pub(crate) fn scan_template<'i, Input, Error>(
+    input: &mut Input,
+    comments: CommentStyle,
+    backslash_escapes: bool,
+) -> Result<Scanned, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'i str>,
+    <Input as Stream>::Slice: AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    trace("scan_template", move |input: &mut Input| {
+        let start = input.checkpoint();
+        let mut line_start = true;
+        let mut own_lines = Vec::new();
+        let mut dropped = Vec::new();
+        let elements: Vec<(Element, Range<usize>)> = repeat(0.., |input: &mut Input| {
+            let from = input.offset_from(&start);
+            let mut found = Vec::new();
+            let el = element(input, comments, backslash_escapes, line_start, &mut found)?;
+            dropped.extend(found.into_iter().map(|r| from + r.start..from + r.end));
+            let block = matches!(
+                el,
+                Element::If(_) | Element::Else | Element::EndIf | Element::BindOrder(_)
+            );
+            // A block marker or `:bind_order()` on a line of its own takes the
+            // whole line with it.
+            let own_line = block && line_start && line_end::<_, Error>(input);
+            line_start = match &el {
+                Element::Sql(sql) => match sql.rfind('\n') {
+                    Some(i) => is_blank(&sql[i + 1..]),
+                    None => line_start && is_blank(sql),
+                },
+                _ => own_line,
+            };
+            own_lines.push(own_line);
+            Ok((el, from..input.offset_from(&start)))
+        })
+        .parse_next(input)?;
+        Ok((trim_indents(elements, &own_lines), dropped))
+    })
+    .parse_next(input)
+}
+
+/// Elements paired with their byte ranges, and the byte ranges of the
+/// template comments dropped between them.
+pub(crate) type Scanned = (Vec<(Element, Range<usize>)>, Vec<Range<usize>>);
+
+/// Whether `text` holds only spaces and tabs.
+fn is_blank(text: &str) -> bool {
+    text.chars().all(|c| c == ' ' || c == '\t')
+}
+
+/// Drop the indentation before each block marker that has a line of its
+/// own, as flagged in `own_lines`, so such lines leave no trace in the SQL.
+fn trim_indents(
+    elements: Vec<(Element, Range<usize>)>,
+    own_lines: &[bool],
+) -> Vec<(Element, Range<usize>)> {
+    let mut trimmed: Vec<(Element, Range<usize>)> = Vec::with_capacity(elements.len());
+    for ((element, range), &own_line) in elements.into_iter().zip(own_lines) {
+        if own_line {
+            if let Some((Element::Sql(sql), _)) = trimmed.last_mut() {
+                sql.truncate(sql.trim_end_matches([' ', '\t']).len());
+                if sql.is_empty() {
+                    trimmed.pop();
+                }
+            }
+        }
+        trimmed.push((element, range));
+    }
+    trimmed
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::types::{Binding, CommandKind, ComposeRef, ComposeTarget};
     use std::path::PathBuf;
-    use winnow::error::ContextError;
 
     type TestInput<'a> = &'a str;
 
@@ -180,9 +694,7 @@ mod tests {
             result[1],
             Element::Bind(Binding {
                 name: "user_id".into(),
-                min_values: None,
-                max_values: None,
-                nullable: false,
+                ..Default::default()
             })
         );
     }
@@ -213,9 +725,7 @@ mod tests {
             result[1],
             Element::Bind(Binding {
                 name: "user_id".into(),
-                min_values: None,
-                max_values: None,
-                nullable: false,
+                ..Default::default()
             })
         );
         assert_eq!(result[2], Element::Sql(" AND active = ".into()));
@@ -223,9 +733,7 @@ mod tests {
             result[3],
             Element::Bind(Binding {
                 name: "active".into(),
-                min_values: None,
-                max_values: None,
-                nullable: false,
+                ..Default::default()
             })
         );
     }
@@ -266,9 +774,7 @@ mod tests {
             result[1],
             Element::Bind(Binding {
                 name: "user_id".into(),
-                min_values: None,
-                max_values: None,
-                nullable: false,
+                ..Default::default()
             })
         );
         assert_eq!(result[2], Element::Sql("\n  AND active = ".into()));
@@ -276,9 +782,7 @@ mod tests {
             result[3],
             Element::Bind(Binding {
                 name: "active".into(),
-                min_values: None,
-                max_values: None,
-                nullable: false,
+                ..Default::default()
             })
         );
         assert_eq!(result[4], Element::Sql(";".into()));
@@ -294,9 +798,7 @@ mod tests {
             result[1],
             Element::Bind(Binding {
                 name: "user_id".into(),
-                min_values: None,
-                max_values: None,
-                nullable: false,
+                ..Default::default()
             })
         );
         assert_eq!(result[2], Element::Sql(";".into()));
@@ -346,9 +848,7 @@ mod tests {
             result[1],
             Element::Bind(Binding {
                 name: "id".into(),
-                min_values: None,
-                max_values: None,
-                nullable: false,
+                ..Default::default()
             })
         );
         assert_eq!(result[2], Element::Sql(";".into()));
@@ -390,6 +890,187 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hash_in_string_literal() {
+        let mut input: TestInput = "SELECT * FROM colors WHERE rgb = '#05131D' # black\nAND id = 1";
+        let result = template::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(
+            result,
+            vec![Element::Sql(
+                "SELECT * FROM colors WHERE rgb = '#05131D' AND id = 1".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_macros_in_quoted_text_are_literal() {
+        let cases = [
+            "SELECT 'it''s :bind(x) # not a comment'",
+            "SELECT E'it\\'s :bind(x)'",
+            r#"SELECT "col :bind(x)" FROM t"#,
+            "SELECT `col#:bind(x)` FROM t",
+            "SELECT $$ :compose(a.sqlc) # $$",
+            "SELECT $fn$ ' :bind(x) $fn$",
+            "SELECT 1 -- :bind(x) # here\n",
+            "SELECT /* :bind(x) /* nested */ # */ 1",
+        ];
+        for sql in cases {
+            let mut input: TestInput = sql;
+            let result = template::<_, ContextError>.parse_next(&mut input).unwrap();
+            assert_eq!(result, vec![Element::Sql(sql.into())], "{sql}");
+        }
+    }
+
+    #[test]
+    fn test_unclosed_quote_stops_scan() {
+        for sql in [
+            "SELECT 'it :bind(x)",
+            "SELECT \"col",
+            "SELECT $$ body",
+            "SELECT /* /* */ :bind(x)",
+        ] {
+            let mut input: TestInput = sql;
+            let result = template::<_, ContextError>.parse_next(&mut input).unwrap();
+            assert_eq!(result, vec![Element::Sql("SELECT ".into())], "{sql}");
+            assert_eq!(input, &sql[7..]);
+        }
+    }
+
+    #[test]
+    fn test_backslash_escapes() {
+        let sql = "SELECT 'it\\'s' AS a, :bind(x)";
+        let parse = |backslash_escapes| {
+            let mut input: TestInput = sql;
+            let result = spanned_template_with::<_, ContextError>(
+                &mut input,
+                CommentStyle::default(),
+                backslash_escapes,
+            )
+            .unwrap();
+            (result.len(), input)
+        };
+
+        // Standard SQL closes the string at `\'`, leaving `' AS a, ...` open.
+        assert_eq!(parse(false), (1, "' AS a, :bind(x)"));
+        assert_eq!(parse(true), (2, ""));
+    }
+
+    #[test]
+    fn test_quoted_len() {
+        assert_eq!(quoted_len("", "'it''s' x", false), Some(7));
+        assert_eq!(quoted_len("", "'it\\'s' x", false), Some(5));
+        assert_eq!(quoted_len("", "'it\\'s' x", true), Some(7));
+        assert_eq!(quoted_len("SELECT E", "'it\\'s' x", false), Some(7));
+        assert_eq!(quoted_len("", "$f$ ' $f$ x", false), Some(9));
+        assert_eq!(quoted_len("", "-- c\nx", false), Some(5));
+        assert_eq!(quoted_len("", "/* /* */ */ x", false), Some(11));
+        assert_eq!(quoted_len("", "'open", false), Some(5));
+        assert_eq!(quoted_len("a", "$1 x", false), None);
+        assert_eq!(quoted_len("", "x 'y'", false), None);
+    }
+
+    #[test]
+    fn test_macro_after_quoted_text() {
+        let mut input: TestInput = "SELECT $1, 'a' || :bind(x) -- c\n, :bind(y)";
+        let result = template::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[0], Element::Sql("SELECT $1, 'a' || ".into()));
+        assert_eq!(result[2], Element::Sql(" -- c\n, ".into()));
+    }
+
+    #[test]
+    fn test_escaped_macro() {
+        let mut input: TestInput = "SELECT '\\:bind(' AS a, \\:bind(x), 'C:\\' AS b";
+        let result = template::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(
+            result,
+            vec![Element::Sql(
+                "SELECT '\\:bind(' AS a, :bind(x), 'C:\\' AS b".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_comment_styles() {
+        let parse = |sql: &str, comments| {
+            let mut input: TestInput = sql;
+            template_with::<_, ContextError>(&mut input, comments).unwrap()
+        };
+        let sql = |text: &str| vec![Element::Sql(text.into())];
+
+        let jsonb = "# totals\nSELECT data #> '{a}', data #- '{b}' FROM t";
+        assert_eq!(
+            parse(jsonb, CommentStyle::LineHash),
+            sql("SELECT data #> '{a}', data #- '{b}' FROM t")
+        );
+        assert_eq!(
+            parse("SELECT 1\n  # note\nFROM t # x", CommentStyle::LineHash),
+            sql("SELECT 1\n  FROM t # x")
+        );
+        assert_eq!(
+            parse(
+                "## note\nSELECT a # b ## c\nFROM t",
+                CommentStyle::DoubleHash
+            ),
+            sql("SELECT a # b FROM t")
+        );
+        assert_eq!(
+            parse("--# note\nSELECT 1 # a -- b\n--# c", CommentStyle::DashHash),
+            sql("SELECT 1 # a -- b\n")
+        );
+    }
+
+    #[test]
+    fn test_line_hash_after_macro() {
+        let mut input: TestInput = ":bind(a) # not a comment\n# comment\n";
+        let result = template_with::<_, ContextError>(&mut input, CommentStyle::LineHash).unwrap();
+        assert_eq!(result[1], Element::Sql(" # not a comment\n".into()));
+    }
+
+    #[test]
+    fn test_if_blocks() {
+        let mut input: TestInput = "WHERE 1 = 1\n  :if( name )\n  AND name = 'x'\n  :else\n  AND 0 = 1\n  :endif\nORDER BY id";
+        let result = template::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                Element::Sql("WHERE 1 = 1\n".into()),
+                Element::If("name".into()),
+                Element::Sql("  AND name = 'x'\n".into()),
+                Element::Else,
+                Element::Sql("  AND 0 = 1\n".into()),
+                Element::EndIf,
+                Element::Sql("ORDER BY id".into()),
+            ]
+        );
+
+        // Inline markers leave the surrounding text alone.
+        let mut input: TestInput = "a :if(x)b:else c:endif d";
+        let result = template::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(result[0], Element::Sql("a ".into()));
+        assert_eq!(result[2], Element::Sql("b".into()));
+        assert_eq!(result[4], Element::Sql(" c".into()));
+        assert_eq!(result[6], Element::Sql(" d".into()));
+
+        // Longer identifiers aren't block keywords.
+        let mut input: TestInput = "SELECT :elsewhere, :endifs";
+        let result = template::<_, ContextError>.parse_next(&mut input).unwrap();
+        assert_eq!(
+            result,
+            vec![Element::Sql("SELECT :elsewhere, :endifs".into())]
+        );
+    }
+
+    #[test]
+    fn test_spanned_template() {
+        let mut input: TestInput = "SELECT :bind(a)\nFROM t";
+        let result = spanned_template::<_, ContextError>
+            .parse_next(&mut input)
+            .unwrap();
+        let spans: Vec<Range<usize>> = result.into_iter().map(|(_, span)| span).collect();
+        assert_eq!(spans, vec![0..7, 7..15, 15..22]);
+    }
+
     #[test]
     fn test_comments_before_macro() {
         let mut input: TestInput = "# comment line 1\n# comment line 2\n:bind(id)";
@@ -401,9 +1082,7 @@ mod tests {
             result[1],
             Element::Bind(Binding {
                 name: "id".into(),
-                min_values: None,
-                max_values: None,
-                nullable: false,
+                ..Default::default()
             })
         );
     }
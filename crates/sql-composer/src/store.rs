@@ -0,0 +1,266 @@
+//! Parsed-template cache and the dependency graph between template files.
+//!
+//! The [`Composer`](crate::composer::Composer) loads every referenced file
+//! through a [`TemplateStore`], so a shared template is read and parsed once
+//! no matter how many `:compose()` or command references point at it.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::error::Result;
+use crate::parser;
+use crate::types::{CommandKind, CommentStyle, Span, Template, TemplateSource};
+
+/// A cache of parsed templates keyed by resolved file path.
+///
+/// Entries live until they are invalidated, so a long-lived store does not
+/// notice edits to files it has already parsed. Call
+/// [`TemplateStore::invalidate()`] when a file changes.
+#[derive(Debug, Default)]
+pub struct TemplateStore {
+    templates: RwLock<HashMap<PathBuf, Parsed>>,
+    comments: CommentStyle,
+    backslash_escapes: bool,
+}
+
+/// A cached template with the text it was parsed from.
+#[derive(Debug)]
+struct Parsed {
+    template: Arc<Template>,
+    text: Arc<str>,
+    /// Source spans parallel to the template's elements.
+    spans: Arc<[Span]>,
+}
+
+impl TemplateStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty store that parses template comments as `comments`
+    /// says.
+    pub fn with_comments(comments: CommentStyle) -> Self {
+        Self::with_syntax(comments, false)
+    }
+
+    /// Create an empty store that parses template comments as `comments`
+    /// says and, with `backslash_escapes` set, reads a backslash in a string
+    /// literal as escaping the next character, as
+    /// [`Capabilities::backslash_escapes`](crate::Capabilities::backslash_escapes)
+    /// describes.
+    pub fn with_syntax(comments: CommentStyle, backslash_escapes: bool) -> Self {
+        Self {
+            comments,
+            backslash_escapes,
+            ..Self::default()
+        }
+    }
+
+    /// How this store recognizes template comments.
+    pub fn comments(&self) -> CommentStyle {
+        self.comments
+    }
+
+    /// Whether this store reads backslash escapes in string literals.
+    pub fn backslash_escapes(&self) -> bool {
+        self.backslash_escapes
+    }
+
+    /// Return the parsed template at `path`, reading and parsing it on first use.
+    pub fn load(&self, path: &Path) -> Result<Arc<Template>> {
+        if let Some(parsed) = self.read().get(path) {
+            return Ok(Arc::clone(&parsed.template));
+        }
+
+        let text = std::fs::read_to_string(path)?;
+        let (template, spans) = parser::parse_spanned(
+            &text,
+            TemplateSource::File(path.to_path_buf()),
+            self.comments,
+            self.backslash_escapes,
+        )?;
+        let template = Arc::new(template);
+        let parsed = Parsed {
+            template: Arc::clone(&template),
+            text: text.into(),
+            spans: spans.into(),
+        };
+        self.write().insert(path.to_path_buf(), parsed);
+        Ok(template)
+    }
+
+    /// The text and element spans of the cached template at `path`, for
+    /// locating errors. Never reads the file.
+    pub(crate) fn source_map(&self, path: &Path) -> Option<(Arc<str>, Arc<[Span]>)> {
+        self.read()
+            .get(path)
+            .map(|parsed| (Arc::clone(&parsed.text), Arc::clone(&parsed.spans)))
+    }
+
+    /// Drop the cached template at `path` so the next load re-parses it.
+    pub fn invalidate(&self, path: &Path) {
+        self.write().remove(path);
+    }
+
+    /// Drop every cached template.
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    /// The resolved paths of all cached templates.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.read().keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    /// Number of cached templates.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Whether the store holds no templates.
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<PathBuf, Parsed>> {
+        self.templates.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<PathBuf, Parsed>> {
+        self.templates.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// How one template refers to another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyKind {
+    /// The target of a `:compose(path)`.
+    Compose,
+    /// A file assigned to a slot: `:compose(..., @name = path)`.
+    Slot(String),
+    /// A source of a `:count(...)`, `:union(...)`, `:intersect(...)` or
+    /// `:except(...)` command.
+    Command(CommandKind),
+}
+
+/// An edge in the [`DependencyGraph`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// Resolved path of the referenced template, or the path as written if it
+    /// could not be found on the search paths.
+    pub path: PathBuf,
+    /// How the template is referenced.
+    pub kind: DependencyKind,
+}
+
+/// Which templates compose which, keyed by resolved file path.
+///
+/// Slot references (`:compose(@name)`) depend on the caller, so they appear
+/// as [`DependencyKind::Slot`] edges from the template that fills the slot
+/// rather than from the template that declares it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DependencyGraph {
+    edges: BTreeMap<PathBuf, Vec<Dependency>>,
+}
+
+impl DependencyGraph {
+    /// Record the direct dependencies of `path`.
+    pub(crate) fn insert(&mut self, path: PathBuf, dependencies: Vec<Dependency>) {
+        self.edges.insert(path, dependencies);
+    }
+
+    /// Every template in the graph.
+    pub fn templates(&self) -> impl Iterator<Item = &Path> {
+        self.edges.keys().map(PathBuf::as_path)
+    }
+
+    /// Direct dependencies of `path`, in document order.
+    pub fn dependencies(&self, path: &Path) -> &[Dependency] {
+        self.edges.get(path).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Templates that directly depend on `path`.
+    pub fn dependents(&self, path: &Path) -> Vec<&Path> {
+        self.edges
+            .iter()
+            .filter(|(_, deps)| deps.iter().any(|d| d.path == path))
+            .map(|(from, _)| from.as_path())
+            .collect()
+    }
+
+    /// `path` together with everything it depends on, transitively.
+    pub fn closure(&self, path: &Path) -> BTreeSet<PathBuf> {
+        let mut seen = BTreeSet::new();
+        let mut pending = vec![path.to_path_buf()];
+        while let Some(next) = pending.pop() {
+            if seen.insert(next.clone()) {
+                pending.extend(self.dependencies(&next).iter().map(|d| d.path.clone()));
+            }
+        }
+        seen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_parses_once() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.sqlc");
+        std::fs::write(&path, "SELECT :bind(a)").unwrap();
+
+        let store = TemplateStore::new();
+        let first = store.load(&path).unwrap();
+        let second = store.load(&path).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_store_invalidate() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.sqlc");
+        std::fs::write(&path, "SELECT 1").unwrap();
+
+        let store = TemplateStore::new();
+        store.load(&path).unwrap();
+        std::fs::write(&path, "SELECT 2").unwrap();
+        store.invalidate(&path);
+
+        let reloaded = store.load(&path).unwrap();
+        assert_eq!(reloaded.elements.len(), 1);
+        assert_eq!(
+            reloaded.elements[0],
+            crate::types::Element::Sql("SELECT 2".into())
+        );
+    }
+
+    #[test]
+    fn test_graph_closure_and_dependents() {
+        let mut graph = DependencyGraph::default();
+        let dep = |path: &str| Dependency {
+            path: PathBuf::from(path),
+            kind: DependencyKind::Compose,
+        };
+        graph.insert("a".into(), vec![dep("b")]);
+        graph.insert("b".into(), vec![dep("c")]);
+        graph.insert("c".into(), vec![]);
+        graph.insert("d".into(), vec![dep("c")]);
+
+        assert_eq!(
+            graph.closure(Path::new("a")),
+            BTreeSet::from(["a".into(), "b".into(), "c".into()])
+        );
+        assert_eq!(
+            graph.dependents(Path::new("c")),
+            vec![Path::new("b"), Path::new("d")]
+        );
+    }
+}
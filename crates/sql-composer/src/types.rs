@@ -1,10 +1,13 @@
 //! Core types for the sql-composer template AST.
 
+use std::fmt;
 use std::path::PathBuf;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use crate::dialect::SqlDialect;
+
 /// A parsed template consisting of a sequence of literal SQL and macro invocations.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -15,6 +18,36 @@ pub struct Template {
     pub source: TemplateSource,
 }
 
+/// The region of template source text an element was parsed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset one past the last character.
+    pub end: usize,
+    /// 1-based line number of `start`.
+    pub line: usize,
+    /// 1-based column (in characters) of `start`.
+    pub column: usize,
+}
+
+impl Span {
+    /// Build a span for `start..end` in `text`, computing its line and column.
+    pub fn from_offsets(text: &str, start: usize, end: usize) -> Self {
+        let before = &text[..start];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let column = before[line_start..].chars().count() + 1;
+        Self {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+}
+
 /// The origin of a template.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -25,6 +58,15 @@ pub enum TemplateSource {
     Literal(String),
 }
 
+impl fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateSource::File(path) => write!(f, "{}", path.display()),
+            TemplateSource::Literal(name) => write!(f, "<{name}>"),
+        }
+    }
+}
+
 /// A single element in a template.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -35,12 +77,26 @@ pub enum Element {
     Bind(Binding),
     /// `:compose(path)` - include another template.
     Compose(ComposeRef),
-    /// `:count(...)` or `:union(...)` - an aggregate command.
+    /// `:count(...)`, `:union(...)`, `:exists(...)` or another aggregate
+    /// command.
     Command(Command),
+    /// `:bind_rows(name (col, ...))` - a list of row placeholders.
+    BindRows(RowBinding),
+    /// `:bind_order(name, ...)` - the placeholder order under
+    /// [`PlaceholderOrdering::Explicit`]; emits no SQL.
+    BindOrder(Vec<String>),
+    /// `:ident(name ONE OF ...)` - an allowlisted identifier chosen at compose time.
+    Ident(Identifier),
+    /// `:if(name)` - opens a block emitted only when `name` has values.
+    If(String),
+    /// `:else` - starts the branch emitted when the `:if` binding has none.
+    Else,
+    /// `:endif` - closes the innermost `:if` block.
+    EndIf,
 }
 
 /// A parameter binding parsed from `:bind(name ...)`.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Binding {
     /// The name of the bind parameter.
@@ -51,6 +107,163 @@ pub struct Binding {
     pub max_values: Option<u32>,
     /// Whether this binding accepts NULL (from `NULL` keyword).
     pub nullable: bool,
+    /// Declared value type (from `AS type`), or the element type of an
+    /// array binding (from `AS ARRAY OF type`).
+    pub sql_type: Option<BindType>,
+    /// Value used when none is supplied (from `DEFAULT literal`).
+    pub default: Option<BindDefault>,
+    /// Whether the values are bound as one array parameter (from
+    /// `AS ARRAY`), on dialects that [support it](SqlDialect::supports_array_binds).
+    pub array: bool,
+}
+
+/// A value type declared on a binding with `:bind(name AS type)`.
+///
+/// Type names follow Postgres, with common aliases accepted when parsing
+/// (`bigint` for `int8`, `varchar` for `text`, ...). A `[]` suffix declares an
+/// array of the element type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BindType {
+    /// `bool`, `boolean`
+    Bool,
+    /// `int2`, `smallint`
+    Int2,
+    /// `int4`, `int`, `integer`
+    Int4,
+    /// `int8`, `bigint`
+    Int8,
+    /// `float4`, `real`
+    Float4,
+    /// `float8`, `double`
+    Float8,
+    /// `numeric`, `decimal`
+    Numeric,
+    /// `text`, `varchar`
+    Text,
+    /// `bytea`, `blob`
+    Bytea,
+    /// `date`
+    Date,
+    /// `time`
+    Time,
+    /// `timestamp`, `datetime`
+    Timestamp,
+    /// `timestamptz`
+    Timestamptz,
+    /// `uuid`
+    Uuid,
+    /// `json`
+    Json,
+    /// `jsonb`
+    Jsonb,
+    /// An array of the element type: `int8[]`.
+    Array(Box<BindType>),
+}
+
+impl BindType {
+    /// Parse a type name such as `int8`, `BIGINT` or `text[]`.
+    ///
+    /// Returns `None` for unknown names and nested arrays.
+    pub fn parse(name: &str) -> Option<Self> {
+        if let Some(element) = name.strip_suffix("[]") {
+            return match Self::parse(element)? {
+                BindType::Array(_) => None,
+                element => Some(BindType::Array(Box::new(element))),
+            };
+        }
+
+        let ty = match name.to_ascii_lowercase().as_str() {
+            "bool" | "boolean" => BindType::Bool,
+            "int2" | "smallint" => BindType::Int2,
+            "int4" | "int" | "integer" => BindType::Int4,
+            "int8" | "bigint" => BindType::Int8,
+            "float4" | "real" => BindType::Float4,
+            "float8" | "double" => BindType::Float8,
+            "numeric" | "decimal" => BindType::Numeric,
+            "text" | "varchar" => BindType::Text,
+            "bytea" | "blob" => BindType::Bytea,
+            "date" => BindType::Date,
+            "time" => BindType::Time,
+            "timestamp" | "datetime" => BindType::Timestamp,
+            "timestamptz" => BindType::Timestamptz,
+            "uuid" => BindType::Uuid,
+            "json" => BindType::Json,
+            "jsonb" => BindType::Jsonb,
+            _ => return None,
+        };
+        Some(ty)
+    }
+}
+
+impl fmt::Display for BindType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BindType::Bool => "bool",
+            BindType::Int2 => "int2",
+            BindType::Int4 => "int4",
+            BindType::Int8 => "int8",
+            BindType::Float4 => "float4",
+            BindType::Float8 => "float8",
+            BindType::Numeric => "numeric",
+            BindType::Text => "text",
+            BindType::Bytea => "bytea",
+            BindType::Date => "date",
+            BindType::Time => "time",
+            BindType::Timestamp => "timestamp",
+            BindType::Timestamptz => "timestamptz",
+            BindType::Uuid => "uuid",
+            BindType::Json => "json",
+            BindType::Jsonb => "jsonb",
+            BindType::Array(element) => return write!(f, "{element}[]"),
+        };
+        f.write_str(name)
+    }
+}
+
+/// A fallback value declared on a binding with `:bind(name DEFAULT literal)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BindDefault {
+    /// `NULL`
+    Null,
+    /// `TRUE` or `FALSE`
+    Bool(bool),
+    /// An integer or decimal number, as written: `100`, `-0.5`.
+    Number(String),
+    /// A single-quoted string, unescaped: `'it''s'` holds `it's`.
+    Text(String),
+}
+
+impl BindDefault {
+    /// The value as an SQL literal for `dialect`, with strings quoted by
+    /// [`SqlDialect::quote_literal()`].
+    pub fn to_sql(&self, dialect: Dialect) -> String {
+        self.to_sql_with(&dialect)
+    }
+
+    /// [`to_sql()`](Self::to_sql) for any [`SqlDialect`].
+    pub fn to_sql_with(&self, dialect: &dyn SqlDialect) -> String {
+        match self {
+            BindDefault::Null => "NULL".to_string(),
+            BindDefault::Bool(value) => dialect.bool_literal(*value),
+            BindDefault::Number(number) => number.clone(),
+            BindDefault::Text(text) => dialect.quote_literal(text),
+        }
+    }
+}
+
+impl fmt::Display for BindDefault {
+    /// Formats the value as written in a template.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindDefault::Null => f.write_str("NULL"),
+            BindDefault::Bool(true) => f.write_str("TRUE"),
+            BindDefault::Bool(false) => f.write_str("FALSE"),
+            BindDefault::Number(number) => f.write_str(number),
+            BindDefault::Text(text) => write!(f, "'{}'", text.replace('\'', "''")),
+        }
+    }
 }
 
 /// What a `:compose(...)` target refers to.
@@ -63,6 +276,15 @@ pub enum ComposeTarget {
     Slot(String),
 }
 
+impl fmt::Display for ComposeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComposeTarget::Path(path) => write!(f, "{}", path.display()),
+            ComposeTarget::Slot(name) => write!(f, "@{name}"),
+        }
+    }
+}
+
 /// A slot assignment: `@name = path`.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -73,6 +295,39 @@ pub struct SlotAssignment {
     pub path: PathBuf,
 }
 
+/// A row-shaped binding parsed from `:bind_rows(name (col, ...))`.
+///
+/// Its values are supplied flat, row after row, and compose to one
+/// parenthesized group of placeholders per row.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RowBinding {
+    /// The name the values are supplied under.
+    pub name: String,
+    /// The columns of each row, one value each.
+    pub columns: Vec<String>,
+}
+
+impl RowBinding {
+    /// The untyped, unconstrained binding each value is bound as.
+    pub fn binding(&self) -> Binding {
+        Binding {
+            name: self.name.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// An identifier substitution parsed from `:ident(name ONE OF a, b, ...)`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Identifier {
+    /// The name the identifier is supplied under.
+    pub name: String,
+    /// The identifiers the value may be, as written after `ONE OF`.
+    pub allowed: Vec<String>,
+}
+
 /// A compose reference parsed from `:compose(target, @slot = path, ...)`.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -83,20 +338,31 @@ pub struct ComposeRef {
     pub slots: Vec<SlotAssignment>,
 }
 
-/// An aggregate command parsed from `:count(...)` or `:union(...)`.
+/// An aggregate command parsed from `:count(...)`, `:union(...)`,
+/// `:intersect(...)`, `:except(...)`, `:exists(...)`, `:not_exists(...)`,
+/// `:json(...)` or `:paginate(...)`.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Command {
-    /// The kind of command (count or union).
+    /// The kind of command (count, a set operation, an existence test, a
+    /// JSON aggregate or a page).
     pub kind: CommandKind,
     /// Whether the DISTINCT modifier is present.
     pub distinct: bool,
     /// Whether the ALL modifier is present.
     pub all: bool,
+    /// Whether the KEYSET modifier is present, paging by key instead of by
+    /// offset.
+    pub keyset: bool,
     /// Optional column list (from `columns OF`).
     pub columns: Option<Vec<String>>,
+    /// Whether each of the `columns` sorts descending (from `column DESC`),
+    /// which only `:paginate()` accepts.
+    pub descending: Vec<bool>,
     /// Source template paths.
     pub sources: Vec<PathBuf>,
+    /// Slot assignments passed to every source, as with `:compose()`.
+    pub slots: Vec<SlotAssignment>,
 }
 
 /// The kind of aggregate command.
@@ -107,11 +373,66 @@ pub enum CommandKind {
     Count,
     /// UNION command - combines sources with UNION.
     Union,
+    /// INTERSECT command - keeps the rows every source returns.
+    Intersect,
+    /// EXCEPT command - keeps the rows of the first source that no later
+    /// source returns (`MINUS` on Oracle).
+    Except,
+    /// EXISTS command - wraps the sources in `EXISTS (...)`.
+    Exists,
+    /// NOT EXISTS command - wraps the sources in `NOT EXISTS (...)`.
+    NotExists,
+    /// JSON command - aggregates the rows into a JSON array of objects, e.g.
+    /// `SELECT json_agg(...) FROM (...)`.
+    Json,
+    /// PAGINATE command - returns one page of rows ordered by the columns,
+    /// e.g. `SELECT * FROM (...) ORDER BY id LIMIT ? OFFSET ?`.
+    Paginate,
+}
+
+/// Which text starts a template comment.
+///
+/// Template comments run to the end of the line and are stripped from
+/// composed SQL. Outside the default, a lone `#` is plain SQL, so Postgres
+/// JSONB operators (`#>`, `#>>`, `#-`) and MySQL `#` comments survive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CommentStyle {
+    /// `#` anywhere outside quoted SQL.
+    #[default]
+    Hash,
+    /// `#` as the first non-blank character of a line.
+    LineHash,
+    /// `##` anywhere outside quoted SQL.
+    DoubleHash,
+    /// `--#` anywhere outside quoted SQL.
+    DashHash,
+}
+
+/// How numbered dialects assign placeholder numbers to binding names.
+///
+/// Every occurrence of a name shares its placeholders either way; only the
+/// numbering changes. Positional dialects always number placeholders in
+/// document order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PlaceholderOrdering {
+    /// Names sorted alphabetically: `$1` is the first name A-Z.
+    #[default]
+    Alphabetical,
+    /// Names in the order they first appear in the composed template, so a
+    /// binding added later in a template doesn't renumber those before it.
+    FirstAppearance,
+    /// Names in the order of the template's `:bind_order(name, ...)`
+    /// declarations, then any others by first appearance.
+    Explicit,
 }
 
-/// Target database dialect for placeholder syntax.
+/// A built-in target database dialect. See [`SqlDialect`] for its
+/// placeholder syntax and other SQL differences.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum Dialect {
     /// PostgreSQL: `$1`, `$2`, `$3`
     Postgres,
@@ -119,23 +440,49 @@ pub enum Dialect {
     Mysql,
     /// SQLite: `?1`, `?2`, `?3`
     Sqlite,
+    /// SQL Server: `@p1`, `@p2`, `@p3`
+    SqlServer,
+    /// Oracle: `:1`, `:2`, `:3`
+    Oracle,
+    /// ClickHouse: `{id:Int64}`, `{name:String}`, `{since:Date}`
+    ClickHouse,
 }
 
+/// Forwarders to the [`SqlDialect`] implementation, so the methods can be
+/// called without importing the trait.
 impl Dialect {
-    /// Format a placeholder for the given 1-based parameter index.
+    /// See [`SqlDialect::placeholder()`].
     pub fn placeholder(&self, index: usize) -> String {
-        match self {
-            Dialect::Postgres => format!("${index}"),
-            Dialect::Mysql => "?".to_string(),
-            Dialect::Sqlite => format!("?{index}"),
-        }
+        SqlDialect::placeholder(self, index)
     }
 
-    /// Whether this dialect uses numbered placeholders ($1, ?1) vs positional (?).
-    ///
-    /// Numbered dialects (Postgres, SQLite) support alphabetical parameter ordering
-    /// and deduplication. Positional dialects (MySQL) use document-order placeholders.
+    /// See [`SqlDialect::quote_literal()`].
+    pub fn quote_literal(&self, value: &str) -> String {
+        SqlDialect::quote_literal(self, value)
+    }
+
+    /// See [`SqlDialect::quote_ident()`].
+    pub fn quote_ident(&self, name: &str) -> String {
+        SqlDialect::quote_ident(self, name)
+    }
+
+    /// See [`SqlDialect::max_bind_params()`].
+    pub fn max_bind_params(&self) -> usize {
+        SqlDialect::max_bind_params(self)
+    }
+
+    /// See [`SqlDialect::supports_array_binds()`].
+    pub fn supports_array_binds(&self) -> bool {
+        SqlDialect::supports_array_binds(self)
+    }
+
+    /// See [`SqlDialect::supports_numbered_placeholders()`].
     pub fn supports_numbered_placeholders(&self) -> bool {
-        matches!(self, Dialect::Postgres | Dialect::Sqlite)
+        SqlDialect::supports_numbered_placeholders(self)
+    }
+
+    /// See [`SqlDialect::cast()`].
+    pub fn cast(&self, placeholder: &str, ty: &BindType) -> Option<String> {
+        SqlDialect::cast(self, placeholder, ty)
     }
 }